@@ -7,12 +7,165 @@
 //! Cargo re-run the build script whenever `memory.x` is changed,
 //! updating `memory.x` ensures a rebuild of the application with the
 //! new memory settings.
+//!
+//! It also turns the BDF fonts in `src/assets/fonts` into Rust tables for
+//! `src/font.rs`, see `FONTS`.
 
 use std::env;
-use std::fs::File;
+use std::fmt::Write as _;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::PathBuf;
 
+/// Fonts to convert: the BDF file, the name of the static and whether to
+/// make a fixed width font proportional by cutting each glyph down to its
+/// ink, with a column to spare.
+const FONTS: &[(&str, &str, bool)] = &[
+    ("9x15.bdf", "PROPORTIONAL_15", true),
+    ("10x20.bdf", "PROPORTIONAL_20", true),
+];
+
+struct Glyph {
+    encoding: u32,
+    advance: u32,
+    // bitmap offset from the left of the cell and down from the top
+    left: i32,
+    top: i32,
+    width: u32,
+    rows: Vec<u32>,
+}
+
+// just what `font.rs` needs from a BDF file
+fn parse_bdf(text: &str) -> (u32, u32, u32, Vec<Glyph>) {
+    let (mut ascent, mut descent, mut default) = (0, 0, 0);
+    let mut glyphs = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let mut words = line.split_whitespace();
+        let value = |words: &mut std::str::SplitWhitespace| -> i32 {
+            words.next().unwrap().parse().unwrap()
+        };
+        match words.next() {
+            Some("FONT_ASCENT") => ascent = value(&mut words) as u32,
+            Some("FONT_DESCENT") => descent = value(&mut words) as u32,
+            Some("DEFAULT_CHAR") => default = value(&mut words) as u32,
+            Some("STARTCHAR") => {
+                let mut glyph = Glyph {
+                    encoding: 0,
+                    advance: 0,
+                    left: 0,
+                    top: 0,
+                    width: 0,
+                    rows: Vec::new(),
+                };
+                let (mut height, mut bottom) = (0, 0);
+                let mut bitmap = false;
+                for line in lines.by_ref() {
+                    let mut words = line.split_whitespace();
+                    match words.next() {
+                        Some("ENCODING") => glyph.encoding = value(&mut words) as u32,
+                        Some("DWIDTH") => glyph.advance = value(&mut words) as u32,
+                        Some("BBX") => {
+                            glyph.width = value(&mut words) as u32;
+                            height = value(&mut words);
+                            glyph.left = value(&mut words);
+                            bottom = value(&mut words);
+                        }
+                        Some("BITMAP") => bitmap = true,
+                        Some("ENDCHAR") => break,
+                        Some(hex) if bitmap => {
+                            // left aligned in whole bytes, keep the `width` left bits
+                            let bits = u32::from_str_radix(hex, 16).unwrap();
+                            let shift = hex.len() as u32 * 4 - glyph.width;
+                            glyph.rows.push(bits >> shift);
+                        }
+                        _ => {}
+                    }
+                }
+                // rows above the baseline count up from it in BDF
+                glyph.top = ascent as i32 - bottom - height;
+                glyphs.push(glyph);
+            }
+            _ => {}
+        }
+    }
+    (ascent, descent, default, glyphs)
+}
+
+// trims the blank columns off both sides
+fn make_proportional(glyph: &mut Glyph, space: u32) {
+    let ink = glyph.rows.iter().fold(0, |ink, row| ink | row);
+    if ink == 0 {
+        glyph.advance = space;
+        glyph.width = 0;
+        glyph.rows.clear();
+        return;
+    }
+    let right = ink.trailing_zeros();
+    let width = 32 - ink.leading_zeros() - right;
+    for row in glyph.rows.iter_mut() {
+        *row >>= right;
+    }
+    glyph.left = 0;
+    glyph.width = width;
+    glyph.advance = width + 1;
+}
+
+fn convert_font(file: &str, name: &str, proportional: bool, out: &mut String) {
+    let path = format!("src/assets/fonts/{}", file);
+    println!("cargo:rerun-if-changed={}", path);
+    // the misc-fixed fonts are latin-1 throughout, only comments are not ascii
+    let bytes = fs::read(&path).unwrap();
+    let text: String = bytes.iter().map(|byte| *byte as char).collect();
+    let (ascent, descent, default, mut glyphs) = parse_bdf(&text);
+    glyphs.retain(|glyph| glyph.encoding == default || char::from_u32(glyph.encoding).is_some());
+    glyphs.sort_by_key(|glyph| glyph.encoding);
+
+    let space = glyphs
+        .iter()
+        .find(|glyph| glyph.encoding == ' ' as u32)
+        .map_or(4, |glyph| glyph.advance / 2);
+    let mut bitmaps: Vec<u8> = Vec::new();
+    let mut table = String::new();
+    let mut fallback = 0;
+    for (i, glyph) in glyphs.iter_mut().enumerate() {
+        if proportional && glyph.encoding != default {
+            make_proportional(glyph, space);
+        }
+        if glyph.encoding == default {
+            fallback = i;
+        }
+        // rows packed into whole bytes, first pixel in the top bit
+        let stride = glyph.width.div_ceil(8);
+        let offset = bitmaps.len();
+        for row in &glyph.rows {
+            let bits = row << (stride * 8 - glyph.width);
+            for byte in (0..stride).rev() {
+                bitmaps.push((bits >> (byte * 8)) as u8);
+            }
+        }
+        writeln!(
+            table,
+            "    Glyph {{ c: '\\u{{{:x}}}', advance: {}, left: {}, top: {}, width: {}, height: {}, offset: {} }},",
+            glyph.encoding,
+            glyph.advance,
+            glyph.left,
+            glyph.top,
+            glyph.width,
+            glyph.rows.len(),
+            offset
+        )
+        .unwrap();
+    }
+
+    writeln!(
+        out,
+        "/// Converted from `{}`.\npub static {}: Font = Font {{\n    ascent: {},\n    descent: {},\n    fallback: {},\n    glyphs: &[\n{}    ],\n    bitmaps: &{:?},\n}};\n",
+        file, name, ascent, descent, fallback, table, bitmaps
+    )
+    .unwrap();
+}
+
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
     // on the linker search path.
@@ -28,4 +181,10 @@ fn main() {
     // here, we ensure the build script is only re-run when
     // `memory.x` is changed.
     println!("cargo:rerun-if-changed=memory.x");
+
+    let mut fonts = String::new();
+    for (file, name, proportional) in FONTS {
+        convert_font(file, name, *proportional, &mut fonts);
+    }
+    fs::write(out.join("fonts.rs"), fonts).unwrap();
 }
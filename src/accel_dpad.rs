@@ -1,61 +1,88 @@
 use accelerometer::Accelerometer;
-use cortex_m::delay::Delay;
 use embedded_graphics::{
-    Drawable,
     mono_font::{MonoTextStyleBuilder, ascii::FONT_6X9},
     pixelcolor::{Rgb555, Rgb565, Rgb888},
     prelude::{DrawTarget, Point, RgbColor, WebColors},
     text::{Alignment, Text},
 };
-use embedded_hal::{delay::DelayNs, digital::InputPin};
-use embedded_hal_compat::Forward;
+use embedded_hal::delay::DelayNs;
 use lis3dh::{Lis3dh, Lis3dhCore};
-use rp2040_hal::gpio::{
-    FunctionSio, Pin, PullDown, SioInput,
-    bank0::{Gpio10, Gpio11, Gpio22, Gpio23, Gpio24, Gpio25},
-};
 use tinybmp::Bmp;
 
 use crate::{
-    RUST_PRIDE, TFT_DISPLAY_HEIGHT,
-    bmp::BmpExt,
+    CONTENT_HEIGHT, RUST_PRIDE,
+    backlight::{self, Panel},
+    bmp::{self, BmpExt},
+    console,
+    draw::{Draw, Michelangelo},
+    error::BadgeError,
+    input::Buttons,
     log::{log_accel, log_dpad},
+    power::Delay,
+    settings,
     sprite::SpriteBuilder,
+    status::StatusBar,
+    supervisor::Faults,
+    theme::Theme,
 };
 
-#[allow(clippy::too_many_arguments)]
+// log the accelerometer every this many frames, the console would be
+// nothing but samples otherwise
+const ACCEL_LOG_EVERY: u32 = 50;
+
 pub fn accel_dpad<D, C, L>(
     display: &mut D,
-    delay: &mut Forward<Delay>,
+    delay: &mut Delay,
     lis3dh: &mut Lis3dh<L>,
-    a_btn_pin: &mut Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
-    b_btn_pin: &mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
-    down_btn_pin: &mut Pin<Gpio23, FunctionSio<SioInput>, PullDown>,
-    up_btn_pin: &mut Pin<Gpio24, FunctionSio<SioInput>, PullDown>,
-    left_btn_pin: &mut Pin<Gpio25, FunctionSio<SioInput>, PullDown>,
-    right_btn_pin: &mut Pin<Gpio22, FunctionSio<SioInput>, PullDown>,
-) where
+    buttons: &mut Buttons,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
     C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
-    D: DrawTarget<Color = C>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
     D::Error: core::fmt::Debug,
     L: Lis3dhCore,
     L::PinError: core::fmt::Debug,
     L::BusError: core::fmt::Debug,
 {
-    display.clear(C::BLACK).unwrap();
+    display
+        .clear(theme.background)
+        .map_err(BadgeError::display)?;
 
-    let rust_logo_bmp: Bmp<C> = Bmp::from_slice(RUST_PRIDE).unwrap();
+    let rust_logo_bmp: Bmp<C> = bmp::load(RUST_PRIDE)?;
     let mut rust_logo_position = rust_logo_bmp.screen_center();
     let mut rust_logo = SpriteBuilder::builder(&rust_logo_bmp)
         .with_position(rust_logo_position)
         .with_screen_boundaries()
         .build();
 
+    let mut painter = Draw::new(theme.background);
     let mut draw = true;
     let mut dpad = true;
+    let mut frame: u32 = 0;
+    let mut faults = Faults::new(BadgeError::Accelerometer);
+    let tilt = settings::current().tilt as f32;
 
     loop {
-        Text::with_alignment(
+        let input = buttons.poll();
+        frame = frame.wrapping_add(1);
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            display
+                .clear(theme.background)
+                .map_err(BadgeError::display)?;
+            // the overlay painted over everything, nothing left to clean up
+            painter = Draw::new(theme.background);
+            rust_logo.draw(display, 0.0).map_err(BadgeError::display)?;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, lis3dh)?;
+        }
+        display.update_status()?;
+
+        let mode = Text::with_alignment(
             if dpad {
                 "mode: D-PAD - press A to toggle"
             } else {
@@ -63,67 +90,69 @@ pub fn accel_dpad<D, C, L>(
             },
             Point::new(
                 display.bounding_box().center().x,
-                (TFT_DISPLAY_HEIGHT - 10) as i32,
+                (CONTENT_HEIGHT - 10) as i32,
             ),
             MonoTextStyleBuilder::new()
                 .font(&FONT_6X9)
-                .text_color(C::WHITE)
-                .background_color(C::BLACK)
+                .text_color(theme.foreground)
+                .background_color(theme.background)
                 .build(),
             Alignment::Center,
-        )
-        .draw(display)
-        .unwrap();
+        );
+        painter.draw(display, &mode)?;
 
         if !dpad {
-            let accel = lis3dh.accel_norm().unwrap();
-
-            log_accel(display, &accel);
+            if let Some(accel) = faults.check(lis3dh.accel_norm())? {
+                backlight::motion(&accel);
+                if frame.is_multiple_of(ACCEL_LOG_EVERY) {
+                    log_accel(&accel);
+                }
 
-            rust_logo_position.x -= (accel.x * 10.0) as i32;
-            rust_logo_position.y -= (accel.y * 10.0) as i32;
-            draw = true;
+                rust_logo_position.x -= (accel.x * tilt) as i32;
+                rust_logo_position.y -= (accel.y * tilt) as i32;
+                draw = true;
+            }
         } else {
-            let left_is_low = left_btn_pin.is_low().unwrap();
-            let right_is_low = right_btn_pin.is_low().unwrap();
-            let up_is_low = up_btn_pin.is_low().unwrap();
-            let down_is_low = down_btn_pin.is_low().unwrap();
+            let held = input.held;
 
-            if right_is_low {
+            if held.right {
                 rust_logo_position.x += 1;
                 draw = true;
             }
-            if left_is_low {
+            if held.left {
                 rust_logo_position.x -= 1;
                 draw = true;
             }
-            if up_is_low {
+            if held.up {
                 rust_logo_position.y -= 1;
                 draw = true;
             }
-            if down_is_low {
+            if held.down {
                 rust_logo_position.y += 1;
                 draw = true;
             }
 
-            log_dpad(display, (left_is_low, right_is_low, up_is_low, down_is_low));
+            if input.pressed != Default::default() || input.released != Default::default() {
+                log_dpad(&held);
+            }
         }
 
         if draw {
-            rust_logo.move_to(display, &mut rust_logo_position, C::BLACK, 0.0);
+            rust_logo
+                .move_to(display, &mut rust_logo_position, theme.background, 0.0)
+                .map_err(BadgeError::display)?;
             draw = false;
             delay.delay_ms(1);
         }
 
-        if a_btn_pin.is_low().unwrap() {
+        if input.pressed.a {
             dpad = !dpad;
-            display.clear(C::BLACK).unwrap();
+            painter.clear(display)?;
             draw = true;
-            delay.delay_ms(200);
         }
 
-        if b_btn_pin.is_low().unwrap() {
-            break;
+        if input.released.b {
+            return Ok(());
         }
     }
 }
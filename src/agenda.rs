@@ -0,0 +1,536 @@
+use core::{cell::RefCell, fmt::Write, write};
+
+use critical_section::Mutex;
+use embedded_graphics::{
+    Drawable,
+    mono_font::{
+        MonoTextStyleBuilder,
+        ascii::{FONT_6X10, FONT_9X15_BOLD},
+    },
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, RgbColor, WebColors},
+    text::{Alignment, Baseline, Text, TextStyle, TextStyleBuilder},
+};
+use embedded_hal::delay::DelayNs;
+use heapless::{String, Vec};
+
+use crate::{
+    CONTENT_HEIGHT, SCHEDULE,
+    backlight::{self, Panel, Tap},
+    clip::Clipped,
+    console,
+    error::BadgeError,
+    input::Buttons,
+    log::{log_error, log_info, log_warn},
+    power::Delay,
+    record,
+    rtc::{self, DateTime},
+    schedule::{Date, MAX_DAYS, MAX_TRACKS, Minutes, Schedule, Session},
+    settings,
+    status::StatusBar,
+    storage::{self, SCHEDULE_LEN},
+    theme::Theme,
+};
+
+// sessions shown for one day, more are cut off
+const MAX_SESSIONS: usize = 48;
+const ROW_HEIGHT: i32 = 18;
+const LIST_TOP: i32 = 24;
+const ROWS: usize = 9;
+const DETAIL_Y: i32 = 194;
+// the last line above the status bar
+const TITLE_Y: i32 = CONTENT_HEIGHT as i32 - FONT_9X15_BOLD.character_size.height as i32 - 1;
+// 9x15 and 6x10 characters across the screen
+const CHARS: usize = 35;
+const DETAIL_CHARS: usize = 53;
+// the longest line in bytes, characters take up to four
+const LINE_LEN: usize = 4 * DETAIL_CHARS;
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+struct Upload {
+    data: Vec<u8, SCHEDULE_LEN>,
+    // checked and waiting for the foreground to write it
+    ready: bool,
+}
+
+static UPLOAD: Mutex<RefCell<Option<Upload>>> = Mutex::new(RefCell::new(None));
+
+/// The uploaded schedule, or the one built in when there is none (or it does
+/// not parse any more).
+pub fn load() -> Result<Schedule<'static>, BadgeError> {
+    let stored = storage::SCHEDULE.read();
+    if stored[0] != 0xff {
+        match Schedule::parse(stored) {
+            Ok(schedule) => return Ok(schedule),
+            Err(error) => log_warn!(
+                "schedule: line {}: {}",
+                error.line,
+                error.kind.description()
+            ),
+        }
+    }
+    Schedule::parse(SCHEDULE).map_err(BadgeError::asset)
+}
+
+/// Identifies a schedule, so stars are not carried over to a different one.
+fn id(schedule: &Schedule) -> u16 {
+    let mut sum = 0u16;
+    for session in schedule.sessions() {
+        sum = sum
+            .wrapping_mul(31)
+            .wrapping_add(record::checksum(session.title.as_bytes()));
+    }
+    sum
+}
+
+pub fn starred(schedule: &Schedule) -> u64 {
+    let settings = settings::load();
+    if settings.schedule_id == id(schedule) {
+        settings.starred
+    } else {
+        0
+    }
+}
+
+fn is_starred(starred: u64, session: &Session) -> bool {
+    session.index < 64 && starred & (1 << session.index) != 0
+}
+
+pub fn to_datetime(date: Date, minutes: Minutes) -> DateTime {
+    DateTime {
+        year: date.year,
+        month: date.month,
+        day: date.day,
+        hour: (minutes / 60) as u8,
+        minute: (minutes % 60) as u8,
+        second: 0,
+    }
+}
+
+/// The first session starting after `now`, in local seconds.
+pub fn next<'a>(schedule: &Schedule<'a>, now: i64) -> Option<(i64, Session<'a>)> {
+    let days: Vec<Date, MAX_DAYS> = schedule.days().collect();
+    schedule
+        .sessions()
+        .map(|session| {
+            let start = to_datetime(days[session.day], session.start).seconds();
+            (start, session)
+        })
+        .filter(|(start, _)| *start > now)
+        .min_by_key(|(start, session)| (*start, session.index))
+}
+
+/// Starts taking the lines that follow as a new schedule, from the shell.
+pub fn begin_upload() {
+    critical_section::with(|cs| {
+        UPLOAD.borrow(cs).replace(Some(Upload {
+            data: Vec::new(),
+            ready: false,
+        }));
+    });
+}
+
+/// Takes a line from the shell while an upload is going on, a lone `.` ends
+/// it. Returns whether the line was taken, with what to answer.
+pub fn receive(line: &str, reply: impl Fn(core::fmt::Arguments)) -> bool {
+    critical_section::with(|cs| {
+        let mut upload = UPLOAD.borrow(cs).borrow_mut();
+        let Some(current) = upload.as_mut().filter(|upload| !upload.ready) else {
+            return false;
+        };
+
+        if line.trim() != "." {
+            if current.data.extend_from_slice(line.as_bytes()).is_err()
+                || current.data.push(b'\n').is_err()
+            {
+                reply(format_args!("schedule too large, upload dropped"));
+                *upload = None;
+            }
+            return true;
+        }
+
+        match Schedule::parse(&current.data) {
+            Ok(schedule) => {
+                reply(format_args!(
+                    "schedule ok, {} sessions",
+                    schedule.sessions().count()
+                ));
+                current.ready = true;
+            }
+            Err(error) => {
+                reply(format_args!(
+                    "line {}: {}, upload dropped",
+                    error.line,
+                    error.kind.description()
+                ));
+                *upload = None;
+            }
+        }
+        true
+    })
+}
+
+/// Drops an upload that is going on, returns whether there was one.
+pub fn cancel_upload() -> bool {
+    critical_section::with(|cs| {
+        UPLOAD
+            .borrow(cs)
+            .borrow_mut()
+            .take_if(|upload| !upload.ready)
+            .is_some()
+    })
+}
+
+/// Goes back to the built-in schedule.
+pub fn clear() {
+    critical_section::with(|cs| {
+        UPLOAD.borrow(cs).replace(Some(Upload {
+            data: Vec::new(),
+            ready: true,
+        }));
+    });
+}
+
+/// Writes a finished upload to flash. Called by `Buttons::poll`, flash is not
+/// written from the usb interrupt.
+pub fn store() {
+    // taken out, the usb interrupt keeps running while flash is written
+    let Some(upload) = critical_section::with(|cs| {
+        UPLOAD
+            .borrow(cs)
+            .borrow_mut()
+            .take_if(|upload| upload.ready)
+    }) else {
+        return;
+    };
+
+    match storage::SCHEDULE.write(&upload.data) {
+        Ok(()) if upload.data.is_empty() => log_info!("schedule: back to the built-in one"),
+        Ok(()) => log_info!("schedule: stored {} bytes", upload.data.len()),
+        Err(error) => log_error!("schedule: {:?}", error),
+    }
+}
+
+/// Where a session is at, for its color.
+#[derive(Clone, Copy, PartialEq)]
+enum Status {
+    Now,
+    Next,
+    Later,
+}
+
+fn day_sessions<'a>(schedule: &Schedule<'a>, day: usize) -> Vec<Session<'a>, MAX_SESSIONS> {
+    let mut sessions: Vec<Session, MAX_SESSIONS> = schedule
+        .sessions()
+        .filter(|session| session.day == day)
+        .take(MAX_SESSIONS)
+        .collect();
+    sessions.sort_unstable_by_key(|session| (session.start, session.index));
+    sessions
+}
+
+// running sessions, and on every track the one after
+fn status(sessions: &[Session], session: &Session, now: Option<Minutes>) -> Status {
+    let Some(now) = now else {
+        return Status::Later;
+    };
+    if session.is_running(now) {
+        return Status::Now;
+    }
+    let next = sessions
+        .iter()
+        .filter(|other| other.track == session.track && other.start > now)
+        .map(|other| other.start)
+        .min();
+    if next == Some(session.start) {
+        Status::Next
+    } else {
+        Status::Later
+    }
+}
+
+// every line is padded to paint over a longer one drawn before
+fn padded(text: &str, width: usize) -> String<LINE_LEN> {
+    let mut line = String::new();
+    let _ = write!(&mut line, "{:<w$}", text, w = width);
+    line
+}
+
+fn centered() -> TextStyle {
+    TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Top)
+        .build()
+}
+
+struct View<'a> {
+    schedule: Schedule<'a>,
+    days: Vec<Date, MAX_DAYS>,
+    tracks: Vec<&'a str, MAX_TRACKS>,
+    day: usize,
+    sessions: Vec<Session<'a>, MAX_SESSIONS>,
+    selected: usize,
+    scroll: usize,
+    starred: u64,
+}
+
+impl View<'_> {
+    fn set_day(&mut self, day: usize) {
+        self.day = day;
+        self.sessions = day_sessions(&self.schedule, day);
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    // minutes into the day shown, when that day is today
+    fn now(&self) -> Option<Minutes> {
+        let now = rtc::now()?;
+        let date = self.days[self.day];
+        (now.year == date.year && now.month == date.month && now.day == date.day)
+            .then_some(now.hour as Minutes * 60 + now.minute as Minutes)
+    }
+
+    fn draw<D, C>(&self, display: &mut D, theme: &Theme<C>) -> Result<(), D::Error>
+    where
+        C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+        D: DrawTarget<Color = C>,
+    {
+        let style = |text, background| {
+            MonoTextStyleBuilder::new()
+                .font(&FONT_9X15_BOLD)
+                .text_color(text)
+                .background_color(background)
+                .build()
+        };
+
+        let date = self.days[self.day];
+        let weekday = to_datetime(date, 0).weekday();
+        let mut header: String<64> = String::new();
+        let _ = write!(
+            &mut header,
+            "< {} {}-{:02}-{:02}  {}/{} >",
+            WEEKDAYS[weekday as usize],
+            date.year,
+            date.month,
+            date.day,
+            self.day + 1,
+            self.days.len()
+        );
+        let mut line: String<64> = String::new();
+        let _ = write!(&mut line, "{:^w$}", header, w = CHARS);
+        Text::with_text_style(
+            &line,
+            Point::new(160, 2),
+            style(theme.accent, theme.background),
+            centered(),
+        )
+        .draw(display)?;
+
+        let now = self.now();
+        for row in 0..ROWS {
+            let position = Point::new(0, LIST_TOP + row as i32 * ROW_HEIGHT);
+
+            let Some(session) = self.sessions.get(self.scroll + row) else {
+                let empty = if row == 0 && self.sessions.is_empty() {
+                    "no sessions"
+                } else {
+                    ""
+                };
+                Text::with_baseline(
+                    &padded(empty, CHARS),
+                    position,
+                    style(theme.foreground, theme.background),
+                    Baseline::Top,
+                )
+                .draw(display)?;
+                continue;
+            };
+
+            let star = is_starred(self.starred, session);
+            let mut text: String<LINE_LEN> = String::new();
+            let track = self.tracks.get(session.track).copied().unwrap_or("");
+            let _ = write!(
+                Clipped::new(&mut text, CHARS),
+                "{}{:02}:{:02} {:<5.5} {}",
+                if star { '*' } else { ' ' },
+                session.start / 60,
+                session.start % 60,
+                track,
+                session.title
+            );
+
+            let color = match status(&self.sessions, session, now) {
+                Status::Now => theme.accent,
+                Status::Next => theme.foreground,
+                Status::Later if star => theme.foreground,
+                Status::Later => theme.muted,
+            };
+            let row_style = if self.scroll + row == self.selected {
+                style(theme.background, color)
+            } else {
+                style(color, theme.background)
+            };
+            Text::with_baseline(&padded(&text, CHARS), position, row_style, Baseline::Top)
+                .draw(display)?;
+        }
+
+        // the selected session in full underneath
+        let mut detail: String<LINE_LEN> = String::new();
+        let mut title: String<LINE_LEN> = String::new();
+        if let Some(session) = self.sessions.get(self.selected) {
+            let _ = write!(
+                Clipped::new(&mut detail, DETAIL_CHARS),
+                "{:02}:{:02}-{:02}:{:02} {} {}",
+                session.start / 60,
+                session.start % 60,
+                session.end / 60,
+                session.end % 60,
+                self.tracks.get(session.track).copied().unwrap_or(""),
+                session.speaker.unwrap_or("")
+            );
+            let _ = write!(Clipped::new(&mut title, CHARS), "{}", session.title);
+        }
+        Text::with_baseline(
+            &padded(&detail, DETAIL_CHARS),
+            Point::new(1, DETAIL_Y),
+            MonoTextStyleBuilder::new()
+                .font(&FONT_6X10)
+                .text_color(theme.muted)
+                .background_color(theme.background)
+                .build(),
+            Baseline::Top,
+        )
+        .draw(display)?;
+        Text::with_baseline(
+            &padded(&title, CHARS),
+            Point::new(1, TITLE_Y),
+            style(theme.foreground, theme.background),
+            Baseline::Top,
+        )
+        .draw(display)?;
+
+        Ok(())
+    }
+}
+
+/// The conference schedule, a day at a time. Left/Right change the day,
+/// Up/Down pick a session, A stars it and B leaves. Running talks are in the
+/// theme's accent, the next one on each track and starred ones in the text
+/// color and the rest dimmed.
+pub fn agenda<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+{
+    let schedule = load()?;
+    let starred = starred(&schedule);
+    let days: Vec<Date, MAX_DAYS> = schedule.days().collect();
+    let today = rtc::now().and_then(|now| {
+        days.iter()
+            .position(|date| (date.year, date.month, date.day) == (now.year, now.month, now.day))
+    });
+
+    let mut view = View {
+        schedule,
+        days,
+        tracks: schedule.tracks().collect(),
+        day: 0,
+        sessions: Vec::new(),
+        selected: 0,
+        scroll: 0,
+        starred,
+    };
+    view.set_day(today.unwrap_or(0));
+    // start at what is on now
+    if let Some(now) = view.now() {
+        view.selected = view
+            .sessions
+            .iter()
+            .position(|session| session.end > now)
+            .unwrap_or(0);
+        view.scroll = view.selected.min(view.sessions.len().saturating_sub(ROWS));
+    }
+
+    display
+        .clear(theme.background)
+        .map_err(BadgeError::display)?;
+    view.draw(display, theme).map_err(BadgeError::display)?;
+    let mut minute = rtc::seconds().map(|seconds| seconds / 60);
+
+    loop {
+        let input = buttons.poll();
+        let mut changed = false;
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            display
+                .clear(theme.background)
+                .map_err(BadgeError::display)?;
+            changed = true;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, tap)?;
+        }
+        display.update_status()?;
+
+        if input.pressed.right && view.day + 1 < view.days.len() {
+            view.set_day(view.day + 1);
+            changed = true;
+        }
+        if input.pressed.left && view.day > 0 {
+            view.set_day(view.day - 1);
+            changed = true;
+        }
+        if input.held.down && view.selected + 1 < view.sessions.len() {
+            view.selected += 1;
+            changed = true;
+        }
+        if input.held.up && view.selected > 0 {
+            view.selected -= 1;
+            changed = true;
+        }
+        view.scroll = view
+            .scroll
+            .clamp(view.selected.saturating_sub(ROWS - 1), view.selected);
+
+        if input.pressed.a
+            && let Some(session) = view.sessions.get(view.selected)
+            && session.index < 64
+        {
+            view.starred ^= 1 << session.index;
+            changed = true;
+        }
+
+        // now and next move along with the clock
+        let now_minute = rtc::seconds().map(|seconds| seconds / 60);
+        if now_minute != minute {
+            minute = now_minute;
+            changed = true;
+        }
+
+        if changed {
+            view.draw(display, theme).map_err(BadgeError::display)?;
+        }
+
+        if input.released.b {
+            let mut settings = settings::load();
+            settings.starred = view.starred;
+            settings.schedule_id = id(&view.schedule);
+            return settings::save(&settings);
+        }
+
+        delay.delay_ms(if changed && (input.held.up || input.held.down) {
+            120
+        } else {
+            10
+        });
+    }
+}
@@ -0,0 +1,310 @@
+use core::cell::RefCell;
+
+use accelerometer::vector::F32x3;
+use critical_section::Mutex;
+use embedded_hal::{delay::DelayNs, digital::OutputPin, pwm::SetDutyCycle};
+use lis3dh::{Lis3dh, Lis3dhCore};
+use mipidsi::{
+    Display,
+    interface::{Interface, InterfacePixelFormat},
+    models::Model,
+};
+use rp2040_hal::{
+    Timer,
+    fugit::Instant,
+    gpio::{FunctionNull, FunctionPwm, Pin, PullDown, bank0::Gpio12},
+    pwm::{FreeRunning, Pwm6, Slice},
+};
+
+use crate::{
+    error::BadgeError,
+    input::Buttons,
+    leds,
+    log::log_warn,
+    power::{self, Delay},
+    record::{BACKLIGHT_MIN, Settings},
+    rtc, usb_serial,
+};
+
+// dimmed is this fraction of the chosen level
+const DIM_DIVISOR: u8 = 4;
+// ceiling while the battery is low
+const LOW_BATTERY_LEVEL: u8 = 64;
+// change in g on any axis that counts as the badge being moved
+const MOTION_THRESHOLD: f32 = 0.15;
+const SLEEP_POLL_MS: u32 = 20;
+// asleep this long, the chip goes dormant and only the buttons wake it
+const DORMANT_AFTER_MS: u32 = 5 * 60 * 1000;
+// one led frame, for the leds to go dark before the clocks stop
+const LED_FRAME_MS: u32 = 20;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Awake,
+    Dimmed,
+    Asleep,
+}
+
+struct Backlight {
+    pwm: Slice<Pwm6, FreeRunning>,
+    _pin: Pin<Gpio12, FunctionPwm, PullDown>,
+    timer: Timer,
+    level: u8,
+    // zero never dims or sleeps
+    dim_after_s: u16,
+    sleep_after_s: u16,
+    state: State,
+    last_activity: Instant<u64, 1, 1_000_000>,
+    last_accel: Option<F32x3>,
+    low_battery: bool,
+    tap_wake: bool,
+}
+
+impl Backlight {
+    fn apply(&mut self) {
+        let level = match self.state {
+            State::Awake => self.level,
+            State::Dimmed => (self.level / DIM_DIVISOR).max(1),
+            State::Asleep => 0,
+        };
+        let level = if self.low_battery {
+            level.min(LOW_BATTERY_LEVEL)
+        } else {
+            level
+        };
+        // the eye is closer to quadratic than linear
+        let duty = (level as u32 * level as u32 * u16::MAX as u32 / (255 * 255)) as u16;
+        let _ = self.pwm.channel_a.set_duty_cycle(duty);
+    }
+
+    fn idle_s(&self) -> u64 {
+        (self.timer.get_counter() - self.last_activity).to_secs()
+    }
+}
+
+static BACKLIGHT: Mutex<RefCell<Option<Backlight>>> = Mutex::new(RefCell::new(None));
+
+/// Drives the backlight on GPIO12 from pwm slice 6 and switches it on at the
+/// saved level.
+pub fn init(
+    mut pwm: Slice<Pwm6, FreeRunning>,
+    pin: Pin<Gpio12, FunctionNull, PullDown>,
+    timer: Timer,
+    settings: &Settings,
+) {
+    pwm.set_ph_correct();
+    pwm.enable();
+    let pin = pwm.channel_a.output_to(pin);
+
+    let mut backlight = Backlight {
+        pwm,
+        _pin: pin,
+        timer,
+        level: settings.backlight.max(BACKLIGHT_MIN),
+        dim_after_s: settings.dim_after_s,
+        sleep_after_s: settings.sleep_after_s,
+        state: State::Awake,
+        last_activity: timer.get_counter(),
+        last_accel: None,
+        low_battery: false,
+        tap_wake: settings.tap_wake,
+    };
+    backlight.apply();
+
+    critical_section::with(|cs| {
+        BACKLIGHT.borrow(cs).replace(Some(backlight));
+    });
+}
+
+fn with_backlight<R>(f: impl FnOnce(&mut Backlight) -> R) -> Option<R> {
+    critical_section::with(|cs| BACKLIGHT.borrow(cs).borrow_mut().as_mut().map(f))
+}
+
+/// Changes the brightness, shown right away unless the badge is asleep.
+pub fn set_level(level: u8) {
+    with_backlight(|backlight| {
+        backlight.level = level.max(BACKLIGHT_MIN);
+        backlight.apply();
+    });
+}
+
+/// Holds the backlight below the chosen level to save the battery.
+pub fn set_low_battery(low_battery: bool) {
+    with_backlight(|backlight| {
+        backlight.low_battery = low_battery;
+        backlight.apply();
+    });
+}
+
+pub fn set_timeouts(dim_after_s: u16, sleep_after_s: u16) {
+    with_backlight(|backlight| {
+        backlight.dim_after_s = dim_after_s;
+        backlight.sleep_after_s = sleep_after_s;
+    });
+}
+
+/// Whether a tap wakes the badge, or only the buttons do.
+pub fn set_tap_wake(tap_wake: bool) {
+    with_backlight(|backlight| backlight.tap_wake = tap_wake);
+}
+
+/// The user did something, restarts the timeouts and undoes dimming.
+pub fn activity() {
+    with_backlight(|backlight| {
+        backlight.last_activity = backlight.timer.get_counter();
+        if backlight.state == State::Dimmed {
+            backlight.state = State::Awake;
+            backlight.apply();
+        }
+    });
+}
+
+/// Counts moving the badge around as activity, for the apps that read the
+/// accelerometer anyway.
+pub fn motion(accel: &F32x3) {
+    let moved = with_backlight(|backlight| {
+        let moved = backlight.last_accel.is_some_and(|last| {
+            (accel.x - last.x).abs() > MOTION_THRESHOLD
+                || (accel.y - last.y).abs() > MOTION_THRESHOLD
+                || (accel.z - last.z).abs() > MOTION_THRESHOLD
+        });
+        backlight.last_accel = Some(*accel);
+        moved
+    });
+
+    if moved == Some(true) {
+        activity();
+    }
+}
+
+/// Dims once the dim timeout ran out, called by `Buttons::poll`. True when
+/// the sleep timeout ran out as well and the app should call `sleep`.
+pub fn update() -> bool {
+    with_backlight(|backlight| {
+        if backlight.state == State::Asleep {
+            return false;
+        }
+
+        let idle_s = backlight.idle_s();
+        if backlight.state == State::Awake
+            && backlight.dim_after_s != 0
+            && idle_s >= backlight.dim_after_s as u64
+        {
+            backlight.state = State::Dimmed;
+            backlight.apply();
+        }
+
+        backlight.sleep_after_s != 0 && idle_s >= backlight.sleep_after_s as u64
+    })
+    .unwrap_or(false)
+}
+
+/// A display that can be put to sleep, keeping what is on it.
+pub trait Panel {
+    fn sleep(&mut self, delay: &mut Delay) -> Result<(), BadgeError>;
+    fn wake(&mut self, delay: &mut Delay) -> Result<(), BadgeError>;
+}
+
+impl<DI, M, RST> Panel for Display<DI, M, RST>
+where
+    DI: Interface,
+    M: Model,
+    M::ColorFormat: InterfacePixelFormat<DI::Word>,
+    RST: OutputPin,
+{
+    fn sleep(&mut self, delay: &mut Delay) -> Result<(), BadgeError> {
+        Display::sleep(self, delay).map_err(BadgeError::display)
+    }
+
+    fn wake(&mut self, delay: &mut Delay) -> Result<(), BadgeError> {
+        Display::wake(self, delay).map_err(BadgeError::display)
+    }
+}
+
+/// Something that notices the badge being tapped.
+pub trait Tap {
+    fn tapped(&mut self) -> bool;
+}
+
+/// For the apps without an accelerometer at hand, only buttons wake them.
+impl Tap for () {
+    fn tapped(&mut self) -> bool {
+        false
+    }
+}
+
+impl<T: Tap> Tap for Option<T> {
+    fn tapped(&mut self) -> bool {
+        self.as_mut().is_some_and(Tap::tapped)
+    }
+}
+
+impl<L: Lis3dhCore> Tap for Lis3dh<L> {
+    fn tapped(&mut self) -> bool {
+        // reading the click source also clears the latched click
+        matches!(self.click_count(), Ok(count) if count > 0)
+    }
+}
+
+/// Switches the backlight and the panel off until a button or, unless that
+/// is turned off, a tap. The panel keeps its picture, so the app carries on
+/// without a redraw, and the button that woke the badge is not passed on to
+/// it.
+pub fn sleep<P>(
+    panel: &mut P,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+) -> Result<(), BadgeError>
+where
+    P: Panel,
+{
+    with_backlight(|backlight| {
+        backlight.state = State::Asleep;
+        backlight.apply();
+    });
+    leds::suspend();
+    panel.sleep(delay)?;
+
+    // a tap from before going to sleep does not count
+    tap.tapped();
+    let tap_wake = with_backlight(|backlight| backlight.tap_wake).unwrap_or(true);
+
+    let mut asleep_ms = 0;
+    loop {
+        let input = buttons.poll();
+
+        // read either way, a tap stays latched until it is
+        let tapped = tap.tapped();
+        if input.held != Default::default() || (tap_wake && tapped) {
+            break;
+        }
+
+        // usb would drop off the bus with its clock gone
+        if asleep_ms >= DORMANT_AFTER_MS && !usb_serial::is_connected() {
+            delay.delay_ms(LED_FRAME_MS);
+            power::dormant(buttons);
+            // the timer stood still with the clocks, so the wall time is off
+            // by however long that was
+            if rtc::seconds().is_some() {
+                log_warn!("clock stopped while dormant, set the time again");
+                rtc::invalidate();
+            }
+            continue;
+        }
+
+        delay.delay_ms(SLEEP_POLL_MS);
+        asleep_ms += SLEEP_POLL_MS;
+    }
+
+    buttons.swallow();
+    panel.wake(delay)?;
+    leds::resume();
+    with_backlight(|backlight| {
+        backlight.state = State::Awake;
+        backlight.apply();
+    });
+    activity();
+
+    Ok(())
+}
@@ -0,0 +1,368 @@
+use core::fmt::Write;
+
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, RgbColor, Size, WebColors},
+    primitives::Rectangle,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+use embedded_hal::delay::DelayNs;
+use heapless::String;
+
+use crate::{
+    CONTENT_HEIGHT, TFT_DISPLAY_WIDTH,
+    backlight::{self, Panel, Tap},
+    console,
+    contact::{Contact, Field},
+    draw::{Draw, Michelangelo},
+    error::BadgeError,
+    font::{FontStyle, PROPORTIONAL_20},
+    input::Buttons,
+    keyboard::keyboard,
+    large_text::LargeText,
+    power::Delay,
+    qr::{Ecc, QrCode},
+    record::{QrContent, Settings},
+    settings,
+    status::StatusBar,
+    theme::Theme,
+};
+
+const NAME_Y: i32 = 14;
+const LABEL_Y: i32 = 34;
+const QR_TOP: i32 = 44;
+
+// the contact page
+const ROWS_TOP: i32 = 48;
+const ROW_HEIGHT: i32 = 28;
+const VALUE_X: i32 = 80;
+
+// the name page, the name scrolls when it would be smaller than
+// `NAME_MIN_SCALE` times its font
+const NAME_AREA: Rectangle = Rectangle::new(Point::new(0, 16), Size::new(320, 144));
+const NAME_MIN_SCALE: u32 = 3;
+const MARQUEE_STEP: u32 = 3;
+const COMPANY_Y: i32 = 184;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Page {
+    /// as large as it gets
+    Name,
+    Code,
+    /// with the field that A edits
+    Contact(Field),
+}
+
+fn hint(settings: &Settings) -> Option<&'static str> {
+    match settings.qr {
+        QrContent::Url if settings.contact.url.is_empty() => {
+            Some("no url, set one with: badge url <url>")
+        }
+        QrContent::Vcard if settings.contact.name.is_empty() => {
+            Some("no name, set one with: badge name <name>")
+        }
+        _ => None,
+    }
+}
+
+fn draw_contact<D, C>(
+    display: &mut D,
+    painter: &mut Draw<C>,
+    contact: &Contact,
+    selected: Field,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    let left = TextStyleBuilder::new().baseline(Baseline::Middle).build();
+    for (i, field) in Field::ALL.into_iter().enumerate() {
+        let y = ROWS_TOP + i as i32 * ROW_HEIGHT;
+        let label = if field == selected {
+            theme.accent
+        } else {
+            theme.foreground
+        };
+        painter.draw(
+            display,
+            &Text::with_text_style(
+                field.name(),
+                Point::new(16, y),
+                MonoTextStyle::new(&FONT_6X10, label),
+                left,
+            ),
+        )?;
+
+        let value = contact.get(field);
+        let value = if value.is_empty() { "-" } else { value };
+        painter.draw(
+            display,
+            &Text::with_text_style(
+                value,
+                Point::new(VALUE_X, y),
+                FontStyle::new(theme.text_font, theme.foreground),
+                left,
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+fn name(settings: &Settings) -> &str {
+    if settings.contact.name.is_empty() {
+        "Hello!"
+    } else {
+        &settings.contact.name
+    }
+}
+
+fn draw<D, C>(
+    display: &mut D,
+    painter: &mut Draw<C>,
+    settings: &Settings,
+    page: Page,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    // whatever the page before drew
+    painter.clear(display)?;
+    let center = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let x = TFT_DISPLAY_WIDTH as i32 / 2;
+
+    if page == Page::Name {
+        // the name itself is drawn by the marquee
+        painter.track(NAME_AREA);
+        return painter.draw(
+            display,
+            &Text::with_text_style(
+                &settings.contact.company,
+                Point::new(x, COMPANY_Y),
+                FontStyle::new(theme.text_font, theme.accent),
+                center,
+            ),
+        );
+    }
+
+    painter.draw(
+        display,
+        &Text::with_text_style(
+            name(settings),
+            Point::new(x, NAME_Y),
+            FontStyle::new(&PROPORTIONAL_20, theme.foreground),
+            center,
+        ),
+    )?;
+
+    if let Page::Contact(selected) = page {
+        return draw_contact(display, painter, &settings.contact, selected, theme);
+    }
+
+    let small = MonoTextStyle::new(&FONT_6X10, theme.foreground);
+    if let Some(hint) = hint(settings) {
+        return painter.draw(
+            display,
+            &Text::with_text_style(hint, Point::new(x, LABEL_Y), small, center),
+        );
+    }
+
+    let card;
+    let payload = match settings.qr {
+        QrContent::Url => Some(settings.contact.url.as_bytes()),
+        QrContent::Vcard => {
+            // a missing name got the hint above, so `None` is a card too long
+            card = settings.contact.vcard();
+            card.as_deref().map(str::as_bytes)
+        }
+    };
+    let Some(code) = payload.and_then(|payload| QrCode::encode(payload, settings.qr_ecc).ok())
+    else {
+        return painter.draw(
+            display,
+            &Text::with_text_style(
+                "too long for a qr code",
+                Point::new(x, LABEL_Y),
+                small,
+                center,
+            ),
+        );
+    };
+
+    let mut label: String<32> = String::new();
+    let _ = write!(
+        label,
+        "{} {}-{}",
+        settings.qr.name(),
+        code.size(),
+        settings.qr_ecc.name()
+    );
+    painter.draw(
+        display,
+        &Text::with_text_style(&label, Point::new(x, LABEL_Y), small, center),
+    )?;
+
+    // as large as fits below the name
+    let room = CONTENT_HEIGHT as u32 - QR_TOP as u32;
+    let scale = (room / code.pixel_size(1)).max(1);
+    let side = code.pixel_size(scale);
+    let top_left = Point::new(x - side as i32 / 2, QR_TOP);
+    painter.track(Rectangle::new(top_left, Size::new_equal(side)));
+    code.draw(display, top_left, scale, C::BLACK, C::WHITE)
+        .map_err(BadgeError::display)
+}
+
+/// The conference badge: the name as large as it fits, then with
+/// Left/Right a QR code to scan and the contact details. On the code A
+/// switches between the url and a contact card and Up/Down change its error
+/// correction. On the details Up/Down pick a field and A edits it, they can
+/// also be set over usb with `badge`. B leaves.
+pub fn badge<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+{
+    let mut settings = settings::current();
+    let mut page = Page::Name;
+    let mut large = LargeText::new(
+        &PROPORTIONAL_20,
+        NAME_AREA,
+        NAME_MIN_SCALE,
+        theme.foreground,
+        theme.background,
+    );
+    let mut painter = Draw::new(theme.background);
+    display
+        .clear(theme.background)
+        .map_err(BadgeError::display)?;
+    draw(display, &mut painter, &settings, page, theme)?;
+
+    loop {
+        let input = buttons.poll();
+        let mut changed = false;
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            display
+                .clear(theme.background)
+                .map_err(BadgeError::display)?;
+            // the overlay painted over everything, nothing left to clean up
+            painter = Draw::new(theme.background);
+            changed = true;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, tap)?;
+        }
+        display.update_status()?;
+
+        if input.pressed.right {
+            page = match page {
+                Page::Name => Page::Code,
+                Page::Code => Page::Contact(Field::Name),
+                Page::Contact(_) => Page::Name,
+            };
+            changed = true;
+        }
+        if input.pressed.left {
+            page = match page {
+                Page::Name => Page::Contact(Field::Name),
+                Page::Code => Page::Name,
+                Page::Contact(_) => Page::Code,
+            };
+            changed = true;
+        }
+        if let Page::Contact(field) = page {
+            let i = Field::ALL.iter().position(|f| *f == field).unwrap_or(0);
+            let count = Field::ALL.len();
+            if input.pressed.up {
+                page = Page::Contact(Field::ALL[(i + count - 1) % count]);
+                changed = true;
+            }
+            if input.pressed.down {
+                page = Page::Contact(Field::ALL[(i + 1) % count]);
+                changed = true;
+            }
+            if input.pressed.a {
+                let edited: Option<String<64>> = keyboard(
+                    display,
+                    delay,
+                    buttons,
+                    tap,
+                    field.name(),
+                    settings.contact.get(field),
+                    settings.contact.capacity(field),
+                )?;
+                if let Some(text) = edited {
+                    settings.contact.set(field, &text);
+                    settings::update(|saved| {
+                        saved.contact.set(field, &text);
+                    });
+                }
+                changed = true;
+            }
+        }
+        if input.pressed.a && page == Page::Code {
+            settings.qr = match settings.qr {
+                QrContent::Url => QrContent::Vcard,
+                QrContent::Vcard => QrContent::Url,
+            };
+            changed = true;
+        }
+        let level = Ecc::ALL
+            .iter()
+            .position(|ecc| *ecc == settings.qr_ecc)
+            .unwrap_or(0);
+        if input.pressed.up && page == Page::Code && level + 1 < Ecc::ALL.len() {
+            settings.qr_ecc = Ecc::ALL[level + 1];
+            changed = true;
+        }
+        if input.pressed.down && page == Page::Code && level > 0 {
+            settings.qr_ecc = Ecc::ALL[level - 1];
+            changed = true;
+        }
+
+        // the fields can change over usb while the page is up
+        let current = settings::current();
+        if current.contact != settings.contact {
+            settings.contact = current.contact;
+            changed = true;
+        }
+
+        if changed {
+            draw(display, &mut painter, &settings, page, theme)?;
+            large.reset();
+        }
+        if page == Page::Name {
+            large
+                .draw(display, name(&settings), MARQUEE_STEP)
+                .map_err(BadgeError::display)?;
+        }
+
+        if input.released.b {
+            settings::update(|saved| {
+                saved.qr = settings.qr;
+                saved.qr_ecc = settings.qr_ecc;
+            });
+            settings::flush();
+            return Ok(());
+        }
+
+        delay.delay_ms(10);
+    }
+}
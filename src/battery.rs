@@ -0,0 +1,207 @@
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use embedded_graphics::{
+    Drawable,
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, Primitive, RgbColor, Size, WebColors},
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use embedded_hal_0_2::adc::OneShot;
+use rp2040_hal::{
+    Adc, Timer,
+    adc::AdcPin,
+    fugit::Instant,
+    gpio::{FunctionSio, Pin, PullNone, SioInput, bank0::Gpio29},
+};
+
+use crate::{
+    backlight, leds,
+    log::{log_info, log_warn},
+};
+
+const SAMPLE_INTERVAL_MS: u64 = 1_000;
+// vsys reaches the adc through a 1:3 divider, like on the pico
+const DIVIDER: u32 = 3;
+const ADC_REF_MV: u32 = 3_300;
+const ADC_MAX: u32 = 4_095;
+// weight of a new sample, out of 8
+const SMOOTHING: u32 = 1;
+// above any lipo, vsys comes from usb through the diode
+const EXTERNAL_MV: u32 = 4_400;
+
+// lipo discharge curve under a light load, millivolts to percent
+const CURVE: [(u32, u8); 10] = [
+    (3_300, 0),
+    (3_500, 5),
+    (3_600, 10),
+    (3_700, 25),
+    (3_750, 40),
+    (3_800, 55),
+    (3_900, 70),
+    (4_000, 82),
+    (4_100, 92),
+    (4_200, 100),
+];
+
+// low battery kicks in below the first and lets go above the second, so it
+// does not flap on a voltage wobbling around the threshold
+const LOW_PERCENT: u8 = 10;
+const RECOVERED_PERCENT: u8 = 15;
+
+/// What the battery looks like, smoothed.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Reading {
+    pub millivolts: u32,
+    pub percent: u8,
+    /// running from usb, the percentage means nothing then
+    pub external: bool,
+    pub low: bool,
+}
+
+struct Battery {
+    adc: Adc,
+    pin: AdcPin<Pin<Gpio29, FunctionSio<SioInput>, PullNone>>,
+    timer: Timer,
+    last_sample: Option<Instant<u64, 1, 1_000_000>>,
+    // millivolts times 8, for the smoothing to keep its fraction
+    filtered: Option<u32>,
+    reading: Option<Reading>,
+    samples: u32,
+}
+
+static BATTERY: Mutex<RefCell<Option<Battery>>> = Mutex::new(RefCell::new(None));
+
+pub fn init(adc: Adc, pin: AdcPin<Pin<Gpio29, FunctionSio<SioInput>, PullNone>>, timer: Timer) {
+    critical_section::with(|cs| {
+        BATTERY.borrow(cs).replace(Some(Battery {
+            adc,
+            pin,
+            timer,
+            last_sample: None,
+            filtered: None,
+            reading: None,
+            samples: 0,
+        }));
+    });
+}
+
+fn percent(millivolts: u32) -> u8 {
+    let Some(upper) = CURVE.iter().position(|(mv, _)| *mv >= millivolts) else {
+        return 100;
+    };
+    if upper == 0 {
+        return 0;
+    }
+
+    let (mv0, p0) = CURVE[upper - 1];
+    let (mv1, p1) = CURVE[upper];
+    (p0 as u32 + (millivolts - mv0) * (p1 - p0) as u32 / (mv1 - mv0)) as u8
+}
+
+/// Takes a sample once a second, called by `Buttons::poll`. Entering and
+/// leaving low battery dims the backlight and switches the leds.
+pub fn update() {
+    let changed = critical_section::with(|cs| {
+        let mut battery = BATTERY.borrow(cs).borrow_mut();
+        let battery = battery.as_mut()?;
+
+        let now = battery.timer.get_counter();
+        if battery
+            .last_sample
+            .is_some_and(|last| (now - last).to_millis() < SAMPLE_INTERVAL_MS)
+        {
+            return None;
+        }
+        battery.last_sample = Some(now);
+
+        let raw: u16 = battery.adc.read(&mut battery.pin).ok()?;
+        let millivolts = raw as u32 * ADC_REF_MV * DIVIDER / ADC_MAX;
+
+        let filtered = match battery.filtered {
+            Some(filtered) => filtered - filtered * SMOOTHING / 8 + millivolts * SMOOTHING,
+            None => millivolts * 8,
+        };
+        battery.filtered = Some(filtered);
+
+        let millivolts = filtered / 8;
+        let percent = percent(millivolts);
+        let external = millivolts >= EXTERNAL_MV;
+        let was_low = battery.reading.is_some_and(|reading| reading.low);
+        let low = !external
+            && if was_low {
+                percent < RECOVERED_PERCENT
+            } else {
+                percent < LOW_PERCENT
+            };
+
+        battery.reading = Some(Reading {
+            millivolts,
+            percent,
+            external,
+            low,
+        });
+        battery.samples = battery.samples.wrapping_add(1);
+
+        (low != was_low).then_some(low)
+    });
+
+    match changed {
+        Some(true) => {
+            log_warn!("battery low, dimming and turning the leds off");
+            backlight::set_low_battery(true);
+            leds::set_low_battery(true);
+        }
+        Some(false) => {
+            log_info!("battery ok again");
+            backlight::set_low_battery(false);
+            leds::set_low_battery(false);
+        }
+        None => {}
+    }
+}
+
+/// The last reading, with how many were taken so far to tell a new one.
+pub fn reading() -> Option<(Reading, u32)> {
+    critical_section::with(|cs| {
+        let battery = BATTERY.borrow(cs).borrow();
+        let battery = battery.as_ref()?;
+        Some((battery.reading?, battery.samples))
+    })
+}
+
+/// Battery outline with a fill for the charge, 24x12 at `top_left`.
+pub fn draw_icon<D, C>(display: &mut D, top_left: Point, reading: &Reading) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    let body = Rectangle::new(top_left, Size::new(22, 12));
+    let tip = Rectangle::new(top_left + Point::new(22, 3), Size::new(2, 6));
+
+    let fill_color = if reading.external {
+        C::CSS_DEEP_SKY_BLUE
+    } else if reading.low {
+        C::RED
+    } else if reading.percent < 30 {
+        C::CSS_ORANGE
+    } else {
+        C::GREEN
+    };
+    let percent = if reading.external {
+        100
+    } else {
+        reading.percent as u32
+    };
+    let width = 18 * percent / 100;
+
+    body.into_styled(PrimitiveStyle::with_fill(C::BLACK))
+        .draw(display)?;
+    body.into_styled(PrimitiveStyle::with_stroke(C::WHITE, 1))
+        .draw(display)?;
+    tip.into_styled(PrimitiveStyle::with_fill(C::WHITE))
+        .draw(display)?;
+    Rectangle::new(top_left + Point::new(2, 2), Size::new(width, 8))
+        .into_styled(PrimitiveStyle::with_fill(fill_color))
+        .draw(display)
+}
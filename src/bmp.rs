@@ -4,7 +4,15 @@ use embedded_graphics::{
 };
 use tinybmp::Bmp;
 
-use crate::{TFT_DISPLAY_HEIGHT, TFT_DISPLAY_WIDTH};
+use crate::{CONTENT_HEIGHT, TFT_DISPLAY_WIDTH, error::BadgeError};
+
+/// Parses one of the images baked into the firmware.
+pub fn load<C>(bytes: &'static [u8]) -> Result<Bmp<'static, C>, BadgeError>
+where
+    C: PixelColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+{
+    Bmp::from_slice(bytes).map_err(BadgeError::asset)
+}
 
 pub trait BmpExt {
     fn screen_center(&self) -> Point;
@@ -19,7 +27,7 @@ where
         let size = self.size();
         Point::new(
             (TFT_DISPLAY_WIDTH as i32 / 2) - (size.width as i32 / 2),
-            (TFT_DISPLAY_HEIGHT as i32 / 2) - (size.height as i32 / 2),
+            (CONTENT_HEIGHT as i32 / 2) - (size.height as i32 / 2),
         )
     }
 
@@ -27,7 +35,7 @@ where
         let size = self.size();
         Point::new(
             TFT_DISPLAY_WIDTH as i32 - size.width as i32,
-            TFT_DISPLAY_HEIGHT as i32 - size.height as i32,
+            CONTENT_HEIGHT as i32 - size.height as i32,
         )
     }
 }
@@ -0,0 +1,62 @@
+use core::fmt;
+
+use heapless::String;
+
+/// Writes into a `String` up to a number of characters and drops the rest,
+/// where `write!` into a full `String` drops the whole argument that did not
+/// fit. Never cuts a character in half.
+pub struct Clipped<'a, const N: usize> {
+    text: &'a mut String<N>,
+    room: usize,
+}
+
+impl<'a, const N: usize> Clipped<'a, N> {
+    /// Appends to `text` until it is `chars` characters long.
+    pub fn new(text: &'a mut String<N>, chars: usize) -> Self {
+        let room = chars.saturating_sub(text.chars().count());
+        Self { text, room }
+    }
+}
+
+impl<const N: usize> fmt::Write for Clipped<'_, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.room == 0 || self.text.push(c).is_err() {
+                break;
+            }
+            self.room -= 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::Write;
+
+    use super::*;
+
+    #[test]
+    fn keeps_what_fits() {
+        let mut text: String<64> = String::new();
+        let title = "a long title";
+        let _ = write!(Clipped::new(&mut text, 8), "{title} in {}m", 5);
+        assert_eq!(text, "a long t");
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        let mut text: String<64> = String::new();
+        let _ = write!(Clipped::new(&mut text, 3), "José");
+        assert_eq!(text, "Jos");
+        let _ = write!(Clipped::new(&mut text, 4), "é and more");
+        assert_eq!(text, "José");
+    }
+
+    #[test]
+    fn stops_at_the_capacity() {
+        let mut text: String<5> = String::new();
+        let _ = write!(Clipped::new(&mut text, 10), "ééé");
+        assert_eq!(text, "éé");
+    }
+}
@@ -0,0 +1,407 @@
+use core::{fmt::Write, write};
+
+use embedded_graphics::{
+    Drawable,
+    mono_font::{
+        MonoTextStyleBuilder,
+        ascii::{FONT_9X15_BOLD, FONT_10X20},
+    },
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, Primitive, RgbColor, Size, WebColors},
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+use embedded_hal::delay::DelayNs;
+use heapless::String;
+use micromath::F32Ext;
+
+use crate::{
+    TFT_DISPLAY_WIDTH, agenda,
+    backlight::{self, Panel, Tap},
+    clip::Clipped,
+    console,
+    draw::{Draw, Michelangelo},
+    error::BadgeError,
+    input::Buttons,
+    power::Delay,
+    rtc::{self, DateTime},
+    schedule::Schedule,
+    status::StatusBar,
+    theme::Theme,
+};
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+const DATE_Y: i32 = 185;
+const COUNTDOWN_Y: i32 = 208;
+// as many 9x15 characters as fit across
+const COUNTDOWN_CHARS: usize = 34;
+
+// seven segment digits
+const DIGIT: Size = Size::new(40, 80);
+const SEGMENT: u32 = 8;
+const DIGIT_GAP: i32 = 12;
+const DIGITS_TOP: i32 = 40;
+
+// analog face
+const FACE_CENTER: Point = Point::new(TFT_DISPLAY_WIDTH as i32 / 2, 88);
+const FACE_RADIUS: i32 = 80;
+const TICK: i32 = 8;
+// hand lengths and widths
+const HOUR_HAND: (i32, u32) = (44, 5);
+const MINUTE_HAND: (i32, u32) = (64, 3);
+const SECOND_HAND: (i32, u32) = (70, 1);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Face {
+    Digital,
+    Analog,
+}
+
+// lit segments of 0-9, bit 6 is the top (a) down to bit 0 the middle (g)
+const SEGMENTS: [u8; 10] = [
+    0b111_1110, 0b011_0000, 0b110_1101, 0b111_1001, 0b011_0011, 0b101_1011, 0b101_1111, 0b111_0000,
+    0b111_1111, 0b111_1011,
+];
+
+fn segment_rects(origin: Point) -> [Rectangle; 7] {
+    let w = DIGIT.width;
+    let half = DIGIT.height / 2;
+    let t = SEGMENT;
+    let at = |x: u32, y: u32| origin + Point::new(x as i32, y as i32);
+    [
+        // a, b, c, d, e, f, g
+        Rectangle::new(at(t, 0), Size::new(w - 2 * t, t)),
+        Rectangle::new(at(w - t, t), Size::new(t, half - t)),
+        Rectangle::new(at(w - t, half), Size::new(t, half - t)),
+        Rectangle::new(at(t, DIGIT.height - t), Size::new(w - 2 * t, t)),
+        Rectangle::new(at(0, half), Size::new(t, half - t)),
+        Rectangle::new(at(0, t), Size::new(t, half - t)),
+        Rectangle::new(at(t, half - t / 2), Size::new(w - 2 * t, t)),
+    ]
+}
+
+fn draw_digit<D, C>(
+    display: &mut D,
+    painter: &mut Draw<C>,
+    origin: Point,
+    digit: u8,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    for (i, rect) in segment_rects(origin).into_iter().enumerate() {
+        let lit = SEGMENTS[digit as usize] & (1 << (6 - i)) != 0;
+        // unlit segments stay faintly visible, like on the real thing
+        let color = if lit { theme.accent } else { theme.muted };
+        painter.draw(display, &rect.into_styled(PrimitiveStyle::with_fill(color)))?;
+    }
+    Ok(())
+}
+
+fn draw_digital<D, C>(
+    display: &mut D,
+    painter: &mut Draw<C>,
+    now: &DateTime,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    let colon = SEGMENT as i32 * 2;
+    let width = 4 * DIGIT.width as i32 + 4 * DIGIT_GAP + colon;
+    let mut x = (TFT_DISPLAY_WIDTH as i32 - width) / 2;
+
+    for (i, digit) in [
+        now.hour / 10,
+        now.hour % 10,
+        now.minute / 10,
+        now.minute % 10,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        draw_digit(display, painter, Point::new(x, DIGITS_TOP), digit, theme)?;
+        x += DIGIT.width as i32 + DIGIT_GAP;
+
+        if i == 1 {
+            // the colon blinks with the seconds
+            let color = if now.second.is_multiple_of(2) {
+                theme.accent
+            } else {
+                theme.muted
+            };
+            for y in [DIGIT.height as i32 / 3, DIGIT.height as i32 * 2 / 3] {
+                let dot = Rectangle::new(
+                    Point::new(x, DIGITS_TOP + y - SEGMENT as i32 / 2),
+                    Size::new(colon as u32, SEGMENT),
+                );
+                painter.draw(display, &dot.into_styled(PrimitiveStyle::with_fill(color)))?;
+            }
+            x += colon + DIGIT_GAP;
+        }
+    }
+    Ok(())
+}
+
+fn hand_end(angle: f32, length: i32) -> Point {
+    // clockwise from twelve
+    let radians = angle.to_radians();
+    FACE_CENTER
+        + Point::new(
+            (radians.sin() * length as f32) as i32,
+            -(radians.cos() * length as f32) as i32,
+        )
+}
+
+fn hands(now: &DateTime) -> [(f32, (i32, u32)); 3] {
+    let second = now.second as f32;
+    let minute = now.minute as f32 + second / 60.0;
+    let hour = (now.hour % 12) as f32 + minute / 60.0;
+    [
+        (hour * 30.0, HOUR_HAND),
+        (minute * 6.0, MINUTE_HAND),
+        (second * 6.0, SECOND_HAND),
+    ]
+}
+
+fn draw_dial<D, C>(
+    display: &mut D,
+    painter: &mut Draw<C>,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    // the hands stay inside, so the dial is all there is to clean up
+    painter.draw(
+        display,
+        &Circle::with_center(FACE_CENTER, FACE_RADIUS as u32 * 2)
+            .into_styled(PrimitiveStyle::with_stroke(theme.foreground, 2)),
+    )?;
+
+    for hour in 0..12u32 {
+        let angle = hour as f32 * 30.0;
+        let width = if hour % 3 == 0 { 3 } else { 1 };
+        Line::new(
+            hand_end(angle, FACE_RADIUS - TICK),
+            hand_end(angle, FACE_RADIUS - 2),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(theme.foreground, width))
+        .draw(display)
+        .map_err(BadgeError::display)?;
+    }
+    Ok(())
+}
+
+fn draw_hands<D, C>(
+    display: &mut D,
+    now: &DateTime,
+    erase: bool,
+    theme: &Theme<C>,
+) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    for (i, (angle, (length, width))) in hands(now).into_iter().enumerate() {
+        let color = match (erase, i) {
+            (true, _) => theme.background,
+            (false, 2) => theme.accent,
+            (false, _) => theme.foreground,
+        };
+        Line::new(FACE_CENTER, hand_end(angle, length))
+            .into_styled(PrimitiveStyle::with_stroke(color, width))
+            .draw(display)?;
+    }
+    Circle::with_center(FACE_CENTER, 7)
+        .into_styled(PrimitiveStyle::with_fill(theme.accent))
+        .draw(display)
+}
+
+fn write_countdown(buf: &mut impl Write, schedule: &Schedule, now: &DateTime) -> core::fmt::Result {
+    let now_s = now.seconds();
+    match agenda::next(schedule, now_s) {
+        Some((start, session)) => match (start - now_s + 59) / 60 {
+            m if m < 60 => write!(buf, "{} in {}m", session.title, m),
+            m if m < 24 * 60 => write!(buf, "{} in {}h {:02}m", session.title, m / 60, m % 60),
+            m => write!(
+                buf,
+                "{} in {}d {}h",
+                session.title,
+                m / (24 * 60),
+                m / 60 % 24
+            ),
+        },
+        None => write!(buf, "no more talks"),
+    }
+}
+
+fn draw_text<D, C>(
+    display: &mut D,
+    painter: &mut Draw<C>,
+    schedule: &Schedule,
+    now: Option<&DateTime>,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let x = TFT_DISPLAY_WIDTH as i32 / 2;
+
+    let mut date: String<24> = String::new();
+    // characters can take up to four bytes
+    let mut countdown: String<{ COUNTDOWN_CHARS * 4 }> = String::new();
+    match now {
+        Some(now) => {
+            let _ = write!(
+                &mut date,
+                "{} {}-{:02}-{:02}",
+                WEEKDAYS[now.weekday() as usize],
+                now.year,
+                now.month,
+                now.day
+            );
+            let _ = write_countdown(
+                &mut Clipped::new(&mut countdown, COUNTDOWN_CHARS),
+                schedule,
+                now,
+            );
+        }
+        None => {
+            let _ = write!(&mut date, "time not set");
+            let _ = write!(&mut countdown, "over usb: time YYYY-MM-DD HH:MM");
+        }
+    }
+    // centered, so padded to the full width to paint over a longer line
+    let mut line: String<{ COUNTDOWN_CHARS * 4 }> = String::new();
+    let _ = write!(&mut line, "{:^w$}", countdown, w = COUNTDOWN_CHARS);
+
+    painter.draw(
+        display,
+        &Text::with_text_style(
+            &date,
+            Point::new(x, DATE_Y),
+            MonoTextStyleBuilder::new()
+                .font(&FONT_10X20)
+                .text_color(theme.foreground)
+                .background_color(theme.background)
+                .build(),
+            centered,
+        ),
+    )?;
+    painter.draw(
+        display,
+        &Text::with_text_style(
+            &line,
+            Point::new(x, COUNTDOWN_Y),
+            MonoTextStyleBuilder::new()
+                .font(&FONT_9X15_BOLD)
+                .text_color(theme.accent)
+                .background_color(theme.background)
+                .build(),
+            centered,
+        ),
+    )
+}
+
+/// Wall clock with a digital and an analog face and a countdown to the next
+/// talk. A switches faces, B leaves. The time is set over the USB shell.
+pub fn clock<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+{
+    let schedule = agenda::load()?;
+    let mut face = Face::Digital;
+    let mut shown: Option<Option<DateTime>> = None;
+    let mut painter = Draw::new(theme.background);
+    display
+        .clear(theme.background)
+        .map_err(BadgeError::display)?;
+
+    loop {
+        let input = buttons.poll();
+        let mut redraw = shown.is_none();
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            display
+                .clear(theme.background)
+                .map_err(BadgeError::display)?;
+            // the overlay painted over everything, nothing left to clean up
+            painter = Draw::new(theme.background);
+            redraw = true;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, tap)?;
+        }
+        display.update_status()?;
+
+        if input.pressed.a {
+            face = match face {
+                Face::Digital => Face::Analog,
+                Face::Analog => Face::Digital,
+            };
+            redraw = true;
+        }
+
+        let now = rtc::now();
+        // setting the time also changes what the screen looks like
+        if shown.is_some_and(|shown| shown.is_some() != now.is_some()) {
+            redraw = true;
+        }
+
+        if redraw {
+            // only the other face has to go
+            painter.clear(display)?;
+            if now.is_some() && face == Face::Analog {
+                draw_dial(display, &mut painter, theme)?;
+            }
+        }
+
+        if redraw || shown != Some(now) {
+            if let Some(now) = &now {
+                match face {
+                    Face::Digital => draw_digital(display, &mut painter, now, theme)?,
+                    Face::Analog => {
+                        if let Some(Some(before)) = shown.filter(|_| !redraw) {
+                            draw_hands(display, &before, true, theme)
+                                .map_err(BadgeError::display)?;
+                        }
+                        draw_hands(display, now, false, theme).map_err(BadgeError::display)?;
+                    }
+                }
+            }
+            draw_text(display, &mut painter, &schedule, now.as_ref(), theme)?;
+            shown = Some(now);
+        }
+
+        if input.released.b {
+            return Ok(());
+        }
+
+        delay.delay_ms(10);
+    }
+}
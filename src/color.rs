@@ -0,0 +1,349 @@
+use embedded_graphics::{
+    pixelcolor::{Rgb565, Rgb888},
+    prelude::RgbColor,
+};
+use smart_leds::RGB8;
+
+/// Perceived brightness to ws2812 duty cycle (gamma 2.8). The leds are linear
+/// while the display and our eyes are not, without this a dim color on screen
+/// is a bright one on the leds.
+const GAMMA8: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
+    5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14,
+    14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22, 22, 23, 24, 24, 25, 25, 26, 27,
+    27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41, 42, 43, 44, 45, 46,
+    47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
+    73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104,
+    105, 107, 109, 110, 112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137,
+    138, 140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
+    223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
+
+/// The ws2812 green and blue dies are brighter than the red one, scaling them
+/// down makes white look white, like it does on the display.
+const LED_WHITE_BALANCE: RGB8 = RGB8 {
+    r: 255,
+    g: 176,
+    b: 240,
+};
+
+/// Draw of a single channel driven at full duty cycle.
+const LED_CHANNEL_MA: u32 = 20;
+
+pub const LED_CURRENT_BUDGET_MA: u32 = 60;
+pub const LED_BRIGHTNESS_CAP_DEFAULT: u8 = 48;
+
+fn scale(value: u8, by: u8) -> u8 {
+    ((value as u16 * by as u16) / 255) as u8
+}
+
+/// Maps a color as it is drawn on screen to the led drive values that look
+/// alike: gamma corrected, then white balanced.
+pub fn screen_to_led(color: RGB8) -> RGB8 {
+    RGB8::new(
+        scale(GAMMA8[color.r as usize], LED_WHITE_BALANCE.r),
+        scale(GAMMA8[color.g as usize], LED_WHITE_BALANCE.g),
+        scale(GAMMA8[color.b as usize], LED_WHITE_BALANCE.b),
+    )
+}
+
+/// Everything a frame goes through between the effects and the leds.
+#[derive(Clone, Copy)]
+pub struct LedPipeline {
+    /// ceiling for every channel after gamma, whatever the effect asks for
+    pub brightness_cap: u8,
+    /// the frame is dimmed as a whole when it would draw more than this
+    pub current_budget_ma: u32,
+}
+
+impl Default for LedPipeline {
+    fn default() -> Self {
+        Self {
+            brightness_cap: LED_BRIGHTNESS_CAP_DEFAULT,
+            current_budget_ma: LED_CURRENT_BUDGET_MA,
+        }
+    }
+}
+
+impl LedPipeline {
+    pub fn apply<const N: usize>(&self, colors: [RGB8; N]) -> [RGB8; N] {
+        let mut leds = colors.map(|color| {
+            let led = screen_to_led(color);
+            RGB8::new(
+                scale(led.r, self.brightness_cap),
+                scale(led.g, self.brightness_cap),
+                scale(led.b, self.brightness_cap),
+            )
+        });
+
+        let duty: u32 = leds
+            .iter()
+            .map(|led| led.r as u32 + led.g as u32 + led.b as u32)
+            .sum();
+        let current_ma = duty * LED_CHANNEL_MA / 255;
+
+        if current_ma > self.current_budget_ma {
+            let by = (self.current_budget_ma * 255 / current_ma) as u8;
+            for led in leds.iter_mut() {
+                *led = RGB8::new(scale(led.r, by), scale(led.g, by), scale(led.b, by));
+            }
+        }
+
+        leds
+    }
+}
+
+/// How an led color is brought to the display.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ScreenBrightness {
+    /// dim stays dim, the screen shows what the led shows
+    Preserve,
+    /// stretched to full brightness, only hue and saturation are kept
+    Normalize,
+}
+
+/// Hue in degrees (0..360), saturation and value in 0..=255.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hsv {
+    pub hue: u16,
+    pub sat: u8,
+    pub val: u8,
+}
+
+/// Hue in degrees (0..360), saturation and lightness in 0..=255.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hsl {
+    pub hue: u16,
+    pub sat: u8,
+    pub light: u8,
+}
+
+fn div_round(n: u32, d: u32) -> u32 {
+    (n + d / 2) / d
+}
+
+pub fn rgb8_to_rgb565(color: RGB8) -> Rgb565 {
+    Rgb565::new(
+        div_round(color.r as u32 * 31, 255) as u8,
+        div_round(color.g as u32 * 63, 255) as u8,
+        div_round(color.b as u32 * 31, 255) as u8,
+    )
+}
+
+pub fn rgb565_to_rgb8(color: Rgb565) -> RGB8 {
+    RGB8::new(
+        div_round(color.r() as u32 * 255, 31) as u8,
+        div_round(color.g() as u32 * 255, 63) as u8,
+        div_round(color.b() as u32 * 255, 31) as u8,
+    )
+}
+
+pub fn rgb8_to_rgb888(color: RGB8) -> Rgb888 {
+    Rgb888::new(color.r, color.g, color.b)
+}
+
+pub fn rgb888_to_rgb8(color: Rgb888) -> RGB8 {
+    RGB8::new(color.r(), color.g(), color.b())
+}
+
+/// Led color to display color, see [`ScreenBrightness`].
+pub fn led_to_screen(color: RGB8, brightness: ScreenBrightness) -> Rgb565 {
+    match brightness {
+        ScreenBrightness::Preserve => rgb8_to_rgb565(color),
+        ScreenBrightness::Normalize => {
+            let max = color.r.max(color.g).max(color.b) as u32;
+            if max == 0 {
+                return Rgb565::BLACK;
+            }
+            let stretch = |channel: u8| div_round(channel as u32 * 255, max) as u8;
+            rgb8_to_rgb565(RGB8::new(
+                stretch(color.r),
+                stretch(color.g),
+                stretch(color.b),
+            ))
+        }
+    }
+}
+
+// hue in degrees from the channel ordering, shared by hsv and hsl
+fn hue(color: RGB8, max: i32, delta: i32) -> u16 {
+    if delta == 0 {
+        return 0;
+    }
+    let (r, g, b) = (color.r as i32, color.g as i32, color.b as i32);
+    let (offset, diff) = if max == r {
+        (0, g - b)
+    } else if max == g {
+        (120, b - r)
+    } else {
+        (240, r - g)
+    };
+    let hue = offset + (60 * diff * 2 + delta).div_euclid(2 * delta);
+    hue.rem_euclid(360) as u16
+}
+
+// places chroma `c` and the secondary component `x` in the hue's sextant, then
+// lifts everything by `m`
+fn from_hue(hue: u16, c: u32, m: u32) -> RGB8 {
+    let hue = hue as u32 % 360;
+    let x = div_round(c * (60 - (hue % 120).abs_diff(60)), 60);
+    let (r, g, b) = match hue / 60 {
+        0 => (c, x, 0),
+        1 => (x, c, 0),
+        2 => (0, c, x),
+        3 => (0, x, c),
+        4 => (x, 0, c),
+        _ => (c, 0, x),
+    };
+    RGB8::new((r + m) as u8, (g + m) as u8, (b + m) as u8)
+}
+
+pub fn rgb8_to_hsv(color: RGB8) -> Hsv {
+    let max = color.r.max(color.g).max(color.b) as i32;
+    let min = color.r.min(color.g).min(color.b) as i32;
+    let delta = max - min;
+
+    Hsv {
+        hue: hue(color, max, delta),
+        sat: if max == 0 {
+            0
+        } else {
+            div_round(delta as u32 * 255, max as u32) as u8
+        },
+        val: max as u8,
+    }
+}
+
+pub fn hsv_to_rgb8(hsv: Hsv) -> RGB8 {
+    let c = div_round(hsv.val as u32 * hsv.sat as u32, 255);
+    from_hue(hsv.hue, c, hsv.val as u32 - c)
+}
+
+pub fn rgb8_to_hsl(color: RGB8) -> Hsl {
+    let max = color.r.max(color.g).max(color.b) as i32;
+    let min = color.r.min(color.g).min(color.b) as i32;
+    let delta = max - min;
+    // 255 - |2l - 255|, in 0..=255 units
+    let range = 255 - (max + min - 255).abs();
+
+    Hsl {
+        hue: hue(color, max, delta),
+        sat: if range == 0 {
+            0
+        } else {
+            div_round(delta as u32 * 255, range as u32) as u8
+        },
+        light: div_round((max + min) as u32, 2) as u8,
+    }
+}
+
+pub fn hsl_to_rgb8(hsl: Hsl) -> RGB8 {
+    let range = 255 - (2 * hsl.light as i32 - 255).unsigned_abs();
+    let c = div_round(range * hsl.sat as u32, 255);
+    from_hue(
+        hsl.hue,
+        c,
+        (hsl.light as u32).saturating_sub(div_round(c, 2)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::pixelcolor::raw::RawU16;
+
+    fn max_diff(a: RGB8, b: RGB8) -> u8 {
+        a.r.abs_diff(b.r)
+            .max(a.g.abs_diff(b.g))
+            .max(a.b.abs_diff(b.b))
+    }
+
+    // every channel value, mixed so each one also meets the others
+    fn colors() -> impl Iterator<Item = RGB8> {
+        (0..=255u8).flat_map(|v| {
+            [
+                RGB8::new(v, v, v),
+                RGB8::new(v, 0, 255 - v),
+                RGB8::new(255, v, v / 2),
+                RGB8::new(v / 3, 255 - v, v),
+                RGB8::new(v, v.wrapping_mul(7), v.wrapping_mul(13)),
+            ]
+        })
+    }
+
+    #[test]
+    fn rgb888_round_trip() {
+        for v in 0..=255 {
+            let color = RGB8::new(v, 255 - v, v / 2);
+            assert_eq!(rgb888_to_rgb8(rgb8_to_rgb888(color)), color);
+        }
+    }
+
+    #[test]
+    fn rgb565_round_trip() {
+        for v in 0..=255 {
+            let color = RGB8::new(v, v, v);
+            let back = rgb565_to_rgb8(rgb8_to_rgb565(color));
+            // half a 5 bit step for red and blue, half a 6 bit step for green
+            assert!(back.r.abs_diff(v) <= 4 && back.b.abs_diff(v) <= 4, "{v}");
+            assert!(back.g.abs_diff(v) <= 2, "{v}");
+        }
+        // the other way around nothing is lost, for any of the colors
+        for raw in 0..=u16::MAX {
+            let color = Rgb565::from(RawU16::new(raw));
+            assert_eq!(rgb8_to_rgb565(rgb565_to_rgb8(color)), color, "{raw:#06x}");
+        }
+    }
+
+    // hues are whole degrees, a step of 1/60 of the chroma
+    const HUE_TOLERANCE: u8 = 3;
+
+    #[test]
+    fn hsv_round_trip() {
+        for color in colors() {
+            let hsv = rgb8_to_hsv(color);
+            let back = hsv_to_rgb8(hsv);
+            assert!(
+                max_diff(back, color) <= HUE_TOLERANCE,
+                "{color:?} {hsv:?} {back:?}"
+            );
+            assert_eq!(hsv.val, color.r.max(color.g).max(color.b));
+        }
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for color in colors() {
+            let hsl = rgb8_to_hsl(color);
+            let back = hsl_to_rgb8(hsl);
+            assert!(
+                max_diff(back, color) <= HUE_TOLERANCE,
+                "{color:?} {hsl:?} {back:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn primary_hues() {
+        for (color, hue) in [
+            (RGB8::new(255, 0, 0), 0),
+            (RGB8::new(255, 255, 0), 60),
+            (RGB8::new(0, 255, 0), 120),
+            (RGB8::new(0, 0, 255), 240),
+            (RGB8::new(255, 0, 255), 300),
+        ] {
+            assert_eq!(rgb8_to_hsv(color).hue, hue);
+            assert_eq!(rgb8_to_hsl(color).hue, hue);
+            assert_eq!(
+                hsv_to_rgb8(Hsv {
+                    hue,
+                    sat: 255,
+                    val: 255
+                }),
+                color
+            );
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use core::{cell::RefCell, fmt::Write, write};
+
+use critical_section::Mutex;
+use embedded_graphics::{
+    Drawable,
+    mono_font::{MonoTextStyleBuilder, ascii::FONT_6X9},
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, RgbColor, WebColors},
+    text::{Baseline, Text},
+};
+use embedded_hal::delay::DelayNs;
+use heapless::{Deque, String};
+
+use crate::{
+    CONTENT_HEIGHT,
+    error::BadgeError,
+    input::Buttons,
+    log::{self, LINE_LEN, Level, Sink},
+    power::Delay,
+};
+
+const CAPACITY: usize = 64;
+const ROW_HEIGHT: i32 = 10;
+const ROWS: usize = (CONTENT_HEIGHT as usize - ROW_HEIGHT as usize) / ROW_HEIGHT as usize;
+
+fn level_color<C>(level: Level) -> C
+where
+    C: RgbColor + WebColors,
+{
+    match level {
+        Level::Debug => C::CSS_GRAY,
+        Level::Info => C::WHITE,
+        Level::Warn => C::YELLOW,
+        Level::Error => C::RED,
+    }
+}
+
+#[derive(Clone)]
+struct Line {
+    level: Level,
+    text: String<LINE_LEN>,
+}
+
+struct Console {
+    lines: Deque<Line, CAPACITY>,
+    // bumped on every new line, tells the overlay to repaint
+    generation: u32,
+    // a warning or error came in since the overlay was last open
+    unread: bool,
+}
+
+static CONSOLE: Mutex<RefCell<Console>> = Mutex::new(RefCell::new(Console {
+    lines: Deque::new(),
+    generation: 0,
+    unread: false,
+}));
+
+/// Appends a line to the scrollback, dropping the oldest one when full.
+pub fn push(level: Level, text: String<LINE_LEN>) {
+    critical_section::with(|cs| {
+        let mut console = CONSOLE.borrow(cs).borrow_mut();
+        if console.lines.is_full() {
+            console.lines.pop_front();
+        }
+        let _ = console.lines.push_back(Line { level, text });
+        console.generation = console.generation.wrapping_add(1);
+        console.unread |= level >= Level::Warn;
+    });
+}
+
+/// Whether a warning or error is waiting to be looked at.
+pub fn unread() -> bool {
+    critical_section::with(|cs| CONSOLE.borrow(cs).borrow().unread)
+}
+
+fn snapshot(scroll: usize) -> (u32, usize, [Option<Line>; ROWS]) {
+    critical_section::with(|cs| {
+        let console = CONSOLE.borrow(cs).borrow();
+        let len = console.lines.len();
+        let scroll = scroll.min(len.saturating_sub(ROWS));
+        let first = len.saturating_sub(ROWS + scroll);
+
+        let mut rows: [Option<Line>; ROWS] = [const { None }; ROWS];
+        for (row, line) in rows.iter_mut().zip(console.lines.iter().skip(first)) {
+            *row = Some(line.clone());
+        }
+
+        (console.generation, scroll, rows)
+    })
+}
+
+// screen filter choices A cycles through
+const SCREEN_FILTERS: [Option<Level>; 5] = [
+    Some(Level::Debug),
+    Some(Level::Info),
+    Some(Level::Warn),
+    Some(Level::Error),
+    None,
+];
+
+fn draw<D, C>(display: &mut D, scroll: usize, rows: &[Option<Line>; ROWS]) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    let style = |color| {
+        MonoTextStyleBuilder::new()
+            .font(&FONT_6X9)
+            .text_color(color)
+            .background_color(C::BLACK)
+            .build()
+    };
+
+    let mut title: String<LINE_LEN> = String::new();
+    let level = log::filter(Sink::Screen).map_or("off", |level| level.name());
+    let _ = write!(
+        &mut title,
+        "scroll {:<3} level {:<5}  U/D/L/R scroll A level B",
+        scroll, level
+    );
+    Text::with_baseline(&title, Point::zero(), style(C::CSS_ORANGE), Baseline::Top)
+        .draw(display)?;
+
+    for (i, row) in rows.iter().enumerate() {
+        // pad every row so whatever was there before is painted over
+        let mut text: String<{ LINE_LEN + 2 }> = String::new();
+        let color = match row {
+            Some(line) => {
+                let _ = write!(
+                    &mut text,
+                    "{} {:<w$}",
+                    line.level.tag(),
+                    line.text,
+                    w = LINE_LEN
+                );
+                level_color(line.level)
+            }
+            None => {
+                let _ = write!(&mut text, "{:<w$}", "", w = LINE_LEN + 2);
+                C::WHITE
+            }
+        };
+
+        Text::with_baseline(
+            &text,
+            Point::new(0, ROW_HEIGHT * (i as i32 + 1)),
+            style(color),
+            Baseline::Top,
+        )
+        .draw(display)?;
+    }
+
+    Ok(())
+}
+
+/// Takes over the screen to browse the console until B (or B+Up again) closes
+/// it. A changes which levels are kept for the screen. Callers have to repaint
+/// afterwards.
+pub fn overlay<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    display.clear(C::BLACK).map_err(BadgeError::display)?;
+    critical_section::with(|cs| CONSOLE.borrow(cs).borrow_mut().unread = false);
+
+    let mut scroll: usize = 0;
+    let mut shown = None;
+
+    loop {
+        let input = buttons.poll();
+
+        if input.console || input.released.b {
+            return Ok(());
+        }
+
+        if input.pressed.up {
+            scroll += 1;
+        }
+        if input.pressed.down {
+            scroll = scroll.saturating_sub(1);
+        }
+        if input.pressed.left {
+            scroll += ROWS;
+        }
+        if input.pressed.right {
+            scroll = scroll.saturating_sub(ROWS);
+        }
+
+        if input.pressed.a {
+            let current = log::filter(Sink::Screen);
+            let idx = SCREEN_FILTERS
+                .iter()
+                .position(|filter| *filter == current)
+                .unwrap_or(0);
+            log::set_filter(
+                Sink::Screen,
+                SCREEN_FILTERS[(idx + 1) % SCREEN_FILTERS.len()],
+            );
+        }
+
+        let (generation, clamped, rows) = snapshot(scroll);
+        scroll = clamped;
+        let filter = log::filter(Sink::Screen);
+
+        if shown != Some((generation, scroll, filter)) {
+            draw(display, scroll, &rows).map_err(BadgeError::display)?;
+            shown = Some((generation, scroll, filter));
+        }
+
+        delay.delay_ms(10);
+    }
+}
@@ -0,0 +1,282 @@
+use core::fmt::{self, Write};
+
+use heapless::String;
+
+/// Room for a vCard with every field full, escapes and folds and all: 725
+/// bytes when every character has to be escaped.
+pub const VCARD_LEN: usize = 736;
+
+// vCard lines are folded to this many bytes, line break not counted
+const LINE_LEN: usize = 75;
+
+/// Who is wearing the badge, set over usb and shared as a vCard.
+#[derive(Clone, Default, PartialEq)]
+pub struct Contact {
+    /// shown large on the badge page
+    pub name: String<32>,
+    pub email: String<48>,
+    /// user name, without the @
+    pub github: String<40>,
+    /// as `@user@server`
+    pub mastodon: String<48>,
+    pub company: String<32>,
+    pub url: String<64>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Name,
+    Email,
+    Github,
+    Mastodon,
+    Company,
+    Url,
+}
+
+impl Field {
+    pub const ALL: [Field; 6] = [
+        Field::Name,
+        Field::Email,
+        Field::Github,
+        Field::Mastodon,
+        Field::Company,
+        Field::Url,
+    ];
+
+    /// As typed in the shell.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Name => "name",
+            Field::Email => "email",
+            Field::Github => "github",
+            Field::Mastodon => "mastodon",
+            Field::Company => "company",
+            Field::Url => "url",
+        }
+    }
+}
+
+// left as it was when the text does not fit
+fn set_text<const N: usize>(value: &mut String<N>, text: &str) -> bool {
+    match String::try_from(text) {
+        Ok(text) => {
+            *value = text;
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+// long lines go on after a line break and a space, never in the middle of
+// a character
+struct Folded<'a> {
+    out: &'a mut String<VCARD_LEN>,
+    line: usize,
+}
+
+impl Write for Folded<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '\n' => self.line = 0,
+                '\r' => {}
+                c => {
+                    if self.line + c.len_utf8() > LINE_LEN {
+                        self.out.write_str("\r\n ")?;
+                        self.line = 1;
+                    }
+                    self.line += c.len_utf8();
+                }
+            }
+            self.out.write_char(c)?;
+        }
+        Ok(())
+    }
+}
+
+// commas, semicolons and backslashes are separators in a vcard value
+fn escape(out: &mut impl Write, value: &str) -> fmt::Result {
+    for c in value.chars() {
+        if matches!(c, ',' | ';' | '\\') {
+            out.write_char('\\')?;
+        }
+        out.write_char(c)?;
+    }
+    Ok(())
+}
+
+// one `KEY:value` line, left out when there is no value
+fn property(out: &mut impl Write, key: &str, prefix: &str, value: &str) -> fmt::Result {
+    if value.is_empty() {
+        return Ok(());
+    }
+    out.write_str(key)?;
+    out.write_str(prefix)?;
+    escape(out, value)?;
+    out.write_str("\r\n")
+}
+
+impl Contact {
+    pub fn get(&self, field: Field) -> &str {
+        match field {
+            Field::Name => &self.name,
+            Field::Email => &self.email,
+            Field::Github => &self.github,
+            Field::Mastodon => &self.mastodon,
+            Field::Company => &self.company,
+            Field::Url => &self.url,
+        }
+    }
+
+    /// Longest text the field takes, in bytes.
+    pub fn capacity(&self, field: Field) -> usize {
+        match field {
+            Field::Name => self.name.capacity(),
+            Field::Email => self.email.capacity(),
+            Field::Github => self.github.capacity(),
+            Field::Mastodon => self.mastodon.capacity(),
+            Field::Company => self.company.capacity(),
+            Field::Url => self.url.capacity(),
+        }
+    }
+
+    /// Sets a field, false (and the field unchanged) when `text` is too long.
+    pub fn set(&mut self, field: Field, text: &str) -> bool {
+        match field {
+            Field::Name => set_text(&mut self.name, text),
+            Field::Email => set_text(&mut self.email, text),
+            Field::Github => set_text(&mut self.github, text.trim_start_matches('@')),
+            Field::Mastodon => set_text(&mut self.mastodon, text),
+            Field::Company => set_text(&mut self.company, text),
+            Field::Url => set_text(&mut self.url, text),
+        }
+    }
+
+    /// A vCard 3.0 of the fields that are set, `None` without a name, which
+    /// the format requires.
+    pub fn vcard(&self) -> Option<String<VCARD_LEN>> {
+        if self.name.is_empty() {
+            return None;
+        }
+        let mut out = String::new();
+        self.write_vcard(&mut Folded {
+            out: &mut out,
+            line: 0,
+        })
+        .ok()?;
+        Some(out)
+    }
+
+    fn write_vcard(&self, out: &mut impl Write) -> fmt::Result {
+        out.write_str("BEGIN:VCARD\r\nVERSION:3.0\r\nFN:")?;
+        escape(out, &self.name)?;
+        // family name last, the rest goes in as given names
+        let (given, family) = self.name.rsplit_once(' ').unwrap_or(("", &self.name));
+        out.write_str("\r\nN:")?;
+        escape(out, family)?;
+        out.write_char(';')?;
+        escape(out, given)?;
+        out.write_str(";;;\r\n")?;
+
+        property(out, "ORG:", "", &self.company)?;
+        property(out, "EMAIL;TYPE=INTERNET:", "", &self.email)?;
+        property(out, "URL:", "", &self.url)?;
+        // profiles as plain links, every contacts app understands those
+        property(out, "URL:", "https://github.com/", &self.github)?;
+        let mut mastodon: String<64> = String::new();
+        match self.mastodon.trim_start_matches('@').split_once('@') {
+            Some((user, server)) => write!(mastodon, "https://{}/@{}", server, user)?,
+            None => write!(mastodon, "{}", self.mastodon)?,
+        }
+        property(out, "URL:", "", &mastodon)?;
+        out.write_str("END:VCARD\r\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(c: char) -> Contact {
+        let mut contact = Contact::default();
+        for field in Field::ALL {
+            let mut text: String<64> = String::new();
+            while text.len() + c.len_utf8() <= contact.capacity(field) {
+                text.push(c).unwrap();
+            }
+            assert!(contact.set(field, &text));
+        }
+        contact
+    }
+
+    #[test]
+    fn keeps_a_field_when_the_text_is_too_long() {
+        let mut contact = Contact::default();
+        assert!(contact.set(Field::Github, "@ferris"));
+        let long: String<64> =
+            core::iter::repeat_n('x', contact.capacity(Field::Github) + 1).collect();
+        assert!(!contact.set(Field::Github, &long));
+        assert_eq!(contact.get(Field::Github), "ferris");
+        assert!(contact.set(Field::Github, &long[1..]));
+        assert_eq!(contact.get(Field::Github), &long[1..]);
+    }
+
+    fn lines(card: &str) -> impl Iterator<Item = &str> {
+        card.split_terminator("\r\n")
+    }
+
+    #[test]
+    fn fits_every_field_full_and_escaped() {
+        let mut contact = filled(',');
+        // the longest mastodon link, split into user and server
+        contact.mastodon.clear();
+        contact.mastodon.push('@').unwrap();
+        for i in 0..contact.mastodon.capacity() - 1 {
+            contact
+                .mastodon
+                .push(if i == 23 { '@' } else { ',' })
+                .unwrap();
+        }
+        // the worst case in the `VCARD_LEN` comment
+        assert_eq!(contact.vcard().unwrap().len(), 725);
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        for c in [',', 'x', 'é', '🦀'] {
+            let card = filled(c).vcard().unwrap();
+            for line in lines(&card) {
+                assert!(line.len() <= LINE_LEN, "{line}");
+            }
+            // unfolding gives back the long lines
+            let mut unfolded: String<VCARD_LEN> = String::new();
+            for line in lines(&card) {
+                match line.strip_prefix(' ') {
+                    Some(rest) => unfolded.push_str(rest).unwrap(),
+                    None => {
+                        unfolded.push_str("\n").unwrap();
+                        unfolded.push_str(line).unwrap();
+                    }
+                }
+            }
+            assert!(unfolded.contains("github.com/"));
+            assert!(unfolded.ends_with("END:VCARD"));
+        }
+    }
+
+    #[test]
+    fn short_card() {
+        let mut contact = Contact::default();
+        assert!(contact.vcard().is_none());
+        contact.set(Field::Name, "Ada King, Lovelace");
+        contact.set(Field::Github, "@ada");
+        contact.set(Field::Mastodon, "@ada@example.social");
+        assert_eq!(
+            contact.vcard().unwrap(),
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Ada King\\, Lovelace\r\n\
+             N:Lovelace;Ada King\\,;;;\r\n\
+             URL:https://github.com/ada\r\n\
+             URL:https://example.social/@ada\r\nEND:VCARD\r\n"
+        );
+    }
+}
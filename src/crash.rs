@@ -0,0 +1,459 @@
+use core::{
+    fmt::Write,
+    mem::MaybeUninit,
+    panic::PanicInfo,
+    ptr::{addr_of, addr_of_mut},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use cortex_m::peripheral::SCB;
+use embedded_graphics::{
+    Drawable,
+    image::Image,
+    mono_font::{
+        MonoTextStyle, MonoTextStyleBuilder,
+        ascii::{FONT_6X10, FONT_9X18_BOLD},
+    },
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, RgbColor, Size, WebColors},
+    primitives::Rectangle,
+    text::{Baseline, Text},
+};
+use embedded_hal::{delay::DelayNs, digital::PinState};
+use embedded_hal_bus::spi::{ExclusiveDevice, NoDelay};
+use heapless::String;
+use mipidsi::interface::SpiInterface;
+use rp2040_hal::{
+    Spi,
+    fugit::RateExtU32,
+    gpio::{FunctionSpi, Pins},
+    pac,
+    sio::Sio,
+};
+use tinybmp::Bmp;
+
+use crate::{
+    GOPHER_PANIC, bmp, console, display_builder,
+    error::BadgeError,
+    input::Buttons,
+    power::Delay,
+    text_box::{TextBox, VerticalAlignment},
+    theme::Theme,
+    usb_serial,
+};
+
+const MAGIC: u32 = 0x6b_6e_61_70;
+const FILE_LEN: usize = 48;
+const MESSAGE_LEN: usize = 120;
+// panics in a row before we stop resetting, so a crash during boot does not
+// turn into a reset loop
+const MAX_RESETS: u32 = 3;
+
+// right of the gopher
+const LOCATION: Rectangle = Rectangle::new(Point::new(204, 40), Size::new(114, 40));
+const MESSAGE: Rectangle = Rectangle::new(Point::new(204, 86), Size::new(114, 110));
+const FOOTER: Rectangle = Rectangle::new(Point::new(204, 200), Size::new(114, 22));
+
+// the screen left up once it stops resetting
+const HALTED_LOCATION: Rectangle = Rectangle::new(Point::new(10, 40), Size::new(300, 24));
+const HALTED_MESSAGE: Rectangle = Rectangle::new(Point::new(10, 70), Size::new(300, 120));
+const HALTED_FOOTER: Rectangle = Rectangle::new(Point::new(10, 210), Size::new(300, 20));
+// what init_clocks_and_plls runs the system and peripheral clocks at
+const SYSTEM_CLOCK_HZ: u32 = 125_000_000;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Record {
+    magic: u32,
+    // inverted magic, garbage after power-on is unlikely to match both
+    check: u32,
+    // panics since the record was last looked at
+    resets: u32,
+    // non-zero once shown, plain integers keep every bit pattern valid
+    seen: u32,
+    line: u32,
+    file_len: u8,
+    message_len: u8,
+    file: [u8; FILE_LEN],
+    message: [u8; MESSAGE_LEN],
+}
+
+// survives a reset, only power-on leaves it with garbage
+#[unsafe(link_section = ".uninit.CRASH")]
+static mut CRASH: MaybeUninit<Record> = MaybeUninit::uninit();
+
+/// What the last panic left behind.
+#[derive(Clone, Copy)]
+pub struct Crash {
+    record: Record,
+}
+
+impl Crash {
+    pub fn file(&self) -> &str {
+        let len = (self.record.file_len as usize).min(FILE_LEN);
+        core::str::from_utf8(&self.record.file[..len]).unwrap_or("?")
+    }
+
+    pub fn line(&self) -> u32 {
+        self.record.line
+    }
+
+    pub fn message(&self) -> &str {
+        let len = (self.record.message_len as usize).min(MESSAGE_LEN);
+        core::str::from_utf8(&self.record.message[..len]).unwrap_or("?")
+    }
+
+    /// Panics in a row, more than one means it keeps crashing.
+    pub fn resets(&self) -> u32 {
+        self.record.resets
+    }
+}
+
+fn read() -> Option<Record> {
+    // SAFETY: single core and only touched from the foreground or the panic
+    // handler. `Record` is plain integers, so leftover RAM is a valid value,
+    // the magic tells whether it is one we wrote.
+    let record = unsafe { addr_of!(CRASH).read_volatile().assume_init() };
+
+    (record.magic == MAGIC && record.check == !MAGIC).then_some(record)
+}
+
+fn write(record: &Record) {
+    // SAFETY: see `read`
+    unsafe { addr_of_mut!(CRASH).write_volatile(MaybeUninit::new(*record)) }
+}
+
+/// The crash recorded by the previous panic, if any.
+pub fn last() -> Option<Crash> {
+    read().map(|record| Crash { record })
+}
+
+/// Forgets the recorded crash.
+pub fn clear() {
+    if let Some(mut record) = read() {
+        record.magic = 0;
+        write(&record);
+    }
+}
+
+fn mark_seen() {
+    if let Some(mut record) = read() {
+        record.seen = 1;
+        record.resets = 0;
+        write(&record);
+    }
+}
+
+static HALTED: AtomicBool = AtomicBool::new(false);
+
+// stand in for the timer, which may be what panicked
+struct Spin;
+
+impl DelayNs for Spin {
+    fn delay_ns(&mut self, ns: u32) {
+        cortex_m::asm::delay(ns / (1_000_000_000 / SYSTEM_CLOCK_HZ) + 1);
+    }
+}
+
+// last words, on a display set up from scratch since the one main built is
+// out of reach here. Best effort, errors are ignored.
+fn draw_halted(file: &str, line: u32, message: &str) {
+    // a panic in here comes back through the handler, draw only once
+    if HALTED.load(Ordering::Relaxed) {
+        return;
+    }
+    HALTED.store(true, Ordering::Relaxed);
+
+    // SAFETY: interrupts are off and nothing returns from the panic handler,
+    // whoever owned these is never going to touch them again
+    let mut pac = unsafe { pac::Peripherals::steal() };
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let spi = Spi::<_, _, _>::new(
+        pac.SPI0,
+        (
+            pins.gpio19.into_function::<FunctionSpi>(),
+            pins.gpio18.into_function::<FunctionSpi>(),
+        ),
+    )
+    .init(
+        &mut pac.RESETS,
+        SYSTEM_CLOCK_HZ.Hz(),
+        16_000_000u32.Hz(),
+        embedded_hal::spi::MODE_3,
+    );
+    // neither can fail, the spi errors are Infallible
+    let Ok(spi_device) = ExclusiveDevice::new(spi, pins.gpio17.into_push_pull_output(), NoDelay);
+    let mut buffer = [0_u8; 512];
+    let di = SpiInterface::new(spi_device, pins.gpio20.into_push_pull_output(), &mut buffer);
+    let Ok(mut display) = display_builder(di).init(&mut Spin);
+
+    // fixed colors, not the theme, the settings may be what keeps panicking
+    let _ = display.clear(Rgb565::BLACK);
+    let title = MonoTextStyle::new(&FONT_9X18_BOLD, Rgb565::CSS_TOMATO);
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let _ = Text::with_baseline(
+        "the badge keeps panicking",
+        Point::new(10, 10),
+        title,
+        Baseline::Top,
+    )
+    .draw(&mut display);
+    let mut location: String<{ FILE_LEN + 12 }> = String::new();
+    let _ = write!(&mut location, "{}:{}", file, line);
+    let _ = TextBox::new(&location, HALTED_LOCATION, style).draw(&mut display);
+    let _ = TextBox::new(message, HALTED_MESSAGE, style)
+        .with_ellipsis()
+        .draw(&mut display);
+    let _ =
+        TextBox::new("stopped, power cycle to restart", HALTED_FOOTER, style).draw(&mut display);
+
+    // full brightness, whatever the backlight was doing
+    let _ = pins.gpio12.into_push_pull_output_in_state(PinState::High);
+}
+
+fn copy_str<const N: usize>(dst: &mut [u8; N], text: &str) -> u8 {
+    // cut on a char boundary so the copy stays valid utf-8
+    let mut len = text.len().min(N);
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    dst[..len].copy_from_slice(&text.as_bytes()[..len]);
+    len as u8
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+
+    let mut message: String<MESSAGE_LEN> = String::new();
+    // a full buffer keeps what fit
+    let _ = write!(&mut message, "{}", info.message());
+
+    let (file, line) = info
+        .location()
+        .map_or(("?", 0), |location| (location.file(), location.line()));
+
+    defmt::error!(
+        "panic at {=str}:{=u32}: {=str}",
+        file,
+        line,
+        message.as_str()
+    );
+
+    let resets = match read() {
+        Some(previous) if previous.seen == 0 => previous.resets + 1,
+        _ => 1,
+    };
+
+    let mut record = Record {
+        magic: MAGIC,
+        check: !MAGIC,
+        resets,
+        seen: 0,
+        line,
+        file_len: 0,
+        message_len: 0,
+        file: [0; FILE_LEN],
+        message: [0; MESSAGE_LEN],
+    };
+    // the end of the path is the interesting part
+    let mut start = file.len().saturating_sub(FILE_LEN);
+    while !file.is_char_boundary(start) {
+        start += 1;
+    }
+    let file = &file[start..];
+    record.file_len = copy_str(&mut record.file, file);
+    record.message_len = copy_str(&mut record.message, &message);
+    write(&record);
+
+    if resets > MAX_RESETS {
+        // SAFETY: nothing else runs any more, and the supervisor would reset
+        // us straight back into the loop we are trying to stop
+        unsafe {
+            (*pac::WATCHDOG::ptr())
+                .ctrl()
+                .modify(|_, w| w.enable().clear_bit());
+        }
+        // after the watchdog is off, bringing up the display takes a while
+        draw_halted(file, line, &message);
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    // the crash screen is drawn by the next boot, with a working display
+    SCB::sys_reset();
+}
+
+/// Prints the recorded crash on the USB serial port.
+pub fn report() {
+    let mut buf: String<{ FILE_LEN + MESSAGE_LEN + 48 }> = String::new();
+    let _ = match last() {
+        Some(crash) => write!(
+            &mut buf,
+            "panic at {}:{} ({} in a row)\r\n  {}\r\n",
+            crash.file(),
+            crash.line(),
+            crash.resets().max(1),
+            crash.message()
+        ),
+        None => write!(&mut buf, "no crash recorded\r\n"),
+    };
+    usb_serial::write_str(&buf);
+}
+
+// returns how many pages the message takes
+fn draw<D, C>(
+    display: &mut D,
+    crash: Option<&Crash>,
+    page: usize,
+    theme: &Theme<C>,
+) -> Result<usize, BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    display
+        .clear(theme.background)
+        .map_err(BadgeError::display)?;
+
+    let gopher: Bmp<C> = bmp::load(GOPHER_PANIC)?;
+    Image::new(&gopher, Point::new(0, 40))
+        .draw(display)
+        .map_err(BadgeError::display)?;
+
+    let title_style = MonoTextStyleBuilder::new()
+        .font(&FONT_9X18_BOLD)
+        .text_color(theme.accent)
+        .build();
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(theme.foreground)
+        .build();
+
+    let title = if crash.is_some() {
+        "the badge panicked"
+    } else {
+        "no crash recorded"
+    };
+    Text::with_baseline(title, Point::new(10, 10), title_style, Baseline::Top)
+        .draw(display)
+        .map_err(BadgeError::display)?;
+
+    let Some(crash) = crash else {
+        TextBox::new("B: back", LOCATION, style)
+            .draw(display)
+            .map_err(BadgeError::display)?;
+        return Ok(1);
+    };
+
+    let mut location: String<{ FILE_LEN + 12 }> = String::new();
+    let _ = write!(&mut location, "{}:{}", crash.file(), crash.line());
+    TextBox::new(&location, LOCATION, style)
+        .with_ellipsis()
+        .draw(display)
+        .map_err(BadgeError::display)?;
+
+    let message = TextBox::new(crash.message(), MESSAGE, style);
+    let pages = message.pages();
+    message
+        .with_page(page)
+        .draw(display)
+        .map_err(BadgeError::display)?;
+
+    // kept to the bottom however many lines it takes
+    let mut footer: String<48> = String::new();
+    if pages > 1 {
+        let _ = writeln!(&mut footer, "Up/Down: {}/{}", page + 1, pages);
+    }
+    let _ = footer.push_str("A: clear  B: back");
+    TextBox::new(&footer, FOOTER, style)
+        .with_vertical_alignment(VerticalAlignment::Bottom)
+        .draw(display)
+        .map_err(BadgeError::display)?;
+    Ok(pages)
+}
+
+/// Shows the recorded crash until B leaves, A forgets it. Up/Down page
+/// through a message too long for the screen. Looking at it resets the
+/// reset-loop guard.
+pub fn crash_report<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    let mut crash = last();
+    let mut page = 0;
+    mark_seen();
+    let mut pages = draw(display, crash.as_ref(), page, theme)?;
+
+    loop {
+        let input = buttons.poll();
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            draw(display, crash.as_ref(), page, theme)?;
+        }
+
+        if input.pressed.a && crash.is_some() {
+            clear();
+            crash = None;
+            page = 0;
+            pages = draw(display, crash.as_ref(), page, theme)?;
+        }
+
+        let turned = if input.pressed.down && page + 1 < pages {
+            page + 1
+        } else if input.pressed.up && page > 0 {
+            page - 1
+        } else {
+            page
+        };
+        if turned != page {
+            page = turned;
+            draw(display, crash.as_ref(), page, theme)?;
+        }
+
+        if input.released.b {
+            return Ok(());
+        }
+
+        delay.delay_ms(10);
+    }
+}
+
+/// At boot, shows a crash nobody has looked at yet.
+pub fn check<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    if let Some(crash) = last()
+        && crash.record.seen == 0
+    {
+        crate::log::log_error!("last boot panicked at {}:{}", crash.file(), crash.line());
+        return crash_report(display, delay, buttons, theme);
+    }
+
+    Ok(())
+}
@@ -1,47 +1,98 @@
 use embedded_graphics::{
+    Drawable,
     image::Image,
-    mono_font::{ascii, MonoFont, MonoTextStyle},
-    pixelcolor::Rgb565,
+    mono_font::{MonoFont, MonoTextStyle, ascii},
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
     prelude::{Dimensions, DrawTarget, Point, RgbColor},
     primitives::Rectangle,
-    text::{renderer::CharacterStyle, Text},
-    Drawable,
+    text::{Text, renderer::CharacterStyle},
 };
 use heapless::Vec;
 use tinybmp::Bmp;
 
-pub trait Michelangelo<D> {
+use crate::error::BadgeError;
+
+/// Retained drawing: everything drawn through it is remembered, so `clear`
+/// only repaints what was drawn instead of the whole screen. For apps whose
+/// layout changes while they run, the others paint over their old content
+/// and only clear the screen when they start or the console overlay was up.
+pub trait Michelangelo<D>
+where
+    D: DrawTarget,
+{
+    #[allow(clippy::too_many_arguments)]
     fn text(
         &mut self,
         display: &mut D,
         text: &str,
         x: i32,
         y: i32,
-        font: Option<MonoFont>,
-        text_color: Option<Rgb565>,
-        bg_color: Option<Rgb565>,
-    );
+        font: Option<&MonoFont<'static>>,
+        text_color: Option<D::Color>,
+        bg_color: Option<D::Color>,
+    ) -> Result<(), BadgeError>;
 
-    fn bmp(&mut self, display: &mut D, bytes: &[u8], x: i32, y: i32);
+    fn bmp(&mut self, display: &mut D, bytes: &[u8], x: i32, y: i32) -> Result<(), BadgeError>;
 
-    fn clear(&mut self, display: &mut D);
+    fn draw<T>(&mut self, display: &mut D, element: &T) -> Result<(), BadgeError>
+    where
+        T: Drawable<Color = D::Color> + Dimensions;
+
+    fn clear(&mut self, display: &mut D) -> Result<(), BadgeError>;
 }
 
-pub struct Draw {
+pub struct Draw<C> {
     cleanup: Vec<Rectangle, 32>,
+    background: C,
 }
 
-impl Draw {
-    pub fn new() -> Self {
+impl<C> Draw<C> {
+    pub fn new(background: C) -> Self {
         Draw {
             cleanup: Vec::new(),
+            background,
+        }
+    }
+
+    /// Remembers an area drawn straight to the display, for `clear`.
+    pub fn track(&mut self, area: Rectangle) {
+        // redrawing the same spot (e.g. a status line) must not fill the list
+        if self
+            .cleanup
+            .iter()
+            .any(|tracked| tracked.intersection(&area) == area)
+        {
+            return;
+        }
+
+        if let Err(area) = self.cleanup.push(area) {
+            // out of slots, grow the last one to cover the new area too
+            let last = self.cleanup.last_mut().unwrap();
+            *last = bounding_union(last, &area);
         }
     }
 }
 
-impl<D> Michelangelo<D> for Draw
+fn bounding_union(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    let a_bottom_right = a.bottom_right().unwrap_or(a.top_left);
+    let b_bottom_right = b.bottom_right().unwrap_or(b.top_left);
+
+    Rectangle::with_corners(
+        Point::new(
+            a.top_left.x.min(b.top_left.x),
+            a.top_left.y.min(b.top_left.y),
+        ),
+        Point::new(
+            a_bottom_right.x.max(b_bottom_right.x),
+            a_bottom_right.y.max(b_bottom_right.y),
+        ),
+    )
+}
+
+impl<D, C> Michelangelo<D> for Draw<C>
 where
-    D: DrawTarget<Color = Rgb565>,
+    C: RgbColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
     D::Error: core::fmt::Debug,
 {
     fn text(
@@ -50,31 +101,43 @@ where
         text: &str,
         x: i32,
         y: i32,
-        font: Option<MonoFont>,
-        text_color: Option<Rgb565>,
-        bg_color: Option<Rgb565>,
-    ) {
-        let font = font.unwrap_or(ascii::FONT_6X13);
-        let text_color = text_color.unwrap_or(Rgb565::WHITE);
-
-        let mut style = MonoTextStyle::new(&font, text_color);
+        font: Option<&MonoFont<'static>>,
+        text_color: Option<C>,
+        bg_color: Option<C>,
+    ) -> Result<(), BadgeError> {
+        let font = font.unwrap_or(&ascii::FONT_6X13);
+        let text_color = text_color.unwrap_or(C::WHITE);
+
+        let mut style = MonoTextStyle::new(font, text_color);
         style.set_background_color(bg_color);
 
         let text = Text::new(text, Point::new(x, y), style);
-        text.draw(display).unwrap();
-
-        self.cleanup.push(text.bounding_box()).unwrap();
+        self.draw(display, &text)
     }
 
-    fn bmp(&mut self, display: &mut D, bytes: &[u8], x: i32, y: i32) {
-        let img: Bmp<Rgb565> = Bmp::from_slice(bytes).unwrap();
+    fn bmp(&mut self, display: &mut D, bytes: &[u8], x: i32, y: i32) -> Result<(), BadgeError> {
+        let img: Bmp<C> = Bmp::from_slice(bytes).map_err(BadgeError::asset)?;
         let img = Image::new(&img, Point::new(x, y));
-        img.draw(display).unwrap();
+        self.draw(display, &img)
+    }
 
-        self.cleanup.push(img.bounding_box()).unwrap();
+    fn draw<T>(&mut self, display: &mut D, element: &T) -> Result<(), BadgeError>
+    where
+        T: Drawable<Color = C> + Dimensions,
+    {
+        // tracked first, a failed draw may still have painted something
+        self.track(element.bounding_box());
+        element.draw(display).map_err(BadgeError::display)?;
+        Ok(())
     }
 
-    fn clear(&mut self, display: &mut D) {
-        display.clear(Rgb565::BLACK).unwrap();
+    fn clear(&mut self, display: &mut D) -> Result<(), BadgeError> {
+        for area in self.cleanup.iter() {
+            display
+                .fill_solid(area, self.background)
+                .map_err(BadgeError::display)?;
+        }
+        self.cleanup.clear();
+        Ok(())
     }
 }
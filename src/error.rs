@@ -0,0 +1,153 @@
+use core::fmt::Debug;
+
+use embedded_graphics::{
+    Drawable,
+    mono_font::{
+        MonoTextStyleBuilder,
+        ascii::{FONT_6X10, FONT_9X18_BOLD},
+    },
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, RgbColor, Size, WebColors},
+    primitives::Rectangle,
+    text::{Alignment, Text},
+};
+use embedded_hal::delay::DelayNs;
+
+use crate::{
+    console,
+    input::Buttons,
+    log::log_error,
+    power::Delay,
+    text_box::{TextBox, VerticalAlignment},
+};
+
+/// Everything that can go wrong talking to the badge hardware. The driver
+/// error behind it is logged where it happens, see the constructors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BadgeError {
+    Display,
+    Accelerometer,
+    Led,
+    /// an embedded image or file did not parse
+    Asset,
+    /// the settings or the schedule could not be written to flash
+    Storage,
+}
+
+impl BadgeError {
+    pub fn display<E: Debug>(error: E) -> Self {
+        Self::Display.logged(error)
+    }
+
+    pub fn accelerometer<E: Debug>(error: E) -> Self {
+        Self::Accelerometer.logged(error)
+    }
+
+    pub fn led<E: Debug>(error: E) -> Self {
+        Self::Led.logged(error)
+    }
+
+    pub fn asset<E: Debug>(error: E) -> Self {
+        Self::Asset.logged(error)
+    }
+
+    pub fn storage<E: Debug>(error: E) -> Self {
+        Self::Storage.logged(error)
+    }
+
+    pub fn logged<E: Debug>(self, error: E) -> Self {
+        log_error!("{}: {:?}", self.name(), error);
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BadgeError::Display => "display",
+            BadgeError::Accelerometer => "accelerometer",
+            BadgeError::Led => "leds",
+            BadgeError::Asset => "asset",
+            BadgeError::Storage => "storage",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            BadgeError::Display => "the display did not take a command",
+            BadgeError::Accelerometer => "the accelerometer is not answering",
+            BadgeError::Led => "the neopixels could not be driven",
+            BadgeError::Asset => "a file in the firmware is corrupt",
+            BadgeError::Storage => "the settings or schedule could not be saved",
+        }
+    }
+}
+
+fn draw_error<D, C>(display: &mut D, error: BadgeError) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    display.clear(C::CSS_DARK_RED)?;
+
+    let center = display.bounding_box().center();
+    let style = |font, color| {
+        MonoTextStyleBuilder::new()
+            .font(font)
+            .text_color(color)
+            .build()
+    };
+
+    Text::with_alignment(
+        error.name(),
+        center - Point::new(0, 30),
+        style(&FONT_9X18_BOLD, C::WHITE),
+        Alignment::Center,
+    )
+    .draw(display)?;
+    TextBox::new(
+        error.description(),
+        Rectangle::with_center(center, Size::new(280, 30)),
+        style(&FONT_6X10, C::WHITE),
+    )
+    .with_alignment(Alignment::Center)
+    .with_vertical_alignment(VerticalAlignment::Middle)
+    .draw(display)?;
+    Text::with_alignment(
+        "B: back to the menu  B+Up: console",
+        center + Point::new(0, 40),
+        style(&FONT_6X10, C::CSS_LIGHT_GRAY),
+        Alignment::Center,
+    )
+    .draw(display)?;
+
+    Ok(())
+}
+
+/// Tells the user an app failed, until B goes back to the menu. Drawing is
+/// best-effort, the display may well be what failed.
+pub fn error_screen<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    error: BadgeError,
+) where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    let _ = draw_error(display, error);
+
+    loop {
+        let input = buttons.poll();
+
+        if input.console {
+            let _ = console::overlay(display, delay, buttons);
+            let _ = draw_error(display, error);
+        }
+
+        if input.released.b {
+            break;
+        }
+
+        delay.delay_ms(10);
+    }
+}
@@ -0,0 +1,197 @@
+// Bitmap fonts with a width per glyph and more than ascii, for names like
+// José, Zoë or Łukasz. The tables are made from the BDF files in
+// `assets/fonts` by `build.rs`. `FontStyle` renders them through
+// embedded-graphics' `Text`, so alignment and baselines work as with the
+// mono fonts.
+
+use embedded_graphics::{
+    Pixel,
+    prelude::{DrawTarget, PixelColor, Point, Size},
+    primitives::Rectangle,
+    text::{
+        Baseline,
+        renderer::{TextMetrics, TextRenderer},
+    },
+};
+
+pub struct Glyph {
+    pub c: char,
+    /// how far the next glyph starts
+    pub advance: u8,
+    /// where the bitmap sits in the cell
+    pub left: i8,
+    pub top: i8,
+    pub width: u8,
+    pub height: u8,
+    /// into `Font::bitmaps`, rows are whole bytes with the first pixel in
+    /// the top bit
+    pub offset: u32,
+}
+
+pub struct Font {
+    pub ascent: u8,
+    pub descent: u8,
+    /// drawn for characters the font does not have
+    pub fallback: usize,
+    /// sorted by `c`
+    pub glyphs: &'static [Glyph],
+    pub bitmaps: &'static [u8],
+}
+
+include!(concat!(env!("OUT_DIR"), "/fonts.rs"));
+
+impl Glyph {
+    fn ink(&self, font: &Font, x: i32, y: i32) -> bool {
+        let (x, y) = (x - self.left as i32, y - self.top as i32);
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return false;
+        }
+        let stride = (self.width as usize).div_ceil(8);
+        let byte = font.bitmaps[self.offset as usize + y as usize * stride + x as usize / 8];
+        byte & (0x80 >> (x % 8)) != 0
+    }
+}
+
+impl Font {
+    pub fn glyph(&self, c: char) -> &Glyph {
+        let i = self
+            .glyphs
+            .binary_search_by_key(&c, |glyph| glyph.c)
+            .unwrap_or(self.fallback);
+        &self.glyphs[i]
+    }
+
+    pub fn height(&self) -> u32 {
+        self.ascent as u32 + self.descent as u32
+    }
+
+    /// Width of `text` in pixels.
+    pub fn width(&self, text: &str) -> u32 {
+        text.chars().map(|c| self.glyph(c).advance as u32).sum()
+    }
+}
+
+/// Draws a `Font` in a color, on a background if there is one.
+#[derive(Clone, Copy)]
+pub struct FontStyle<'a, C> {
+    font: &'a Font,
+    color: C,
+    background: Option<C>,
+}
+
+impl<'a, C: PixelColor> FontStyle<'a, C> {
+    pub fn new(font: &'a Font, color: C) -> Self {
+        Self {
+            font,
+            color,
+            background: None,
+        }
+    }
+
+    /// Fills every glyph cell, so text can be redrawn in place.
+    pub fn with_background(self, background: C) -> Self {
+        Self {
+            background: Some(background),
+            ..self
+        }
+    }
+
+    // top of the cells for a `position` on `baseline`
+    fn top(&self, position: Point, baseline: Baseline) -> i32 {
+        let height = self.font.height() as i32;
+        position.y
+            - match baseline {
+                Baseline::Top => 0,
+                Baseline::Bottom => height - 1,
+                Baseline::Middle => (height - 1) / 2,
+                Baseline::Alphabetic => self.font.ascent as i32 - 1,
+            }
+    }
+}
+
+impl<C: PixelColor> TextRenderer for FontStyle<'_, C> {
+    type Color = C;
+
+    fn draw_string<D>(
+        &self,
+        text: &str,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let top = self.top(position, baseline);
+        let height = self.font.height();
+        let mut x = position.x;
+
+        for c in text.chars() {
+            let glyph = self.font.glyph(c);
+            let cell = Rectangle::new(Point::new(x, top), Size::new(glyph.advance as u32, height));
+            let ink = |point: Point| glyph.ink(self.font, point.x - x, point.y - top);
+            match self.background {
+                // every pixel of the cell in one go
+                Some(background) => target.fill_contiguous(
+                    &cell,
+                    cell.rows().flat_map(|y| {
+                        cell.columns().map(move |x| {
+                            if ink(Point::new(x, y)) {
+                                self.color
+                            } else {
+                                background
+                            }
+                        })
+                    }),
+                )?,
+                None => target.draw_iter(
+                    cell.rows()
+                        .flat_map(|y| cell.columns().map(move |x| Point::new(x, y)))
+                        .filter(|point| ink(*point))
+                        .map(|point| Pixel(point, self.color)),
+                )?,
+            }
+            x += glyph.advance as i32;
+        }
+
+        Ok(Point::new(x, position.y))
+    }
+
+    fn draw_whitespace<D>(
+        &self,
+        width: u32,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        if let Some(background) = self.background {
+            let top = self.top(position, baseline);
+            target.fill_solid(
+                &Rectangle::new(
+                    Point::new(position.x, top),
+                    Size::new(width, self.font.height()),
+                ),
+                background,
+            )?;
+        }
+        Ok(position + Point::new(width as i32, 0))
+    }
+
+    fn measure_string(&self, text: &str, position: Point, baseline: Baseline) -> TextMetrics {
+        let width = self.font.width(text);
+        TextMetrics {
+            bounding_box: Rectangle::new(
+                Point::new(position.x, self.top(position, baseline)),
+                Size::new(width, self.font.height()),
+            ),
+            next_position: position + Point::new(width as i32, 0),
+        }
+    }
+
+    fn line_height(&self) -> u32 {
+        self.font.height()
+    }
+}
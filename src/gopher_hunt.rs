@@ -1,30 +1,37 @@
 use accelerometer::Accelerometer;
-use cortex_m::delay::Delay;
 use embedded_graphics::{
-    Drawable,
-    image::Image,
     pixelcolor::{Rgb555, Rgb565, Rgb888},
     prelude::{DrawTarget, Point, RgbColor, WebColors},
 };
-use embedded_hal::{delay::DelayNs, digital::InputPin};
-use embedded_hal_compat::Forward;
+use embedded_hal::delay::DelayNs;
 use lis3dh::{Lis3dh, Lis3dhCore};
 use micromath::F32Ext;
 use rand_chacha::ChaCha8Rng;
 use rand_core::{RngCore, SeedableRng};
-use rp2040_hal::gpio::{
-    FunctionSio, Pin, PullDown, SioInput,
-    bank0::{Gpio10, Gpio11},
-};
+use smart_leds::RGB8;
 use tinybmp::Bmp;
 
 use crate::{
-    GOPHER_DEAD, GOPHER_HEAD, RUST_CRAB, TFT_DISPLAY_HEIGHT, TFT_DISPLAY_WIDTH,
-    bmp::BmpExt,
+    CONTENT_HEIGHT, GOPHER_DEAD, GOPHER_HEAD, NEOPIXEL_COUNT, RUST_CRAB, TFT_DISPLAY_WIDTH,
+    backlight::{self, Panel},
+    bmp::{self, BmpExt},
+    console,
+    draw::{Draw, Michelangelo},
+    error::BadgeError,
+    input::Buttons,
+    leds,
     log::{log_accel, log_angle},
+    power::Delay,
+    settings,
     sprite::{Sprite, SpriteBuilder},
+    status::StatusBar,
+    supervisor::Faults,
+    theme::Theme,
 };
 
+// log the accelerometer every this many frames
+const ACCEL_LOG_EVERY: u32 = 50;
+
 fn accel_to_angle_deg(x: f32, y: f32) -> f32 {
     let radians = y.atan2(x);
     radians.to_degrees() - 90.0
@@ -92,90 +99,143 @@ where
     let new_top_left = Point::new(center.x - half_width, center.y - half_height);
 
     let wrapped_x = new_top_left.x.rem_euclid(TFT_DISPLAY_WIDTH as i32);
-    let wrapped_y = new_top_left.y.rem_euclid(TFT_DISPLAY_HEIGHT as i32);
+    let wrapped_y = new_top_left.y.rem_euclid(CONTENT_HEIGHT as i32);
 
     Point::new(wrapped_x, wrapped_y)
 }
 
 pub fn gopher_hunt<D, C, L>(
     display: &mut D,
-    delay: &mut Forward<Delay>,
+    delay: &mut Delay,
     lis3dh: &mut Lis3dh<L>,
-    a_btn_pin: &mut Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
-    b_btn_pin: &mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
-) where
+    buttons: &mut Buttons,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
     C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
-    D: DrawTarget<Color = C>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
     D::Error: core::fmt::Debug,
     L: Lis3dhCore,
     L::PinError: core::fmt::Debug,
     L::BusError: core::fmt::Debug,
 {
-    display.clear(C::BLACK).unwrap();
-    let player_bmp = Bmp::from_slice(RUST_CRAB).unwrap();
+    display
+        .clear(theme.background)
+        .map_err(BadgeError::display)?;
+    let player_bmp: Bmp<C> = bmp::load(RUST_CRAB)?;
     let mut player_position = player_bmp.screen_center();
     let mut player = SpriteBuilder::builder(&player_bmp)
         .with_position(player_position)
         .with_screen_boundaries()
         .build();
 
-    let gopher_bmp = Bmp::from_slice(GOPHER_HEAD).unwrap();
+    let gopher_bmp = bmp::load(GOPHER_HEAD)?;
     let mut gopher = SpriteBuilder::builder(&gopher_bmp)
         .with_position(Point::new(100, 100))
         .with_screen_boundaries()
         .build();
 
-    gopher.draw(display, 0.0);
+    gopher.draw(display, 0.0).map_err(BadgeError::display)?;
 
     let mut rng = ChaCha8Rng::seed_from_u64(0x12345678);
+    let mut painter = Draw::new(theme.background);
     let mut draw = false;
     let mut dead = false;
+    let mut frame: u32 = 0;
+    let mut faults = Faults::new(BadgeError::Accelerometer);
+    let tilt = settings::current().tilt as f32;
 
     loop {
+        let input = buttons.poll();
+        frame = frame.wrapping_add(1);
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            display
+                .clear(theme.background)
+                .map_err(BadgeError::display)?;
+            painter = Draw::new(theme.background);
+            gopher.draw(display, 0.0).map_err(BadgeError::display)?;
+            // puts the dead gopher back up
+            draw = dead;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, lis3dh)?;
+        }
+        display.update_status()?;
+
+        if input.released.b {
+            return Ok(());
+        }
+
         if dead {
             if draw {
-                display.clear(C::WHITE).unwrap();
-                let gopher_dead = Bmp::from_slice(GOPHER_DEAD).unwrap();
-                Image::new(&gopher_dead, gopher_dead.screen_center())
-                    .draw(display)
-                    .unwrap();
+                let position = bmp::load::<C>(GOPHER_DEAD)?.screen_center();
+                painter.bmp(display, GOPHER_DEAD, position.x, position.y)?;
+                painter.text(
+                    display,
+                    "press A to play again",
+                    position.x,
+                    CONTENT_HEIGHT as i32 - 10,
+                    None,
+                    Some(theme.foreground),
+                    Some(theme.background),
+                )?;
                 draw = false;
             }
 
             delay.delay_ms(100);
 
-            if a_btn_pin.is_low().unwrap() {
+            if input.pressed.a {
                 dead = false;
-                gopher.move_to(display, &mut gopher_bmp.screen_center(), C::BLACK, 0.0);
-                display.clear(C::BLACK).unwrap();
+                painter.clear(display)?;
+                gopher
+                    .move_to(
+                        display,
+                        &mut gopher_bmp.screen_center(),
+                        theme.background,
+                        0.0,
+                    )
+                    .map_err(BadgeError::display)?;
             }
             continue;
         }
 
-        let accel = lis3dh.accel_norm().unwrap();
+        let Some(accel) = faults.check(lis3dh.accel_norm())? else {
+            continue;
+        };
+
+        backlight::motion(&accel);
 
-        log_accel(display, &accel);
+        let log = frame.is_multiple_of(ACCEL_LOG_EVERY);
+        if log {
+            log_accel(&accel);
+        }
 
-        player_position.x -= (accel.x * 10.0) as i32;
-        player_position.y -= (accel.y * 10.0) as i32;
+        player_position.x -= (accel.x * tilt) as i32;
+        player_position.y -= (accel.y * tilt) as i32;
 
         let angle = accel_to_angle_deg(accel.x, accel.y);
 
-        log_angle(display, angle);
+        if log {
+            log_angle(angle);
+        }
 
-        player.move_to(display, &mut player_position, C::BLACK, angle);
+        player
+            .move_to(display, &mut player_position, theme.background, angle)
+            .map_err(BadgeError::display)?;
         delay.delay_ms(1);
 
         let mut new_pos = run_away_from(&gopher, &player, &mut rng, 6, 70);
-        gopher.move_to(display, &mut new_pos, C::BLACK, 0.0);
+        gopher
+            .move_to(display, &mut new_pos, theme.background, 0.0)
+            .map_err(BadgeError::display)?;
 
         if is_colliding(&gopher, &player) {
+            leds::flash([RGB8::new(255, 0, 0); NEOPIXEL_COUNT], 1000);
             draw = true;
             dead = true;
         }
-
-        if b_btn_pin.is_low().unwrap() {
-            break;
-        }
     }
 }
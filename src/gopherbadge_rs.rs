@@ -1,33 +1,81 @@
-use cortex_m::delay::Delay;
 use embedded_graphics::{
     pixelcolor::{Rgb555, Rgb565, Rgb888},
     prelude::{DrawTarget, Point, RgbColor, WebColors},
 };
-use embedded_hal::{delay::DelayNs, digital::InputPin};
-use embedded_hal_compat::Forward;
-use rp2040_hal::gpio::{FunctionSio, Pin, PullDown, SioInput, bank0::Gpio11};
-use tinybmp::Bmp;
+use embedded_hal::delay::DelayNs;
 
-use crate::{GOPHERBADGE_RS, sprite::SpriteBuilder};
+use crate::{
+    GOPHERBADGE_RS,
+    backlight::{self, Panel, Tap},
+    bmp, console,
+    error::BadgeError,
+    input::Buttons,
+    power::{self, Delay, Speed},
+    sprite::SpriteBuilder,
+    status::StatusBar,
+};
 
-pub fn gopherbadge_rs<D, C>(
-    display: &mut D,
-    delay: &mut Forward<Delay>,
-    b_btn_pin: &mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
-) where
+fn draw_background<D, C>(display: &mut D) -> Result<(), BadgeError>
+where
     C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
     D: DrawTarget<Color = C>,
     D::Error: core::fmt::Debug,
 {
-    SpriteBuilder::builder(&Bmp::from_slice(GOPHERBADGE_RS).unwrap())
+    SpriteBuilder::builder(&bmp::load(GOPHERBADGE_RS)?)
         .with_position(Point::new(0, 0))
         .build()
-        .draw(display, 0.0);
+        .draw(display, 0.0)
+        .map_err(BadgeError::display)
+}
+
+/// The badge page. Nothing moves on it, so it runs on a reduced system clock.
+pub fn gopherbadge_rs<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+{
+    draw_background(display)?;
+
+    power::set_speed(Speed::Reduced);
+    let result = show(display, delay, buttons, tap);
+    power::set_speed(Speed::Full);
 
+    result
+}
+
+fn show<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+{
     loop {
-        if b_btn_pin.is_low().unwrap() {
-            break;
+        let input = buttons.poll();
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            draw_background(display)?;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, tap)?;
+        }
+        display.update_status()?;
+
+        if input.released.b {
+            return Ok(());
         }
-        delay.delay_ms(100);
+        delay.delay_ms(10);
     }
 }
@@ -71,10 +71,13 @@ where
                 let x_src_i = x_src.round().to_num::<i32>();
                 let y_src_i = y_src.round().to_num::<i32>();
 
-                if x_src_i >= 0 && x_src_i < w && y_src_i >= 0 && y_src_i < h {
-                    if let Some(color) = self.bmp.pixel(Point::new(x_src_i, y_src_i)) {
-                        display.draw_iter([Pixel(self.pos + Point::new(x_out, y_out), color)])?;
-                    }
+                if x_src_i >= 0
+                    && x_src_i < w
+                    && y_src_i >= 0
+                    && y_src_i < h
+                    && let Some(color) = self.bmp.pixel(Point::new(x_src_i, y_src_i))
+                {
+                    display.draw_iter([Pixel(self.pos + Point::new(x_out, y_out), color)])?;
                 }
             }
         }
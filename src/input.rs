@@ -0,0 +1,188 @@
+use embedded_hal::digital::InputPin;
+use rp2040_hal::{
+    Timer,
+    fugit::Instant,
+    gpio::{
+        FunctionSio, Interrupt, Pin, PullDown, SioInput,
+        bank0::{Gpio10, Gpio11, Gpio22, Gpio23, Gpio24, Gpio25},
+    },
+};
+
+use crate::{agenda, backlight, battery, settings, supervisor};
+
+// how long B+Up have to be held together to open the console
+const CONSOLE_CHORD_MS: u64 = 400;
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct ButtonState {
+    pub a: bool,
+    pub b: bool,
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+impl ButtonState {
+    fn edges(from: &Self, to: &Self) -> Self {
+        Self {
+            a: !from.a && to.a,
+            b: !from.b && to.b,
+            up: !from.up && to.up,
+            down: !from.down && to.down,
+            left: !from.left && to.left,
+            right: !from.right && to.right,
+        }
+    }
+}
+
+/// One read of the buttons.
+#[derive(Clone, Copy, Default)]
+pub struct Input {
+    /// down right now
+    pub held: ButtonState,
+    /// went down since the previous poll
+    pub pressed: ButtonState,
+    /// went up since the previous poll, buttons that were part of a chord
+    /// are not reported
+    pub released: ButtonState,
+    /// B+Up were held long enough to open the console
+    pub console: bool,
+    /// nothing happened for the sleep timeout, see `backlight::sleep`
+    pub sleep: bool,
+}
+
+pub struct Buttons {
+    a: Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
+    b: Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
+    down: Pin<Gpio23, FunctionSio<SioInput>, PullDown>,
+    up: Pin<Gpio24, FunctionSio<SioInput>, PullDown>,
+    left: Pin<Gpio25, FunctionSio<SioInput>, PullDown>,
+    right: Pin<Gpio22, FunctionSio<SioInput>, PullDown>,
+    timer: Timer,
+    held: ButtonState,
+    chord_since: Option<Instant<u64, 1, 1_000_000>>,
+    chord_fired: bool,
+    chorded: bool,
+}
+
+impl Buttons {
+    pub fn new(
+        a: Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
+        b: Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
+        down: Pin<Gpio23, FunctionSio<SioInput>, PullDown>,
+        up: Pin<Gpio24, FunctionSio<SioInput>, PullDown>,
+        left: Pin<Gpio25, FunctionSio<SioInput>, PullDown>,
+        right: Pin<Gpio22, FunctionSio<SioInput>, PullDown>,
+        timer: Timer,
+    ) -> Self {
+        Self {
+            a,
+            b,
+            down,
+            up,
+            left,
+            right,
+            timer,
+            held: ButtonState::default(),
+            chord_since: None,
+            chord_fired: false,
+            chorded: false,
+        }
+    }
+
+    fn read(&mut self) -> ButtonState {
+        ButtonState {
+            // the sio pins cannot fail to read
+            a: matches!(self.a.is_low(), Ok(true)),
+            b: matches!(self.b.is_low(), Ok(true)),
+            up: matches!(self.up.is_low(), Ok(true)),
+            down: matches!(self.down.is_low(), Ok(true)),
+            left: matches!(self.left.is_low(), Ok(true)),
+            right: matches!(self.right.is_low(), Ok(true)),
+        }
+    }
+
+    /// Lets a button press wake the chip from dormant.
+    pub fn set_dormant_wake(&mut self, enabled: bool) {
+        // pressed reads low
+        self.a.set_dormant_wake_enabled(Interrupt::EdgeLow, enabled);
+        self.b.set_dormant_wake_enabled(Interrupt::EdgeLow, enabled);
+        self.up
+            .set_dormant_wake_enabled(Interrupt::EdgeLow, enabled);
+        self.down
+            .set_dormant_wake_enabled(Interrupt::EdgeLow, enabled);
+        self.left
+            .set_dormant_wake_enabled(Interrupt::EdgeLow, enabled);
+        self.right
+            .set_dormant_wake_enabled(Interrupt::EdgeLow, enabled);
+
+        // the edge that woke us stays latched otherwise
+        self.a.clear_interrupt(Interrupt::EdgeLow);
+        self.b.clear_interrupt(Interrupt::EdgeLow);
+        self.up.clear_interrupt(Interrupt::EdgeLow);
+        self.down.clear_interrupt(Interrupt::EdgeLow);
+        self.left.clear_interrupt(Interrupt::EdgeLow);
+        self.right.clear_interrupt(Interrupt::EdgeLow);
+    }
+
+    /// Keeps the buttons held right now from being reported when they come
+    /// back up, for a press that was already used up, e.g. waking the badge.
+    pub fn swallow(&mut self) {
+        if self.held != ButtonState::default() {
+            self.chorded = true;
+        }
+    }
+
+    /// Reads the buttons. Every app loop calls this once per frame, so it is
+    /// also where the foreground tells the supervisor it is still alive, where
+    /// the backlight times out, where the battery is sampled and where an
+    /// uploaded schedule and settings changed over usb are written to flash.
+    pub fn poll(&mut self) -> Input {
+        supervisor::checkin();
+        battery::update();
+        agenda::store();
+        settings::flush();
+
+        let held = self.read();
+        if held != ButtonState::default() {
+            backlight::activity();
+        }
+        let sleep = backlight::update();
+        let pressed = ButtonState::edges(&self.held, &held);
+        let mut released = ButtonState::edges(&held, &self.held);
+        let mut console = false;
+
+        if held.b && held.up {
+            self.chorded = true;
+            let now = self.timer.get_counter();
+            let since = *self.chord_since.get_or_insert(now);
+            // fires once per hold
+            if !self.chord_fired && (now - since).to_millis() >= CONSOLE_CHORD_MS {
+                console = true;
+                self.chord_fired = true;
+            }
+        } else {
+            self.chord_since = None;
+        }
+
+        // the buttons of a chord are not reported when they come back up
+        if self.chorded {
+            released = ButtonState::default();
+            if held == ButtonState::default() {
+                self.chorded = false;
+                self.chord_fired = false;
+            }
+        }
+
+        self.held = held;
+
+        Input {
+            held,
+            pressed,
+            released,
+            console,
+            sleep,
+        }
+    }
+}
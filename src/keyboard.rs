@@ -0,0 +1,374 @@
+use embedded_graphics::{
+    Drawable,
+    mono_font::{
+        MonoTextStyle,
+        ascii::{FONT_6X10, FONT_9X15},
+    },
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, Primitive, RgbColor, Size, WebColors},
+    primitives::{PrimitiveStyle, PrimitiveStyleBuilder, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+use embedded_hal::delay::DelayNs;
+use heapless::String;
+
+use crate::{
+    TFT_DISPLAY_WIDTH,
+    backlight::{self, Panel, Tap},
+    console,
+    error::BadgeError,
+    font::FontStyle,
+    input::Buttons,
+    power::Delay,
+    status::StatusBar,
+    theme::Theme,
+};
+
+const PROMPT_Y: i32 = 8;
+const ENTRY: Rectangle = Rectangle::new(Point::new(4, 18), Size::new(312, 26));
+
+const COLUMNS: usize = 10;
+const ROWS: usize = 4;
+const KEYS_TOP: i32 = 52;
+const KEY: Size = Size::new(32, 34);
+const KEY_GAP: u32 = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Layout {
+    Qwerty,
+    Abc,
+}
+
+// [layout][shift][row]
+const KEYS: [[[&str; ROWS]; 2]; 2] = [
+    [
+        ["1234567890", "qwertyuiop", "asdfghjkl-", "zxcvbnm.@_"],
+        ["!\"#$%&'()=", "QWERTYUIOP", "ASDFGHJKL+", "ZXCVBNM,:/"],
+    ],
+    [
+        ["abcdefghij", "klmnopqrst", "uvwxyz.@-_", "1234567890"],
+        ["ABCDEFGHIJ", "KLMNOPQRST", "UVWXYZ,:+/", "!\"#$%&'()="],
+    ],
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Special {
+    Shift,
+    Layout,
+    Space,
+    Delete,
+    Cancel,
+    Ok,
+}
+
+// the bottom row
+const SPECIALS: [Special; 6] = [
+    Special::Shift,
+    Special::Layout,
+    Special::Space,
+    Special::Delete,
+    Special::Cancel,
+    Special::Ok,
+];
+const SPECIAL_WIDTH: u32 = TFT_DISPLAY_WIDTH as u32 / SPECIALS.len() as u32;
+
+struct Keyboard<const N: usize> {
+    text: String<N>,
+    limit: usize,
+    layout: Layout,
+    shift: bool,
+    // row `ROWS` is the special keys
+    row: usize,
+    column: usize,
+}
+
+impl<const N: usize> Keyboard<N> {
+    fn columns(row: usize) -> usize {
+        if row == ROWS { SPECIALS.len() } else { COLUMNS }
+    }
+
+    fn key(&self, row: usize, column: usize) -> char {
+        let keys = KEYS[self.layout as usize][self.shift as usize][row];
+        keys.as_bytes()[column] as char
+    }
+
+    fn bounds(row: usize, column: usize) -> Rectangle {
+        let y = KEYS_TOP + row as i32 * KEY.height as i32;
+        let (x, width) = if row == ROWS {
+            (column as u32 * SPECIAL_WIDTH, SPECIAL_WIDTH)
+        } else {
+            (column as u32 * KEY.width, KEY.width)
+        };
+        Rectangle::new(
+            Point::new(x as i32 + 1, y + 1),
+            Size::new(width - KEY_GAP, KEY.height - KEY_GAP),
+        )
+    }
+
+    fn move_to(&mut self, row: usize) {
+        // keep roughly the same place across rows of different widths
+        let from = Self::columns(self.row);
+        self.column = (self.column * 2 + 1) * Self::columns(row) / (from * 2);
+        self.row = row;
+    }
+
+    fn label(&self, special: Special) -> &'static str {
+        match special {
+            Special::Shift => "shift",
+            Special::Layout if self.layout == Layout::Qwerty => "abc",
+            Special::Layout => "qwerty",
+            Special::Space => "space",
+            Special::Delete => "del",
+            Special::Cancel => "cancel",
+            Special::Ok => "ok",
+        }
+    }
+
+    fn draw_key<D, C>(
+        &self,
+        display: &mut D,
+        row: usize,
+        column: usize,
+        theme: &Theme<C>,
+    ) -> Result<(), D::Error>
+    where
+        C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+        D: DrawTarget<Color = C>,
+    {
+        let selected = (row, column) == (self.row, self.column);
+        let bounds = Self::bounds(row, column);
+        let (fill, ink) = if selected {
+            (theme.selection, theme.background)
+        } else {
+            (C::CSS_DARK_SLATE_GRAY, C::WHITE)
+        };
+        bounds
+            .into_styled(PrimitiveStyle::with_fill(fill))
+            .draw(display)?;
+
+        let center = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+        if row == ROWS {
+            let special = SPECIALS[column];
+            // shift stays lit while it is on
+            let ink = if special == Special::Shift && self.shift && !selected {
+                theme.accent
+            } else {
+                ink
+            };
+            Text::with_text_style(
+                self.label(special),
+                bounds.center(),
+                MonoTextStyle::new(&FONT_6X10, ink),
+                center,
+            )
+            .draw(display)?;
+        } else {
+            let mut buf = [0u8; 4];
+            Text::with_text_style(
+                self.key(row, column).encode_utf8(&mut buf),
+                bounds.center(),
+                MonoTextStyle::new(&FONT_9X15, ink),
+                center,
+            )
+            .draw(display)?;
+        }
+        Ok(())
+    }
+
+    fn draw_keys<D, C>(&self, display: &mut D, theme: &Theme<C>) -> Result<(), D::Error>
+    where
+        C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+        D: DrawTarget<Color = C>,
+    {
+        for row in 0..=ROWS {
+            for column in 0..Self::columns(row) {
+                self.draw_key(display, row, column, theme)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_entry<D, C>(&self, display: &mut D, theme: &Theme<C>) -> Result<(), D::Error>
+    where
+        C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+        D: DrawTarget<Color = C>,
+    {
+        ENTRY
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .stroke_color(C::CSS_GRAY)
+                    .stroke_width(1)
+                    .fill_color(theme.background)
+                    .build(),
+            )
+            .draw(display)?;
+
+        // the end of the text when it is longer than the box
+        let font = theme.text_font;
+        let room = ENTRY.size.width - 8 - font.width("_");
+        let start = self
+            .text
+            .char_indices()
+            .find(|(i, _)| font.width(&self.text[*i..]) <= room)
+            .map_or(self.text.len(), |(i, _)| i);
+        let shown = &self.text[start..];
+        let left = TextStyleBuilder::new().baseline(Baseline::Middle).build();
+        let end = Text::with_text_style(
+            shown,
+            Point::new(ENTRY.top_left.x + 4, ENTRY.center().y),
+            FontStyle::new(font, theme.foreground),
+            left,
+        )
+        .draw(display)?;
+        Text::with_text_style("_", end, MonoTextStyle::new(&FONT_9X15, theme.accent), left)
+            .draw(display)?;
+        Ok(())
+    }
+
+    fn draw<D, C>(&self, display: &mut D, prompt: &str, theme: &Theme<C>) -> Result<(), D::Error>
+    where
+        C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+        D: DrawTarget<Color = C>,
+    {
+        display.clear(theme.background)?;
+        Text::with_text_style(
+            prompt,
+            Point::new(ENTRY.top_left.x, PROMPT_Y),
+            MonoTextStyle::new(&FONT_6X10, theme.foreground),
+            TextStyleBuilder::new().baseline(Baseline::Middle).build(),
+        )
+        .draw(display)?;
+        self.draw_entry(display, theme)?;
+        self.draw_keys(display, theme)
+    }
+
+    fn push(&mut self, c: char) -> bool {
+        self.text.chars().count() < self.limit && self.text.push(c).is_ok()
+    }
+}
+
+/// Lets the user type up to `limit` characters (and no more than `N`
+/// bytes), starting from `initial`.
+/// The D-pad moves between keys, A presses one and B deletes the last
+/// character, or cancels when there is none. Returns `None` when cancelled.
+pub fn keyboard<D, C, const N: usize>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+    prompt: &str,
+    initial: &str,
+    limit: usize,
+) -> Result<Option<String<N>>, BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+{
+    // in whatever theme the app that opened it is
+    let theme = &Theme::current();
+    let mut keyboard = Keyboard::<N> {
+        text: String::new(),
+        limit,
+        layout: Layout::Qwerty,
+        shift: false,
+        row: 1,
+        column: 0,
+    };
+    for c in initial.chars() {
+        keyboard.push(c);
+    }
+
+    keyboard
+        .draw(display, prompt, theme)
+        .map_err(BadgeError::display)?;
+
+    loop {
+        let input = buttons.poll();
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            keyboard
+                .draw(display, prompt, theme)
+                .map_err(BadgeError::display)?;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, tap)?;
+        }
+        display.update_status()?;
+
+        let (row, column) = (keyboard.row, keyboard.column);
+        let columns = Keyboard::<N>::columns(row);
+        if input.held.left {
+            keyboard.column = (column + columns - 1) % columns;
+        }
+        if input.held.right {
+            keyboard.column = (column + 1) % columns;
+        }
+        if input.held.up {
+            keyboard.move_to((row + ROWS) % (ROWS + 1));
+        }
+        if input.held.down {
+            keyboard.move_to((row + 1) % (ROWS + 1));
+        }
+        let moved = (row, column) != (keyboard.row, keyboard.column);
+        if moved {
+            keyboard
+                .draw_key(display, row, column, theme)
+                .and_then(|()| keyboard.draw_key(display, keyboard.row, keyboard.column, theme))
+                .map_err(BadgeError::display)?;
+        }
+
+        let mut typed = false;
+        let mut relabel = false;
+        if input.pressed.a {
+            if keyboard.row < ROWS {
+                typed = keyboard.push(keyboard.key(keyboard.row, keyboard.column));
+                // shift is good for one character
+                relabel = typed && keyboard.shift;
+                keyboard.shift &= !typed;
+            } else {
+                match SPECIALS[keyboard.column] {
+                    Special::Shift => {
+                        keyboard.shift = !keyboard.shift;
+                        relabel = true;
+                    }
+                    Special::Layout => {
+                        keyboard.layout = match keyboard.layout {
+                            Layout::Qwerty => Layout::Abc,
+                            Layout::Abc => Layout::Qwerty,
+                        };
+                        relabel = true;
+                    }
+                    Special::Space => typed = keyboard.push(' '),
+                    Special::Delete => typed = keyboard.text.pop().is_some(),
+                    Special::Cancel => return Ok(None),
+                    Special::Ok => return Ok(Some(keyboard.text)),
+                }
+            }
+        }
+        if input.released.b {
+            if keyboard.text.pop().is_none() {
+                return Ok(None);
+            }
+            typed = true;
+        }
+
+        if typed {
+            keyboard
+                .draw_entry(display, theme)
+                .map_err(BadgeError::display)?;
+        }
+        if relabel {
+            keyboard
+                .draw_keys(display, theme)
+                .map_err(BadgeError::display)?;
+        }
+
+        delay.delay_ms(if moved { 150 } else { 10 });
+    }
+}
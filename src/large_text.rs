@@ -0,0 +1,233 @@
+// Text larger than the biggest mono font, for reading a name across a room.
+// Glyphs are drawn through `Scaled`, which blows every pixel up into a
+// square, and always with a background so that redrawing in place replaces
+// what was there without clearing it first.
+
+use embedded_graphics::{
+    Drawable, Pixel,
+    prelude::{Dimensions, DrawTarget, DrawTargetExt, PixelColor, Point, PointsIter, Size},
+    primitives::Rectangle,
+    text::{Baseline, Text},
+};
+use heapless::Vec;
+
+use crate::font::{Font, FontStyle};
+
+// largest glyph cell `Scaled` blows up in one go, 10x20 fits
+const GLYPH_PIXELS: usize = 256;
+// between the end of a scrolling text and its next pass, in source pixels
+const MARQUEE_GAP: u32 = 24;
+
+/// Scales everything drawn into it by a whole number around `origin`.
+struct Scaled<'a, D> {
+    target: &'a mut D,
+    origin: Point,
+    scale: u32,
+}
+
+impl<D: DrawTarget> Scaled<'_, D> {
+    fn scaled(&self, area: &Rectangle) -> Rectangle {
+        Rectangle::new(
+            self.origin + area.top_left * self.scale as i32,
+            area.size * self.scale,
+        )
+    }
+}
+
+impl<D: DrawTarget> Dimensions for Scaled<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        let target = self.target.bounding_box();
+        Rectangle::new(
+            (target.top_left - self.origin) / self.scale as i32,
+            target.size / self.scale + Size::new(1, 1),
+        )
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for Scaled<'_, D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let square = self.scaled(&Rectangle::new(point, Size::new(1, 1)));
+            self.target.fill_solid(&square, color)?;
+        }
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let mut glyph: Vec<D::Color, GLYPH_PIXELS> = Vec::new();
+        let mut colors = colors.into_iter();
+        for color in colors.by_ref().take(GLYPH_PIXELS) {
+            let _ = glyph.push(color);
+        }
+        let pixels = (area.size.width * area.size.height) as usize;
+        if pixels > glyph.len() {
+            // too big to hold, a square at a time
+            return self.draw_iter(
+                area.points()
+                    .zip(glyph.into_iter().chain(colors))
+                    .map(|(point, color)| Pixel(point, color)),
+            );
+        }
+
+        // one window for the whole glyph, each source row sent `scale` times
+        let (width, scale) = (area.size.width as usize, self.scale as usize);
+        let scaled = self.scaled(area);
+        self.target.fill_contiguous(
+            &scaled,
+            (0..scaled.size.height as usize).flat_map(|y| {
+                let row = &glyph[y / scale * width..][..width];
+                (0..width * scale).map(move |x| row[x / scale])
+            }),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let scaled = self.scaled(area);
+        self.target.fill_solid(&scaled, color)
+    }
+}
+
+/// The largest scale `text` fits `size` at, zero when it does not fit at all.
+pub fn scale_to_fit(text: &str, font: &Font, size: Size) -> u32 {
+    let width = font.width(text).max(1);
+    (size.width / width).min(size.height / font.height())
+}
+
+/// Draws `text` `scale` times the size of `font`, its top left at
+/// `top_left`, on a `background` box.
+pub fn draw_scaled<D, C>(
+    display: &mut D,
+    text: &str,
+    font: &Font,
+    scale: u32,
+    top_left: Point,
+    color: C,
+    background: C,
+) -> Result<(), D::Error>
+where
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    let style = FontStyle::new(font, color).with_background(background);
+    let mut scaled = Scaled {
+        target: display,
+        origin: top_left,
+        scale,
+    };
+    Text::with_baseline(text, Point::zero(), style, Baseline::Top).draw(&mut scaled)?;
+    Ok(())
+}
+
+/// A line of large text in `area`: as big as fits, or scrolling sideways
+/// at `min_scale` when it would come out smaller than that. Keeps how far
+/// it has scrolled between frames.
+pub struct LargeText<'a, C> {
+    font: &'a Font,
+    area: Rectangle,
+    min_scale: u32,
+    color: C,
+    background: C,
+    // in screen pixels
+    offset: u32,
+    drawn: bool,
+}
+
+impl<'a, C: PixelColor> LargeText<'a, C> {
+    pub fn new(font: &'a Font, area: Rectangle, min_scale: u32, color: C, background: C) -> Self {
+        Self {
+            font,
+            area,
+            min_scale,
+            color,
+            background,
+            offset: 0,
+            drawn: false,
+        }
+    }
+
+    // the scale for `text` and whether it has to scroll at that
+    fn layout(&self, text: &str) -> (u32, bool) {
+        let fit = scale_to_fit(text, self.font, self.area.size);
+        let tallest = (self.area.size.height / self.font.height()).max(1);
+        let scale = self.min_scale.clamp(1, tallest);
+        if fit >= scale {
+            (fit, false)
+        } else {
+            (scale, true)
+        }
+    }
+
+    /// Starts over, the next `draw` paints the whole area.
+    pub fn reset(&mut self) {
+        self.offset = 0;
+        self.drawn = false;
+    }
+
+    /// Draws `text`, moving it `step` pixels on when it scrolls. Text that
+    /// fits is only drawn after a `reset`. Every pixel is written once, so
+    /// there is no flicker.
+    pub fn draw<D>(&mut self, display: &mut D, text: &str, step: u32) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let (scale, scrolling) = self.layout(text);
+        if self.drawn && !scrolling {
+            return Ok(());
+        }
+
+        let size = Size::new(self.font.width(text), self.font.height()) * scale;
+        let top = self.area.top_left.y + (self.area.size.height as i32 - size.height as i32) / 2;
+        let mut display = display.clipped(&self.area);
+
+        // above and below the text never change
+        if !self.drawn {
+            self.drawn = true;
+            display.fill_solid(&self.area, self.background)?;
+        }
+
+        if !scrolling {
+            let left = self.area.center().x - size.width as i32 / 2;
+            return draw_scaled(
+                &mut display,
+                text,
+                self.font,
+                scale,
+                Point::new(left, top),
+                self.color,
+                self.background,
+            );
+        }
+
+        let period = size.width + MARQUEE_GAP * scale;
+        self.offset = (self.offset + step) % period;
+        for pass in 0..2 {
+            let left = self.area.top_left.x - self.offset as i32 + (pass * period) as i32;
+            draw_scaled(
+                &mut display,
+                text,
+                self.font,
+                scale,
+                Point::new(left, top),
+                self.color,
+                self.background,
+            )?;
+            display.fill_solid(
+                &Rectangle::new(
+                    Point::new(left + size.width as i32, top),
+                    Size::new(MARQUEE_GAP * scale, size.height),
+                ),
+                self.background,
+            )?;
+        }
+        Ok(())
+    }
+}
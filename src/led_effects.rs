@@ -0,0 +1,315 @@
+use micromath::F32Ext;
+use smart_leds::RGB8;
+
+use crate::color::{Hsv, hsv_to_rgb8};
+
+pub const SPEED_MIN: u8 = 1;
+pub const SPEED_MAX: u8 = 8;
+// speed at which effects run in real time
+const SPEED_NORMAL: u32 = 4;
+// the random looking effects repeat after this many slots
+const SLOTS: u32 = 64;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Effect {
+    Rainbow,
+    Breathe,
+    Chase,
+    Police,
+    Heartbeat,
+    Sparkle,
+    Fire,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 7] = [
+        Effect::Rainbow,
+        Effect::Breathe,
+        Effect::Chase,
+        Effect::Police,
+        Effect::Heartbeat,
+        Effect::Sparkle,
+        Effect::Fire,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::Rainbow => "rainbow",
+            Effect::Breathe => "breathe",
+            Effect::Chase => "chase",
+            Effect::Police => "police",
+            Effect::Heartbeat => "heartbeat",
+            Effect::Sparkle => "sparkle",
+            Effect::Fire => "fire",
+        }
+    }
+
+    /// Milliseconds after which the effect repeats when played in real time
+    /// on `n` leds.
+    fn period_ms(&self, n: usize) -> u32 {
+        match self {
+            Effect::Rainbow => 256 * 8,
+            Effect::Breathe => 4000,
+            Effect::Chase => 250 * n.max(1) as u32,
+            Effect::Police => 1000,
+            Effect::Heartbeat => 1200,
+            Effect::Sparkle => SLOTS * 80,
+            Effect::Fire => SLOTS * 60,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let idx = Self::ALL.iter().position(|e| e == self).unwrap();
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(&self) -> Self {
+        let idx = Self::ALL.iter().position(|e| e == self).unwrap();
+        Self::ALL[(idx + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+/// An effect plus its parameters. Rendering is a pure function of time so the
+/// same animation can be driven from any loop (or checked on the host).
+#[derive(Clone, Copy, PartialEq)]
+pub struct Animation {
+    pub effect: Effect,
+    /// 1..=8, 4 plays the effect in real time
+    pub speed: u8,
+    /// peak brightness as drawn on screen, the led pipeline maps it to duty cycle
+    pub brightness: u8,
+    /// base color for the single-color effects (breathe, chase, heartbeat)
+    pub hue: u8,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self {
+            effect: Effect::Rainbow,
+            speed: SPEED_NORMAL as u8,
+            brightness: 192,
+            hue: 12, // rust orange
+        }
+    }
+}
+
+impl Animation {
+    /// Colors of the `N` leds, `t_ms` milliseconds into the animation.
+    pub fn frame<const N: usize>(&self, t_ms: u64) -> [RGB8; N] {
+        // reduce to one period before scaling so the product can't overflow
+        let period = self.effect.period_ms(N) * SPEED_NORMAL;
+        let speed = self.speed.clamp(SPEED_MIN, SPEED_MAX) as u32;
+        let t = (t_ms % period as u64) as u32 * speed / SPEED_NORMAL;
+        let mut leds = [RGB8::default(); N];
+
+        for (i, led) in leds.iter_mut().enumerate() {
+            *led = match self.effect {
+                Effect::Rainbow => self.rainbow(t, i, N),
+                Effect::Breathe => self.breathe(t),
+                Effect::Chase => self.chase(t, i, N),
+                Effect::Police => self.police(t, i),
+                Effect::Heartbeat => self.heartbeat(t),
+                Effect::Sparkle => self.sparkle(t, i),
+                Effect::Fire => self.fire(t, i),
+            };
+        }
+
+        leds
+    }
+
+    // hues wrap around at 256 here, so effects can sweep with plain u8 math
+    fn hsv(&self, hue: u8, sat: u8, level: u8) -> RGB8 {
+        hsv_to_rgb8(Hsv {
+            hue: (hue as u32 * 360 / 256) as u16,
+            sat,
+            val: scale(self.brightness, level),
+        })
+    }
+
+    fn rainbow(&self, t: u32, i: usize, n: usize) -> RGB8 {
+        let hue = (t / 8) as u8;
+        self.hsv(hue.wrapping_add((i * 256 / n) as u8), 255, 255)
+    }
+
+    fn breathe(&self, t: u32) -> RGB8 {
+        let phase = (t % 4000) as f32 / 4000.0;
+        // micromath on the host too, so tests see what the badge shows
+        let level = (1.0 - F32Ext::cos(phase * 2.0 * core::f32::consts::PI)) / 2.0;
+        self.hsv(self.hue, 255, (level * 255.0) as u8)
+    }
+
+    fn chase(&self, t: u32, i: usize, n: usize) -> RGB8 {
+        let step = (t / 250) as usize % n;
+        // the led that was just left keeps a dim trail
+        let trail = (step + n - 1) % n;
+        if i == step {
+            self.hsv(self.hue, 255, 255)
+        } else if i == trail && n > 1 {
+            self.hsv(self.hue, 255, 32)
+        } else {
+            RGB8::default()
+        }
+    }
+
+    fn police(&self, t: u32, i: usize) -> RGB8 {
+        // each half second one side double-flashes: on, off, on, off
+        let t = t % 1000;
+        let red_side = t < 500;
+        let flash_on = ((t % 500) / 100).is_multiple_of(2) && t % 500 < 300;
+
+        if !flash_on || i.is_multiple_of(2) != red_side {
+            RGB8::default()
+        } else if red_side {
+            RGB8::new(self.brightness, 0, 0)
+        } else {
+            RGB8::new(0, 0, self.brightness)
+        }
+    }
+
+    fn heartbeat(&self, t: u32) -> RGB8 {
+        // lub-dub followed by a rest, decaying after each beat
+        let t = t % 1200;
+        let level = match t {
+            0..250 => 255 - (t * 255 / 250),
+            250..550 => 180 - ((t - 250) * 180 / 300),
+            _ => 0,
+        };
+        self.hsv(self.hue, 255, level as u8)
+    }
+
+    fn sparkle(&self, t: u32, i: usize) -> RGB8 {
+        let slot = (t / 80) % SLOTS;
+        let seed = hash(slot ^ hash(i as u32));
+        if !seed.is_multiple_of(6) {
+            return RGB8::default();
+        }
+        // fade out within the slot
+        let level = 255 - ((t % 80) * 255 / 80) as u8;
+        self.hsv((seed >> 8) as u8, (seed >> 16) as u8 / 2, level)
+    }
+
+    fn fire(&self, t: u32, i: usize) -> RGB8 {
+        // interpolate between random flicker targets for a smooth glow
+        let slot = (t / 60) % SLOTS;
+        let progress = t % 60;
+        let from = hash(slot ^ hash(i as u32 + 1));
+        let to = hash(((slot + 1) % SLOTS) ^ hash(i as u32 + 1));
+        let flicker = |seed: u32| 140 + seed % 116;
+        let level = (flicker(from) * (60 - progress) + flicker(to) * progress) / 60;
+        let hue = (from >> 8) as u8 % 24;
+        self.hsv(hue, 255, level as u8)
+    }
+}
+
+fn scale(value: u8, level: u8) -> u8 {
+    ((value as u16 * level as u16) / 255) as u8
+}
+
+// integer mixer, gives the random looking effects a deterministic source
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEDS: usize = 2;
+
+    fn animation(effect: Effect, speed: u8) -> Animation {
+        Animation {
+            effect,
+            speed,
+            ..Animation::default()
+        }
+    }
+
+    #[test]
+    fn repeats_after_one_period() {
+        for effect in Effect::ALL {
+            let period = effect.period_ms(LEDS) as u64;
+            let a = animation(effect, SPEED_NORMAL as u8);
+            for t in [0, 1, 77, period / 3] {
+                assert!(
+                    a.frame::<LEDS>(t) == a.frame::<LEDS>(t + period),
+                    "{}",
+                    effect.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn black_at_zero_brightness() {
+        for effect in Effect::ALL {
+            let a = Animation {
+                brightness: 0,
+                ..animation(effect, SPEED_NORMAL as u8)
+            };
+            for t in (0..10_000).step_by(7) {
+                assert!(
+                    a.frame::<LEDS>(t) == [RGB8::default(); LEDS],
+                    "{}",
+                    effect.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn speed_scales_the_period() {
+        for effect in Effect::ALL {
+            let normal = animation(effect, SPEED_NORMAL as u8);
+            for speed in [1, 2, 8] {
+                let a = animation(effect, speed);
+                let period = (effect.period_ms(LEDS) * SPEED_NORMAL / speed as u32) as u64;
+                assert!(
+                    a.frame::<LEDS>(0) == a.frame::<LEDS>(period),
+                    "{}",
+                    effect.name()
+                );
+            }
+            // twice as fast shows at t what normal speed shows at 2t
+            let double = animation(effect, 2 * SPEED_NORMAL as u8);
+            for t in (0..10_000).step_by(13) {
+                assert!(
+                    double.frame::<LEDS>(t) == normal.frame::<LEDS>(2 * t),
+                    "{}",
+                    effect.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn no_jump_when_time_times_speed_overflows() {
+        // where the product overflows a u32, where the milliseconds do after
+        // about 49.7 days, and where the product overflows a u64
+        let overflows = [
+            u32::MAX as u64 / SPEED_MAX as u64,
+            u32::MAX as u64,
+            u64::MAX / SPEED_MAX as u64,
+        ];
+        for effect in Effect::ALL {
+            let fast = animation(effect, SPEED_MAX);
+            let normal = animation(effect, SPEED_NORMAL as u8);
+            let period = effect.period_ms(LEDS) as u128;
+            for overflow in overflows {
+                for t in overflow - 100..overflow + 100 {
+                    // the time the effect is at, worked out without overflow
+                    let scaled = t as u128 * SPEED_MAX as u128 / SPEED_NORMAL as u128;
+                    assert!(
+                        fast.frame::<LEDS>(t) == normal.frame::<LEDS>((scaled % period) as u64),
+                        "{} jumps at {t}",
+                        effect.name()
+                    );
+                }
+            }
+        }
+    }
+}
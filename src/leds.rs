@@ -0,0 +1,164 @@
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use rp2040_hal::{
+    Timer,
+    fugit::{ExtU32, Instant, MicrosDurationU32},
+    gpio::{FunctionPio0, Pin, PullDown, bank0::Gpio15},
+    pac::{self, PIO0, interrupt},
+    pio::SM0,
+    timer::{Alarm, Alarm0, CountDown},
+};
+use smart_leds::{RGB8, SmartLedsWrite};
+use ws2812_pio::Ws2812;
+
+use crate::{NEOPIXEL_COUNT, color::LedPipeline, error::BadgeError, led_effects::Animation};
+
+pub type Neopixels = Ws2812<PIO0, SM0, CountDown, Pin<Gpio15, FunctionPio0, PullDown>>;
+
+const FRAME_INTERVAL: MicrosDurationU32 = MicrosDurationU32::millis(20);
+
+/// Colors an app pushed on top of the ambient animation.
+struct Override {
+    colors: [RGB8; NEOPIXEL_COUNT],
+    until: Instant<u64, 1, 1_000_000>,
+}
+
+struct Leds {
+    ws: Neopixels,
+    alarm: Alarm0,
+    timer: Timer,
+    ambient: Animation,
+    overlay: Option<Override>,
+    pipeline: LedPipeline,
+    shown: [RGB8; NEOPIXEL_COUNT],
+    // dark while the badge sleeps or the battery is low
+    suspended: bool,
+    low_battery: bool,
+}
+
+static LEDS: Mutex<RefCell<Option<Leds>>> = Mutex::new(RefCell::new(None));
+
+/// Hands the neopixels over to the timer interrupt, which renders the ambient
+/// animation (or an app override) every frame from then on.
+pub fn init(
+    ws: Neopixels,
+    mut timer: Timer,
+    ambient: Animation,
+    pipeline: LedPipeline,
+) -> Result<(), BadgeError> {
+    let mut alarm = timer
+        .alarm_0()
+        .ok_or_else(|| BadgeError::led("alarm 0 already taken"))?;
+    alarm.schedule(FRAME_INTERVAL).map_err(BadgeError::led)?;
+    alarm.enable_interrupt();
+
+    critical_section::with(|cs| {
+        LEDS.borrow(cs).replace(Some(Leds {
+            ws,
+            alarm,
+            timer,
+            ambient,
+            overlay: None,
+            pipeline,
+            shown: [RGB8::default(); NEOPIXEL_COUNT],
+            suspended: false,
+            low_battery: false,
+        }));
+    });
+
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_0);
+    }
+
+    Ok(())
+}
+
+fn with_leds<R>(f: impl FnOnce(&mut Leds) -> R) -> Option<R> {
+    critical_section::with(|cs| LEDS.borrow(cs).borrow_mut().as_mut().map(f))
+}
+
+pub fn ambient() -> Animation {
+    with_leds(|leds| leds.ambient).unwrap_or_default()
+}
+
+/// Replaces the animation running underneath every app.
+pub fn set_ambient(animation: Animation) {
+    with_leds(|leds| leds.ambient = animation);
+}
+
+pub fn brightness_cap() -> u8 {
+    with_leds(|leds| leds.pipeline.brightness_cap).unwrap_or_default()
+}
+
+/// Limits every led channel, whatever the animation or override asks for.
+pub fn set_brightness_cap(brightness_cap: u8) {
+    with_leds(|leds| leds.pipeline.brightness_cap = brightness_cap);
+}
+
+/// Shows `colors` for `duration_ms`, then falls back to the ambient animation.
+pub fn flash(colors: [RGB8; NEOPIXEL_COUNT], duration_ms: u32) {
+    with_leds(|leds| {
+        leds.overlay = Some(Override {
+            colors,
+            until: leds.timer.get_counter() + duration_ms.millis(),
+        })
+    });
+}
+
+/// Turns the leds off, the animation picks up again on `resume`.
+pub fn suspend() {
+    with_leds(|leds| leds.suspended = true);
+}
+
+pub fn resume() {
+    with_leds(|leds| leds.suspended = false);
+}
+
+/// Keeps the leds off, whatever `resume` says, until the battery recovers.
+pub fn set_low_battery(low_battery: bool) {
+    with_leds(|leds| leds.low_battery = low_battery);
+}
+
+/// What the leds displayed on the last frame, before the pipeline, for
+/// mirroring them on screen.
+pub fn shown() -> [RGB8; NEOPIXEL_COUNT] {
+    with_leds(|leds| leds.shown).unwrap_or_default()
+}
+
+#[interrupt]
+fn TIMER_IRQ_0() {
+    critical_section::with(|cs| {
+        let mut leds = LEDS.borrow(cs).borrow_mut();
+        let Some(leds) = leds.as_mut() else {
+            return;
+        };
+
+        leds.alarm.clear_interrupt();
+        let _ = leds.alarm.schedule(FRAME_INTERVAL);
+
+        let now = leds.timer.get_counter();
+
+        if leds
+            .overlay
+            .as_ref()
+            .is_some_and(|overlay| now >= overlay.until)
+        {
+            leds.overlay = None;
+        }
+
+        let colors = match &leds.overlay {
+            Some(overlay) => overlay.colors,
+            None => leds.ambient.frame(now.duration_since_epoch().to_millis()),
+        };
+
+        let output = if leds.suspended || leds.low_battery {
+            [RGB8::default(); NEOPIXEL_COUNT]
+        } else {
+            leds.pipeline.apply(colors)
+        };
+        if leds.ws.write(output.iter().cloned()).is_ok() {
+            leds.shown = colors;
+        }
+    });
+}
@@ -0,0 +1,13 @@
+//! The parts of the badge that do not touch the hardware. They build for
+//! the host too, so `make test` runs their tests there.
+#![no_std]
+
+pub mod clip;
+pub mod color;
+pub mod contact;
+pub mod led_effects;
+pub mod line;
+pub mod qr;
+pub mod record;
+pub mod schedule;
+pub mod text_box;
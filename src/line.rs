@@ -0,0 +1,107 @@
+/// A line typed on the serial port, collected a byte at a time. A line
+/// longer than the buffer is remembered as such and dropped whole when it
+/// ends, never handed on cut short.
+pub struct LineBuffer<const N: usize> {
+    bytes: heapless::Vec<u8, N>,
+    overflow: bool,
+}
+
+/// The line did not fit the buffer.
+#[derive(Debug, PartialEq)]
+pub struct TooLong;
+
+impl<const N: usize> LineBuffer<N> {
+    pub const fn new() -> Self {
+        Self {
+            bytes: heapless::Vec::new(),
+            overflow: false,
+        }
+    }
+
+    /// Adds a byte, returns whether it was kept and should be echoed.
+    pub fn push(&mut self, byte: u8) -> bool {
+        if self.overflow || self.bytes.push(byte).is_err() {
+            self.overflow = true;
+            return false;
+        }
+        true
+    }
+
+    /// Removes the last character, returns whether there was one.
+    pub fn backspace(&mut self) -> bool {
+        // a whole character, continuation bytes are 0b10xx_xxxx
+        while let Some(byte) = self.bytes.pop() {
+            if byte & 0xc0 != 0x80 {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Ends the line and starts the next one.
+    pub fn finish(&mut self) -> Result<heapless::Vec<u8, N>, TooLong> {
+        let bytes = core::mem::take(&mut self.bytes);
+        if core::mem::take(&mut self.overflow) {
+            return Err(TooLong);
+        }
+        Ok(bytes)
+    }
+}
+
+impl<const N: usize> Default for LineBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed<const N: usize>(line: &mut LineBuffer<N>, text: &str) {
+        for byte in text.bytes() {
+            line.push(byte);
+        }
+    }
+
+    #[test]
+    fn hands_on_a_line_that_fits() {
+        let mut line: LineBuffer<16> = LineBuffer::new();
+        typed(&mut line, "time 12:00");
+        assert_eq!(line.finish().unwrap(), b"time 12:00");
+        assert_eq!(line.finish().unwrap(), b"");
+    }
+
+    #[test]
+    fn drops_an_upload_line_that_does_not_fit() {
+        let mut line: LineBuffer<64> = LineBuffer::new();
+        // a session with a long title and a speaker
+        typed(
+            &mut line,
+            "09:00 09:45 1 Writing embedded Rust for a conference badge|Ada Lovelace",
+        );
+        assert_eq!(line.finish(), Err(TooLong));
+
+        // the next line starts afresh
+        typed(&mut line, ".");
+        assert_eq!(line.finish().unwrap(), b".");
+    }
+
+    #[test]
+    fn keeps_dropping_after_a_backspace() {
+        let mut line: LineBuffer<4> = LineBuffer::new();
+        typed(&mut line, "abcdef");
+        assert!(line.backspace());
+        assert!(!line.push(b'x'));
+        assert_eq!(line.finish(), Err(TooLong));
+    }
+
+    #[test]
+    fn backspace_removes_a_whole_character() {
+        let mut line: LineBuffer<16> = LineBuffer::new();
+        typed(&mut line, "né🦀");
+        assert!(line.backspace());
+        assert_eq!(line.finish().unwrap(), "né".as_bytes());
+        assert!(!line.backspace());
+    }
+}
@@ -1,100 +1,179 @@
+use core::{cell::Cell, fmt::Write, write};
+
 use accelerometer::vector::F32x3;
-use core::{fmt::Write, write};
-use embedded_graphics::{
-    Drawable,
-    mono_font::{MonoTextStyleBuilder, ascii::FONT_6X9},
-    pixelcolor::{Rgb555, Rgb565, Rgb888},
-    prelude::{DrawTarget, Point, RgbColor},
-    text::Text,
-};
+use critical_section::Mutex;
+use embedded_graphics::prelude::RgbColor;
 use heapless::String;
 use smart_leds::RGB8;
 
-use crate::TFT_DISPLAY_HEIGHT;
+use crate::{console, input::ButtonState, usb_serial};
 
-pub fn log<D, C>(display: &mut D, text: &str, x: i32, y: i32)
-where
-    D: DrawTarget<Color = C>,
-    D::Error: core::fmt::Debug,
-    C: RgbColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
-{
-    Text::new(
-        text,
-        Point::new(x, y),
-        MonoTextStyleBuilder::new()
-            .font(&FONT_6X9)
-            .text_color(C::WHITE)
-            .background_color(C::BLACK)
-            .build(),
-    )
-    .draw(display)
-    .unwrap();
-}
-
-pub fn log_accel<D, C>(display: &mut D, accel: &F32x3)
-where
-    D: DrawTarget<Color = C>,
-    D::Error: core::fmt::Debug,
-    C: RgbColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
-{
-    let mut buf: String<32> = String::new();
-    let _ = write!(
-        &mut buf,
-        "X: {:.2} Y: {:.2} Z: {:.2}",
-        accel.x, accel.y, accel.z
-    );
-    log(display, &buf, 10, 10);
+/// Longest message kept, anything past it is cut.
+pub const LINE_LEN: usize = 52;
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
 }
 
-pub fn log_dpad<D, C>(display: &mut D, buttons: (bool, bool, bool, bool))
-where
-    D: DrawTarget<Color = C>,
-    D::Error: core::fmt::Debug,
-    C: RgbColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
-{
-    let mut buf: String<64> = String::new();
-    let _ = write!(
-        &mut buf,
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warn, Level::Error];
+
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Level::Debug => "D",
+            Level::Info => "I",
+            Level::Warn => "W",
+            Level::Error => "E",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// Where log lines end up.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Sink {
+    /// RTT, only seen with a probe attached
+    Defmt,
+    /// the CDC serial port
+    Usb,
+    /// the on-screen console
+    Screen,
+}
+
+impl Sink {
+    pub const ALL: [Sink; 3] = [Sink::Defmt, Sink::Usb, Sink::Screen];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sink::Defmt => "defmt",
+            Sink::Usb => "usb",
+            Sink::Screen => "screen",
+        }
+    }
+}
+
+// lowest level each sink lets through, `None` turns it off
+static FILTERS: Mutex<Cell<[Option<Level>; 3]>> = Mutex::new(Cell::new([
+    Some(Level::Debug),
+    Some(Level::Info),
+    Some(Level::Debug),
+]));
+
+pub fn filter(sink: Sink) -> Option<Level> {
+    critical_section::with(|cs| FILTERS.borrow(cs).get()[sink as usize])
+}
+
+pub fn set_filter(sink: Sink, level: Option<Level>) {
+    critical_section::with(|cs| {
+        let filters = FILTERS.borrow(cs);
+        let mut current = filters.get();
+        current[sink as usize] = level;
+        filters.set(current);
+    });
+}
+
+fn enabled(sink: Sink, level: Level) -> bool {
+    filter(sink).is_some_and(|min| level >= min)
+}
+
+/// Formats once and hands the line to every sink whose filter lets it
+/// through. Use the `log_debug!` .. `log_error!` macros rather than calling this.
+pub fn log(level: Level, args: core::fmt::Arguments) {
+    if !Sink::ALL.iter().any(|sink| enabled(*sink, level)) {
+        return;
+    }
+
+    let mut text: String<LINE_LEN> = String::new();
+    // a full buffer just truncates the line
+    let _ = text.write_fmt(args);
+
+    if enabled(Sink::Defmt, level) {
+        match level {
+            Level::Debug => defmt::debug!("{=str}", text.as_str()),
+            Level::Info => defmt::info!("{=str}", text.as_str()),
+            Level::Warn => defmt::warn!("{=str}", text.as_str()),
+            Level::Error => defmt::error!("{=str}", text.as_str()),
+        }
+    }
+
+    if enabled(Sink::Usb, level) {
+        let mut line: String<{ LINE_LEN + 6 }> = String::new();
+        let _ = write!(&mut line, "[{}] {}\r\n", level.tag(), text);
+        usb_serial::write_str(&line);
+    }
+
+    if enabled(Sink::Screen, level) {
+        console::push(level, text);
+    }
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Debug, format_args!($($arg)*))
+    };
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Info, format_args!($($arg)*))
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Error, format_args!($($arg)*))
+    };
+}
+
+pub(crate) use {log_error, log_info, log_warn};
+
+pub fn log_accel(accel: &F32x3) {
+    log_debug!("X: {:.2} Y: {:.2} Z: {:.2}", accel.x, accel.y, accel.z);
+}
+
+pub fn log_dpad(buttons: &ButtonState) {
+    log_debug!(
         "left: {} right: {} up: {} down: {}",
-        if buttons.0 { 1 } else { 0 },
-        if buttons.1 { 1 } else { 0 },
-        if buttons.2 { 1 } else { 0 },
-        if buttons.3 { 1 } else { 0 }
+        buttons.left as u8,
+        buttons.right as u8,
+        buttons.up as u8,
+        buttons.down as u8
     );
-    log(display, &buf, 10, 10);
 }
 
-pub fn log_color<D, C>(display: &mut D, eye_color: &C, led_color: &RGB8)
+pub fn log_color<C>(eye_color: &C, led_color: &RGB8)
 where
-    D: DrawTarget<Color = C>,
-    D::Error: core::fmt::Debug,
-    C: RgbColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    C: RgbColor,
 {
-    let mut buf: String<32> = String::new();
-    let _ = write!(
-        &mut buf,
-        "eye_color: r:{} g:{} b:{}",
+    log_debug!(
+        "eye: r:{} g:{} b:{}  led: r:{} g:{} b:{}",
         eye_color.r(),
         eye_color.g(),
-        eye_color.b()
-    );
-    log(display, &buf, 10, 10);
-    let mut buf: String<32> = String::new();
-    let _ = write!(
-        &mut buf,
-        "led_color: r:{} g:{} b:{}",
-        led_color.r, led_color.g, led_color.b,
+        eye_color.b(),
+        led_color.r,
+        led_color.g,
+        led_color.b,
     );
-    log(display, &buf, 10, 20);
 }
 
-pub fn log_angle<D, C>(display: &mut D, angle: f32)
-where
-    D: DrawTarget<Color = C>,
-    D::Error: core::fmt::Debug,
-    C: RgbColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
-{
-    let mut buf: String<32> = String::new();
-    let _ = write!(&mut buf, "angle: {:.2}", angle);
-    log(display, &buf, 10, TFT_DISPLAY_HEIGHT as i32 - 20);
+pub fn log_angle(angle: f32) {
+    log_debug!("angle: {:.2}", angle);
 }
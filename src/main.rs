@@ -2,36 +2,69 @@
 #![no_main]
 
 mod accel_dpad;
+mod agenda;
+mod backlight;
+mod badge;
+mod battery;
 mod bmp;
+mod clock;
+mod console;
+mod crash;
+mod draw;
+mod error;
+mod font;
 mod gopher_hunt;
 mod gopherbadge_rs;
 mod image_rotate;
+mod input;
+mod keyboard;
+mod large_text;
+mod leds;
 mod log;
 mod menu;
 mod neopixel;
+mod power;
+mod preferences;
+mod rtc;
+mod scope;
+mod settings;
+mod shell;
 mod splash;
 mod sprite;
+mod status;
+mod storage;
+mod supervisor;
+mod theme;
+mod usb_serial;
 
 use accel_dpad::accel_dpad;
+use agenda::agenda;
+use badge::badge;
+use clock::clock;
+use color::LedPipeline;
 use defmt_rtt as _;
 use embedded_hal_bus::spi::{ExclusiveDevice, NoDelay};
-use embedded_hal_compat::ForwardCompat;
-use lis3dh::{DataRate, Lis3dh, Range, SlaveAddr};
+use error::BadgeError;
+use gopherbadge::{clip, color, contact, led_effects, line, qr, record, schedule, text_box};
+use input::Buttons;
+use lis3dh::{ClickCount, DataRate, Lis3dh, Range, SlaveAddr};
 use menu::{MenuOption, menu};
 use mipidsi::{
-    Builder,
-    interface::SpiInterface,
+    Builder, NoResetPin,
+    interface::{Interface, SpiInterface},
     models::ST7789,
     options::{ColorInversion, Orientation, Rotation},
 };
 use neopixel::neopixel;
-use panic_probe as _;
+use preferences::preferences;
 
 use rp2040_hal::{
-    self as hal, I2C, Spi,
+    self as hal, Adc, I2C, Spi,
+    adc::AdcPin,
     fugit::RateExtU32,
     gpio::{FunctionSpi, Pins},
     pio::PIOExt,
+    pwm::Slices,
 };
 
 use hal::{
@@ -42,12 +75,11 @@ use hal::{
     watchdog::Watchdog,
 };
 
+use scope::scope;
 use splash::splash_screen;
-use usb_device::{
-    bus::UsbBusAllocator,
-    device::{StringDescriptors, UsbDeviceBuilder, UsbVidPid},
-};
-use usbd_serial::{SerialPort, USB_CLASS_CDC};
+use status::Screen;
+use theme::Theme;
+use usb_device::bus::UsbBusAllocator;
 use ws2812_pio::Ws2812;
 
 use crate::{gopher_hunt::gopher_hunt, gopherbadge_rs::gopherbadge_rs};
@@ -62,6 +94,16 @@ pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_GENERIC_03H;
 const XTAL_FREQ_HZ: u32 = 12_000_000u32;
 pub const TFT_DISPLAY_HEIGHT: u16 = 240;
 pub const TFT_DISPLAY_WIDTH: u16 = 320;
+pub const STATUS_BAR_HEIGHT: u16 = 16;
+/// Height apps get, below the status bar.
+pub const CONTENT_HEIGHT: u16 = TFT_DISPLAY_HEIGHT - STATUS_BAR_HEIGHT;
+pub const NEOPIXEL_COUNT: usize = 2;
+pub const ACCEL_RANGE: Range = Range::G2;
+pub const ACCEL_DATA_RATE: DataRate = DataRate::Hz_100;
+// a knock on the badge, in steps of the range / 128
+const ACCEL_TAP_THRESHOLD: u8 = 40;
+// longest a tap may last, in samples
+const ACCEL_TAP_TIME_LIMIT: u8 = 10;
 
 pub static GOPHER_PANIC: &[u8] = include_bytes!("./assets/gopher-panic.bmp");
 pub static GOPHER_HEAD: &[u8] = include_bytes!("./assets/gopher-head.bmp");
@@ -69,11 +111,21 @@ pub static GOPHER_DEAD: &[u8] = include_bytes!("./assets/gopher-dead.bmp");
 pub static GOPHERBADGE_RS: &[u8] = include_bytes!("./assets/gopherbadge-rs.bmp");
 pub static RUST_PRIDE: &[u8] = include_bytes!("./assets/rust-pride.bmp");
 pub static RUST_CRAB: &[u8] = include_bytes!("./assets/crab.bmp");
+pub static SCHEDULE: &[u8] = include_bytes!("./assets/schedule.txt");
+
+/// The ST7789 the way it is mounted on the badge, in landscape.
+pub fn display_builder<DI: Interface<Word = u8>>(di: DI) -> Builder<DI, ST7789, NoResetPin> {
+    let orientation = Orientation::new();
+    let orientation = orientation.rotate(Rotation::Deg270);
+    Builder::new(ST7789, di)
+        .display_size(TFT_DISPLAY_HEIGHT, TFT_DISPLAY_WIDTH)
+        .invert_colors(ColorInversion::Inverted)
+        .orientation(orientation)
+}
 
 #[entry]
 fn main() -> ! {
     let mut pac = pac::Peripherals::take().unwrap();
-    let core = pac::CorePeripherals::take().unwrap();
     let mut watchdog = Watchdog::new(pac.WATCHDOG);
     let sio = Sio::new(pac.SIO);
 
@@ -96,10 +148,8 @@ fn main() -> ! {
         &mut pac.RESETS,
     );
 
-    let delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
-    let mut delay = delay.forward();
-
     let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+    let mut delay = power::Delay::new(timer);
 
     // -- usb serial
     let usb_bus = UsbBus::new(
@@ -110,17 +160,9 @@ fn main() -> ! {
         &mut pac.RESETS,
     );
 
-    let usb_bus = UsbBusAllocator::new(usb_bus);
-    let mut _serial = SerialPort::new(&usb_bus);
-
-    let mut _usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
-        .strings(&[StringDescriptors::default()
-            .manufacturer("Evil Corp")
-            .product("go-desecrator")
-            .serial_number("N#-of-the-BEST")]) // not beast :)
-        .unwrap()
-        .device_class(USB_CLASS_CDC) // from: https://www.usb.org/defined-class-codes
-        .build();
+    let usb_bus =
+        cortex_m::singleton!(: UsbBusAllocator<UsbBus> = UsbBusAllocator::new(usb_bus)).unwrap();
+    usb_serial::init(usb_bus);
 
     // -- i2c - accelerometer
     let sda = pins.gpio0.reconfigure();
@@ -135,10 +177,22 @@ fn main() -> ! {
         clocks.system_clock.freq(),
     );
 
-    // sensor driver
-    let mut lis3dh = Lis3dh::new_i2c(i2c, SlaveAddr::Default).unwrap();
-    lis3dh.set_range(Range::G2).unwrap();
-    lis3dh.set_datarate(DataRate::Hz_100).unwrap();
+    // sensor driver, the badge stays usable without it
+    let mut lis3dh = match Lis3dh::new_i2c(i2c, SlaveAddr::Default).and_then(|mut lis3dh| {
+        lis3dh.set_range(ACCEL_RANGE)?;
+        lis3dh.set_datarate(ACCEL_DATA_RATE)?;
+        // latched, so a tap is still there when the sleeping badge looks
+        lis3dh.set_click_threshold(true, ACCEL_TAP_THRESHOLD)?;
+        lis3dh.set_click_time_limit(ACCEL_TAP_TIME_LIMIT)?;
+        lis3dh.enable_xyz_click_detection(ClickCount::Single)?;
+        Ok(lis3dh)
+    }) {
+        Ok(lis3dh) => Some(lis3dh),
+        Err(error) => {
+            log::log_error!("accel: {:?}", error);
+            None
+        }
+    };
 
     // -- spi - display
     // control pins
@@ -162,88 +216,144 @@ fn main() -> ! {
     let di = SpiInterface::new(spi_device, dc, &mut buffer);
 
     // display initialization
-    let orientation = Orientation::new();
-    let orientation = orientation.rotate(Rotation::Deg270);
-    let mut display = Builder::new(ST7789, di)
-        .display_size(TFT_DISPLAY_HEIGHT, TFT_DISPLAY_WIDTH)
-        .invert_colors(ColorInversion::Inverted)
-        .orientation(orientation)
-        .init(&mut delay)
-        .unwrap();
+    let mut display = display_builder(di).init(&mut delay).unwrap();
 
     // neopixel led
     let neopixel_pin = pins.gpio15.into_function();
     let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
 
-    let mut ws = Ws2812::new(
+    let ws = Ws2812::new(
         neopixel_pin,
         &mut pio,
         sm0,
         clocks.peripheral_clock.freq(),
         timer.count_down(),
     );
+    let settings = settings::load();
+    // the badge works without leds, the error is logged
+    let _ = leds::init(
+        ws,
+        timer,
+        settings.ambient,
+        LedPipeline {
+            brightness_cap: settings.led_brightness_cap,
+            ..Default::default()
+        },
+    );
+    let hung = supervisor::init(watchdog, timer);
+    // wall time, set over usb
+    rtc::init(timer);
+    log::log_info!(
+        "boot ({}), led cap {}",
+        supervisor::reset_reason().name(),
+        settings.led_brightness_cap
+    );
 
     // -- io pins
-    // display backlight
-    let display_backlight_pin = &mut pins.gpio12.into_push_pull_output();
+    // display backlight, off until the first screen is drawn
+    let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+
+    // battery voltage, sampled from the button polling
+    let adc = Adc::new(pac.ADC, &mut pac.RESETS);
+    match AdcPin::new(pins.gpio29.into_floating_input()) {
+        Ok(vsys) => battery::init(adc, vsys, timer),
+        Err(_) => log::log_error!("battery: gpio29 is not an adc pin"),
+    }
 
     // led
     let _backside_led_pin = pins.gpio2.into_push_pull_output();
 
     // buttons
-    let mut a_btn_pin = pins.gpio10.into_pull_down_input();
-    let mut b_btn_pin = pins.gpio11.into_pull_down_input();
-    let mut down_btn_pin = pins.gpio23.into_pull_down_input();
-    let mut up_btn_pin = pins.gpio24.into_pull_down_input();
-    let mut left_btn_pin = pins.gpio25.into_pull_down_input();
-    let mut right_btn_pin = pins.gpio22.into_pull_down_input();
-
-    splash_screen(
-        &mut display,
-        &mut delay,
-        display_backlight_pin,
-        GOPHER_PANIC,
+    let mut buttons = Buttons::new(
+        pins.gpio10.into_pull_down_input(),
+        pins.gpio11.into_pull_down_input(),
+        pins.gpio23.into_pull_down_input(),
+        pins.gpio24.into_pull_down_input(),
+        pins.gpio25.into_pull_down_input(),
+        pins.gpio22.into_pull_down_input(),
+        timer,
     );
 
+    backlight::init(pwm_slices.pwm6, pins.gpio12, timer, &settings);
+    let theme = Theme::new(settings.theme);
+    let booted = match hung.and_then(MenuOption::from_id) {
+        // straight back to the menu, the user was already past the splash
+        Some(app) => {
+            log::log_error!("{} stopped responding", <&str>::from(&app));
+            supervisor::hung_notice(&mut display, &mut delay, (&app).into())
+                .map_err(BadgeError::display)
+        }
+        None => splash_screen(&mut display, &mut delay, GOPHER_PANIC, &theme),
+    }
+    .and_then(|()| crash::check(&mut display, &mut delay, &mut buttons, &theme));
+    if let Err(error) = booted {
+        error::error_screen(&mut display, &mut delay, &mut buttons, error);
+    }
+
     loop {
-        match menu(
-            &mut display,
+        // picked again every time, the settings may have changed it
+        let theme = Theme::current();
+        let app = match menu(
+            &mut Screen::new(&mut display, timer),
             &mut delay,
-            &mut a_btn_pin,
-            &mut down_btn_pin,
-            &mut up_btn_pin,
+            &mut buttons,
+            &mut lis3dh,
+            &theme,
         ) {
-            MenuOption::Badge => {
-                // badge(&mut display, &mut delay);
+            Ok(app) => app,
+            Err(error) => {
+                error::error_screen(&mut display, &mut delay, &mut buttons, error);
+                continue;
             }
-            MenuOption::AccelerometerDPad => {
-                accel_dpad(
-                    &mut display,
-                    &mut delay,
-                    &mut lis3dh,
-                    &mut a_btn_pin,
-                    &mut b_btn_pin,
-                    &mut down_btn_pin,
-                    &mut up_btn_pin,
-                    &mut left_btn_pin,
-                    &mut right_btn_pin,
-                );
+        };
+        supervisor::enter(app.id());
+
+        // apps draw below the status bar
+        let mut screen = Screen::new(&mut display, timer);
+        let result = match (app, lis3dh.as_mut()) {
+            (MenuOption::Badge, _) => {
+                badge(&mut screen, &mut delay, &mut buttons, &mut lis3dh, &theme)
             }
-            MenuOption::Neopixel => {
-                neopixel(&mut display, &mut delay, &mut b_btn_pin, &mut ws);
+            (MenuOption::AccelerometerDPad, Some(lis3dh)) => {
+                accel_dpad(&mut screen, &mut delay, lis3dh, &mut buttons, &theme)
             }
-            MenuOption::HuntTheGopher => {
-                gopher_hunt(
-                    &mut display,
-                    &mut delay,
-                    &mut lis3dh,
-                    &mut a_btn_pin,
-                    &mut b_btn_pin,
-                );
+            (MenuOption::Neopixel, _) => {
+                neopixel(&mut screen, &mut delay, &mut buttons, &mut lis3dh, &theme)
             }
-            MenuOption::GopherbadgeRust => {
-                gopherbadge_rs(&mut display, &mut delay, &mut b_btn_pin);
+            (MenuOption::HuntTheGopher, Some(lis3dh)) => {
+                gopher_hunt(&mut screen, &mut delay, lis3dh, &mut buttons, &theme)
             }
+            (MenuOption::GopherbadgeRust, _) => {
+                gopherbadge_rs(&mut screen, &mut delay, &mut buttons, &mut lis3dh)
+            }
+            (MenuOption::AccelerometerScope, Some(lis3dh)) => scope(
+                &mut screen,
+                &mut delay,
+                &timer,
+                lis3dh,
+                &mut buttons,
+                &theme,
+            ),
+            (MenuOption::CrashReport, _) => {
+                crash::crash_report(screen.panel(), &mut delay, &mut buttons, &theme)
+            }
+            (MenuOption::Settings, _) => {
+                preferences(&mut screen, &mut delay, &mut buttons, &mut lis3dh, &theme)
+            }
+            (MenuOption::Clock, _) => {
+                clock(&mut screen, &mut delay, &mut buttons, &mut lis3dh, &theme)
+            }
+            (MenuOption::Schedule, _) => {
+                agenda(&mut screen, &mut delay, &mut buttons, &mut lis3dh, &theme)
+            }
+            // the accelerometer apps, without one that did not come up at boot
+            (_, None) => Err(BadgeError::Accelerometer),
+        };
+
+        if let Err(error) = result {
+            error::error_screen(&mut display, &mut delay, &mut buttons, error);
         }
+
+        supervisor::leave();
     }
 }
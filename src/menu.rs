@@ -1,20 +1,28 @@
-use cortex_m::delay::Delay;
 use embedded_graphics::{
     Drawable,
-    mono_font::{MonoTextStyleBuilder, ascii::FONT_9X15_BOLD},
+    mono_font::MonoTextStyleBuilder,
     pixelcolor::{Rgb555, Rgb565, Rgb888},
-    prelude::{DrawTarget, Point, RgbColor, WebColors},
-    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    prelude::{DrawTarget, Point, RgbColor, Size, WebColors},
+    primitives::Rectangle,
 };
-use embedded_hal::{delay::DelayNs, digital::InputPin};
-use embedded_hal_compat::Forward;
-use rp2040_hal::gpio::{
-    FunctionSio, Pin, PullDown, SioInput,
-    bank0::{Gpio10, Gpio23, Gpio24},
+use embedded_hal::delay::DelayNs;
+
+use crate::{
+    RUST_PRIDE, TFT_DISPLAY_WIDTH,
+    backlight::{self, Panel, Tap},
+    bmp::{self, BmpExt},
+    console,
+    error::BadgeError,
+    input::Buttons,
+    power::Delay,
+    sprite::SpriteBuilder,
+    status::StatusBar,
+    text_box::{TextBox, VerticalAlignment},
+    theme::Theme,
 };
-use tinybmp::Bmp;
 
-use crate::{RUST_PRIDE, bmp::BmpExt, sprite::SpriteBuilder};
+// one label per row, centred on the option's point
+const LABEL_SIZE: Size = Size::new(TFT_DISPLAY_WIDTH as u32 - 28, 20);
 
 #[derive(PartialEq)]
 pub enum MenuOption {
@@ -23,18 +31,40 @@ pub enum MenuOption {
     Neopixel,
     HuntTheGopher,
     GopherbadgeRust,
+    AccelerometerScope,
+    CrashReport,
+    Settings,
+    Clock,
+    Schedule,
 }
 
 impl MenuOption {
-    pub fn options() -> [Self; 5] {
+    pub fn options() -> [Self; 10] {
         [
             Self::Badge,
             Self::AccelerometerDPad,
             Self::Neopixel,
             Self::HuntTheGopher,
             Self::GopherbadgeRust,
+            Self::AccelerometerScope,
+            Self::CrashReport,
+            Self::Settings,
+            Self::Clock,
+            Self::Schedule,
         ]
     }
+
+    /// Stable number for the option, kept across a watchdog reset.
+    pub fn id(&self) -> u16 {
+        Self::options()
+            .iter()
+            .position(|option| option == self)
+            .unwrap() as u16
+    }
+
+    pub fn from_id(id: u16) -> Option<Self> {
+        Self::options().into_iter().nth(id as usize)
+    }
 }
 
 impl Iterator for MenuOption {
@@ -45,7 +75,12 @@ impl Iterator for MenuOption {
             MenuOption::AccelerometerDPad => Some(MenuOption::Neopixel),
             MenuOption::Neopixel => Some(MenuOption::HuntTheGopher),
             MenuOption::HuntTheGopher => Some(MenuOption::GopherbadgeRust),
-            MenuOption::GopherbadgeRust => Some(MenuOption::Badge),
+            MenuOption::GopherbadgeRust => Some(MenuOption::AccelerometerScope),
+            MenuOption::AccelerometerScope => Some(MenuOption::CrashReport),
+            MenuOption::CrashReport => Some(MenuOption::Settings),
+            MenuOption::Settings => Some(MenuOption::Clock),
+            MenuOption::Clock => Some(MenuOption::Schedule),
+            MenuOption::Schedule => Some(MenuOption::Badge),
         }
     }
 }
@@ -53,11 +88,16 @@ impl Iterator for MenuOption {
 impl DoubleEndedIterator for MenuOption {
     fn next_back(&mut self) -> Option<Self::Item> {
         match self {
-            MenuOption::Badge => Some(MenuOption::GopherbadgeRust),
+            MenuOption::Badge => Some(MenuOption::Schedule),
             MenuOption::AccelerometerDPad => Some(MenuOption::Badge),
             MenuOption::Neopixel => Some(MenuOption::AccelerometerDPad),
             MenuOption::HuntTheGopher => Some(MenuOption::Neopixel),
             MenuOption::GopherbadgeRust => Some(MenuOption::HuntTheGopher),
+            MenuOption::AccelerometerScope => Some(MenuOption::GopherbadgeRust),
+            MenuOption::CrashReport => Some(MenuOption::AccelerometerScope),
+            MenuOption::Settings => Some(MenuOption::CrashReport),
+            MenuOption::Clock => Some(MenuOption::Settings),
+            MenuOption::Schedule => Some(MenuOption::Clock),
         }
     }
 }
@@ -70,6 +110,11 @@ impl From<&MenuOption> for Point {
             MenuOption::Neopixel => 60,
             MenuOption::HuntTheGopher => 80,
             MenuOption::GopherbadgeRust => 100,
+            MenuOption::AccelerometerScope => 120,
+            MenuOption::CrashReport => 140,
+            MenuOption::Settings => 160,
+            MenuOption::Clock => 180,
+            MenuOption::Schedule => 200,
         };
 
         Point::new(20, y)
@@ -84,83 +129,113 @@ impl From<&MenuOption> for &'static str {
             MenuOption::Neopixel => "Neopixel - Sight beyond sight",
             MenuOption::HuntTheGopher => "Hunt the Gopher",
             MenuOption::GopherbadgeRust => "gopherbadge-rs",
+            MenuOption::AccelerometerScope => "Accelerometer scope",
+            MenuOption::CrashReport => "Crash report",
+            MenuOption::Settings => "Settings",
+            MenuOption::Clock => "Clock",
+            MenuOption::Schedule => "Schedule",
         }
     }
 }
 
+fn draw_background<D, C>(display: &mut D, theme: &Theme<C>) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    display
+        .clear(theme.background)
+        .map_err(BadgeError::display)?;
+
+    let rust_logo_bmp = bmp::load(RUST_PRIDE)?;
+    let mut rust_logo_position = rust_logo_bmp.screen_bottom_right();
+    rust_logo_position.x -= 10;
+    rust_logo_position.y -= 10;
+    SpriteBuilder::<C>::builder(&rust_logo_bmp)
+        .with_position(rust_logo_position)
+        .with_transparency(C::BLACK)
+        .build()
+        .draw_with_transparency(display)
+        .map_err(BadgeError::display)
+}
+
 pub fn menu<D, C>(
     display: &mut D,
-    delay: &mut Forward<Delay>,
-    a_btn_pin: &mut Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
-    down_btn_pin: &mut Pin<Gpio23, FunctionSio<SioInput>, PullDown>,
-    up_btn_pin: &mut Pin<Gpio24, FunctionSio<SioInput>, PullDown>,
-) -> MenuOption
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+    theme: &Theme<C>,
+) -> Result<MenuOption, BadgeError>
 where
     C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
-    D: DrawTarget<Color = C>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
     D::Error: core::fmt::Debug,
 {
-    display.clear(C::CSS_ORANGE_RED).unwrap();
+    draw_background(display, theme)?;
     let mut selected_option = MenuOption::Badge;
     let mut redraw = true;
 
     let char_style = MonoTextStyleBuilder::new()
-        .font(&FONT_9X15_BOLD)
-        .text_color(C::CSS_WHITE)
-        .background_color(C::CSS_ORANGE_RED)
+        .font(theme.font)
+        .text_color(theme.foreground)
+        .background_color(theme.background)
         .build();
 
     let selected_char_style = MonoTextStyleBuilder::new()
-        .font(&FONT_9X15_BOLD)
-        .text_color(C::CSS_ORANGE_RED)
-        .background_color(C::CSS_WHITE)
-        .build();
-
-    let text_style = TextStyleBuilder::new()
-        .alignment(Alignment::Left)
-        .baseline(Baseline::Middle)
+        .font(theme.font)
+        .text_color(theme.background)
+        .background_color(theme.selection)
         .build();
 
-    let rust_logo_bmp = Bmp::from_slice(RUST_PRIDE).unwrap();
-    let mut rust_logo_position = rust_logo_bmp.screen_bottom_right();
-    rust_logo_position.x -= 10;
-    rust_logo_position.y -= 10;
-    SpriteBuilder::<C>::builder(&rust_logo_bmp)
-        .with_position(rust_logo_position)
-        .with_transparency(C::BLACK)
-        .build()
-        .draw_with_transparency(display);
-
+    // labels too long for the row, in the bigger fonts, end in dots
     let text = |option: &MenuOption, selected_option: &MenuOption| {
-        Text::with_text_style(
-            option.into(),
+        let top_left = Point::from(option) - Point::new(0, LABEL_SIZE.height as i32 / 2);
+        TextBox::new(
             option.into(),
+            Rectangle::new(top_left, LABEL_SIZE),
             if selected_option == option {
                 selected_char_style
             } else {
                 char_style
             },
-            text_style,
         )
+        .with_vertical_alignment(VerticalAlignment::Middle)
+        .with_ellipsis()
     };
 
     loop {
-        if down_btn_pin.is_low().unwrap() {
+        let input = buttons.poll();
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            draw_background(display, theme)?;
+            redraw = true;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, tap)?;
+        }
+        display.update_status()?;
+
+        if input.held.down {
             selected_option = selected_option.next().unwrap();
             redraw = true;
         }
-        if up_btn_pin.is_low().unwrap() {
+        if input.held.up {
             selected_option = selected_option.next_back().unwrap();
             redraw = true;
         }
-        if a_btn_pin.is_low().unwrap() {
+        if input.pressed.a {
             break;
         }
 
         if redraw {
-            MenuOption::options().iter().for_each(|option| {
-                text(option, &selected_option).draw(display).unwrap();
-            });
+            for option in MenuOption::options().iter() {
+                text(option, &selected_option)
+                    .draw(display)
+                    .map_err(BadgeError::display)?;
+            }
 
             delay.delay_ms(200);
             redraw = false;
@@ -169,5 +244,5 @@ where
         delay.delay_ms(10);
     }
 
-    selected_option
+    Ok(selected_option)
 }
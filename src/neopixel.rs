@@ -1,105 +1,250 @@
-use cortex_m::delay::Delay;
+use core::{fmt::Write, write};
 use embedded_graphics::{
     Drawable,
+    mono_font::MonoTextStyleBuilder,
     pixelcolor::{Rgb555, Rgb565, Rgb888},
     prelude::{DrawTarget, Point, Primitive, RgbColor, WebColors},
     primitives::{Circle, PrimitiveStyle},
+    text::{Baseline, Text},
 };
-use embedded_hal::{delay::DelayNs, digital::InputPin};
-use embedded_hal_compat::Forward;
-use rp2040_hal::{
-    gpio::{
-        FunctionPio0, FunctionSio, Pin, PullDown, SioInput,
-        bank0::{Gpio11, Gpio15},
-    },
-    pac::PIO0,
-    pio::SM0,
-    timer::CountDown,
+use embedded_hal::delay::DelayNs;
+use heapless::String;
+
+use crate::{
+    CONTENT_HEIGHT,
+    backlight::{self, Panel, Tap},
+    color::{ScreenBrightness, led_to_screen},
+    console,
+    error::BadgeError,
+    input::Buttons,
+    led_effects::{Animation, SPEED_MAX, SPEED_MIN},
+    leds,
+    log::log_color,
+    power::Delay,
+    record::LED_CAP_MIN,
+    settings,
+    status::StatusBar,
+    theme::Theme,
 };
-use smart_leds::{
-    RGB8, SmartLedsWrite,
-    hsv::{Hsv, hsv2rgb},
-};
-use ws2812_pio::Ws2812;
 
-use crate::log::log_color;
+// zero turns the ambient animation off
+const BRIGHTNESS_LEVELS: [u8; 5] = [0, 64, 128, 192, 255];
+const LED_CAP_STEP: u8 = 8;
 
-fn rgb8_to_rgb565(rgb: &RGB8) -> Rgb565 {
-    let max_rgb = rgb.r.max(rgb.g).max(rgb.b).max(1); // avoid divide-by-zero
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Effect,
+    Speed,
+    Brightness,
+    LedCap,
+}
 
-    let r_scaled = (rgb.r as u16 * 255 / max_rgb as u16).min(255);
-    let g_scaled = (rgb.g as u16 * 255 / max_rgb as u16).min(255);
-    let b_scaled = (rgb.b as u16 * 255 / max_rgb as u16).min(255);
+impl Field {
+    const ALL: [Field; 4] = [
+        Field::Effect,
+        Field::Speed,
+        Field::Brightness,
+        Field::LedCap,
+    ];
+
+    fn position(&self) -> Point {
+        let (x, y) = match self {
+            Field::Effect => (10, CONTENT_HEIGHT as i32 - 30),
+            Field::Speed => (170, CONTENT_HEIGHT as i32 - 30),
+            Field::Brightness => (10, CONTENT_HEIGHT as i32 - 12),
+            Field::LedCap => (170, CONTENT_HEIGHT as i32 - 12),
+        };
+        Point::new(x, y)
+    }
+}
 
-    let r5 = (r_scaled * 31 + 127) / 255;
-    let g6 = (g_scaled * 63 + 127) / 255;
-    let b5 = (b_scaled * 31 + 127) / 255;
+fn draw_fields<D, C>(
+    display: &mut D,
+    animation: &Animation,
+    led_cap: u8,
+    selected: Field,
+    theme: &Theme<C>,
+) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    for field in Field::ALL {
+        let mut buf: String<24> = String::new();
+        let _ = match field {
+            Field::Effect => write!(&mut buf, "effect: {:<9}", animation.effect.name()),
+            Field::Speed => write!(&mut buf, "speed: {:<2}", animation.speed),
+            Field::Brightness if animation.brightness == 0 => write!(&mut buf, "bright: off"),
+            Field::Brightness => write!(&mut buf, "bright: {:<3}", animation.brightness),
+            Field::LedCap => write!(&mut buf, "led cap: {:<3}", led_cap),
+        };
+
+        let (text_color, background_color) = if field == selected {
+            (theme.background, theme.selection)
+        } else {
+            (theme.foreground, theme.background)
+        };
+
+        Text::with_baseline(
+            &buf,
+            field.position(),
+            MonoTextStyleBuilder::new()
+                .font(theme.font)
+                .text_color(text_color)
+                .background_color(background_color)
+                .build(),
+            Baseline::Middle,
+        )
+        .draw(display)?;
+    }
 
-    Rgb565::new(r5 as u8, g6 as u8, b5 as u8)
+    Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn neopixel<D, C>(
-    display: &mut D,
-    delay: &mut Forward<Delay>,
-    b_btn_pin: &mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
-    ws: &mut Ws2812<PIO0, SM0, CountDown, Pin<Gpio15, FunctionPio0, PullDown>>,
-) where
+fn draw_background<D, C>(display: &mut D, theme: &Theme<C>) -> Result<(), D::Error>
+where
     C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
     D: DrawTarget<Color = C>,
-    D::Error: core::fmt::Debug,
 {
-    display.clear(C::CSS_PURPLE).unwrap();
+    display.clear(theme.background)?;
     let center = display.bounding_box().center();
 
     Circle::new(Point::new(center.x - 150, center.y - 70), 140)
         .into_styled(PrimitiveStyle::with_fill(C::WHITE))
-        .draw(display)
-        .unwrap();
+        .draw(display)?;
 
     Circle::new(Point::new(center.x + 10, center.y - 70), 140)
         .into_styled(PrimitiveStyle::with_fill(C::WHITE))
         .draw(display)
-        .unwrap();
+}
+
+fn step<T: Copy + PartialEq>(values: &[T], current: T, forward: bool) -> T {
+    let idx = values.iter().position(|v| *v == current).unwrap_or(0);
+    if forward {
+        values[(idx + 1).min(values.len() - 1)]
+    } else {
+        values[idx.saturating_sub(1)]
+    }
+}
+
+/// Picks the ambient led animation, mirrored on the gopher eyes. Up/Down move
+/// between the options, Left/Right change the selected one, A switches the
+/// eyes between true brightness and hue only, and B leaves. The
+/// animation keeps running in the background after leaving, the led cap is
+/// saved to flash.
+pub fn neopixel<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+{
+    draw_background(display, theme).map_err(BadgeError::display)?;
+    let center = display.bounding_box().center();
 
-    let mut hue: u8 = 0;
+    let mut animation = leds::ambient();
+    let mut led_cap = leds::brightness_cap();
+    let mut selected = Field::Effect;
+    let mut eye_brightness = ScreenBrightness::Preserve;
+
+    draw_fields(display, &animation, led_cap, selected, theme).map_err(BadgeError::display)?;
 
     loop {
-        let led1_color = hsv2rgb(Hsv {
-            hue,
-            sat: 255,
-            val: 32,
-        });
-        let led2_color = hsv2rgb(Hsv {
-            hue: 255 - hue,
-            sat: 255,
-            val: 32,
-        });
-
-        let eye1_color: C = rgb8_to_rgb565(&led1_color).into();
-        let eye2_color: C = rgb8_to_rgb565(&led2_color).into();
-
-        log_color(display, &eye1_color, &led1_color);
+        let input = buttons.poll();
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            draw_background(display, theme).map_err(BadgeError::display)?;
+            draw_fields(display, &animation, led_cap, selected, theme)
+                .map_err(BadgeError::display)?;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, tap)?;
+        }
+        display.update_status()?;
+
+        let [led1_color, led2_color] = leds::shown();
+
+        let eye1_color: C = led_to_screen(led1_color, eye_brightness).into();
+        let eye2_color: C = led_to_screen(led2_color, eye_brightness).into();
 
         Circle::new(Point::new(center.x - 140, center.y - 25), 50)
             .into_styled(PrimitiveStyle::with_fill(eye1_color))
             .draw(display)
-            .unwrap();
+            .map_err(BadgeError::display)?;
 
         Circle::new(Point::new(center.x + 20, center.y - 25), 50)
             .into_styled(PrimitiveStyle::with_fill(eye2_color))
             .draw(display)
-            .unwrap();
+            .map_err(BadgeError::display)?;
 
-        ws.write([led1_color, led2_color].iter().cloned()).unwrap();
-        hue = hue.wrapping_add(1);
+        let mut changed = false;
 
-        if b_btn_pin.is_low().unwrap() {
-            delay.delay_ms(10);
-            break;
+        if input.pressed.down {
+            selected = step(&Field::ALL, selected, true);
+            changed = true;
+        }
+        if input.pressed.up {
+            selected = step(&Field::ALL, selected, false);
+            changed = true;
+        }
+
+        let right = input.pressed.right;
+        let left = input.pressed.left;
+        if right || left {
+            match selected {
+                Field::Effect if right => animation.effect = animation.effect.next(),
+                Field::Effect => animation.effect = animation.effect.previous(),
+                Field::Speed if right => {
+                    animation.speed = animation
+                        .speed
+                        .saturating_add(1)
+                        .clamp(SPEED_MIN, SPEED_MAX)
+                }
+                Field::Speed => {
+                    animation.speed = animation
+                        .speed
+                        .saturating_sub(1)
+                        .clamp(SPEED_MIN, SPEED_MAX)
+                }
+                Field::Brightness => {
+                    animation.brightness = step(&BRIGHTNESS_LEVELS, animation.brightness, right)
+                }
+                Field::LedCap if right => led_cap = led_cap.saturating_add(LED_CAP_STEP),
+                Field::LedCap => led_cap = led_cap.saturating_sub(LED_CAP_STEP).max(LED_CAP_MIN),
+            }
+            leds::set_ambient(animation);
+            leds::set_brightness_cap(led_cap);
+            changed = true;
+        }
+
+        if input.pressed.a {
+            eye_brightness = match eye_brightness {
+                ScreenBrightness::Preserve => ScreenBrightness::Normalize,
+                ScreenBrightness::Normalize => ScreenBrightness::Preserve,
+            };
+            changed = true;
         }
-    }
 
-    ws.write([RGB8::new(0, 0, 0), RGB8::new(0, 0, 0)].iter().cloned())
-        .unwrap();
+        if changed {
+            draw_fields(display, &animation, led_cap, selected, theme)
+                .map_err(BadgeError::display)?;
+            log_color(&eye1_color, &led1_color);
+        }
+
+        if input.released.b {
+            let mut settings = settings::load();
+            settings.led_brightness_cap = led_cap;
+            settings.ambient = animation;
+            return settings::save(&settings);
+        }
+
+        delay.delay_ms(10);
+    }
 }
@@ -0,0 +1,198 @@
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use embedded_hal::delay::DelayNs;
+use rp2040_hal::{
+    Timer,
+    fugit::MicrosDurationU32,
+    pac::{self, interrupt},
+    timer::{Alarm, Alarm2},
+};
+
+use crate::input::Buttons;
+
+// waits shorter than this spin, an alarm is not worth setting up for them
+const SPIN_US: u32 = 50;
+// the longest single alarm, longer waits are done in pieces
+const MAX_WAIT_US: u32 = 1_000_000;
+
+// clk_sys divider while only a static screen is shown, 125 -> 31.25 MHz
+const REDUCED_DIVIDER: u32 = 4;
+// "coma", the magic that stops the crystal oscillator
+const XOSC_DORMANT: u32 = 0x636f_6d61;
+
+/// How fast the system clock runs.
+#[derive(Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum Speed {
+    Full = 1,
+    /// enough to redraw now and then and to keep usb going
+    Reduced = REDUCED_DIVIDER as u8,
+}
+
+static SPEED: AtomicU8 = AtomicU8::new(Speed::Full as u8);
+
+/// Waits on the timer with the core asleep in WFI. Timed off the 1 MHz
+/// reference tick, so it is not thrown off by `set_speed`.
+pub struct Delay {
+    timer: Timer,
+    alarm: Alarm2,
+}
+
+impl Delay {
+    pub fn new(mut timer: Timer) -> Self {
+        // only one `Delay` is made, at boot, see `supervisor::init`
+        let mut alarm = timer.alarm_2().expect("alarm 2 is the delay's");
+        alarm.enable_interrupt();
+
+        unsafe {
+            pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_2);
+        }
+
+        Self { timer, alarm }
+    }
+
+    fn spin_us(&self, us: u32) {
+        let start = self.timer.get_counter();
+        while (self.timer.get_counter() - start).to_micros() < us as u64 {}
+    }
+
+    fn wait_us(&mut self, mut us: u32) {
+        while us > 0 {
+            let step = us.min(MAX_WAIT_US);
+            us -= step;
+
+            if step < SPIN_US
+                || self
+                    .alarm
+                    .schedule(MicrosDurationU32::micros(step))
+                    .is_err()
+            {
+                self.spin_us(step);
+                continue;
+            }
+
+            while !self.alarm.finished() {
+                // with interrupts off the alarm cannot fire between the
+                // check and the wfi, and wfi still wakes for it
+                cortex_m::interrupt::free(|_| {
+                    if !self.alarm.finished() {
+                        cortex_m::asm::wfi();
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.wait_us(ns.div_ceil(1_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.wait_us(us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        for _ in 0..ms / 1_000 {
+            self.wait_us(1_000_000);
+        }
+        self.wait_us(ms % 1_000 * 1_000);
+    }
+}
+
+#[interrupt]
+fn TIMER_IRQ_2() {
+    // only there to wake the core, `Delay` looks at the armed bit
+    unsafe {
+        (*pac::TIMER::ptr())
+            .intr()
+            .write_with_zero(|w| w.alarm_2().clear_bit_by_one());
+    }
+}
+
+/// Changes the system clock on the fly. The neopixel state machine runs off
+/// clk_sys, so its divider is scaled along to keep the led timing.
+pub fn set_speed(speed: Speed) {
+    // only the foreground changes speed, no need for an atomic swap
+    let from = SPEED.load(Ordering::Relaxed) as u32;
+    let to = speed as u32;
+    if from == to {
+        return;
+    }
+    SPEED.store(speed as u8, Ordering::Relaxed);
+
+    critical_section::with(|_| {
+        // SAFETY: nothing else touches the clk_sys divider or the state
+        // machine clock after boot, and the leds interrupt is held off
+        unsafe {
+            let clocks = &*pac::CLOCKS::ptr();
+            let pio = &*pac::PIO0::ptr();
+
+            let sm = pio.sm(0);
+            let clkdiv = sm.sm_clkdiv().read();
+            let pio_div = (clkdiv.int().bits() as u32) << 8 | clkdiv.frac().bits() as u32;
+            let pio_div = (pio_div * from / to).max(1 << 8);
+
+            // slow the state machine down before the clock goes up, and the
+            // other way round, so it never runs faster than the leds take
+            let write_pio = || {
+                sm.sm_clkdiv().write(|w| {
+                    w.int()
+                        .bits((pio_div >> 8) as u16)
+                        .frac()
+                        .bits(pio_div as u8)
+                })
+            };
+            if to < from {
+                write_pio();
+            }
+            clocks.clk_sys_div().write(|w| w.int().bits(to));
+            if to > from {
+                write_pio();
+            }
+        }
+    });
+}
+
+/// Stops every clock, the crystal included, until a button goes down. USB
+/// and the timers stop with it, the caller makes sure nothing needs them.
+pub fn dormant(buttons: &mut Buttons) {
+    buttons.set_dormant_wake(true);
+
+    critical_section::with(|_| {
+        // SAFETY: interrupts are off and the core is the only one running,
+        // the clocks are put back exactly as `init_clocks_and_plls` left them
+        unsafe {
+            let clocks = &*pac::CLOCKS::ptr();
+            let xosc = &*pac::XOSC::ptr();
+            let plls = [&*pac::PLL_SYS::ptr(), &*pac::PLL_USB::ptr()];
+
+            // run from the crystal and stop the plls, they must not be
+            // running when it goes away
+            clocks.clk_sys_ctrl().modify(|_, w| w.src().clk_ref());
+            while clocks.clk_sys_selected().read().bits() != 1 {}
+            for pll in plls {
+                pll.pwr()
+                    .modify(|_, w| w.pd().set_bit().vcopd().set_bit().postdivpd().set_bit());
+            }
+
+            xosc.dormant().write(|w| w.bits(XOSC_DORMANT));
+            // a button edge restarts the crystal and the core goes on here
+            while xosc.status().read().stable().bit_is_clear() {}
+
+            for pll in plls {
+                pll.pwr()
+                    .modify(|_, w| w.pd().clear_bit().vcopd().clear_bit());
+                while pll.cs().read().lock().bit_is_clear() {}
+                pll.pwr().modify(|_, w| w.postdivpd().clear_bit());
+            }
+            clocks
+                .clk_sys_ctrl()
+                .modify(|_, w| w.src().clksrc_clk_sys_aux());
+            while clocks.clk_sys_selected().read().bits() != 2 {}
+        }
+    });
+
+    buttons.set_dormant_wake(false);
+}
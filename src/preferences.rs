@@ -0,0 +1,612 @@
+// The settings app and the `set` shell command, both driven by `PREFERENCES`:
+// one entry per option, saying what kind of value it is and where in
+// `Settings` it lives. A new option is a new entry, the screen and the shell
+// pick it up from there.
+
+use core::fmt::Write;
+
+use embedded_graphics::{
+    Drawable,
+    mono_font::MonoTextStyle,
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, Primitive, RgbColor, Size, WebColors},
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text},
+};
+use embedded_hal::delay::DelayNs;
+use heapless::String;
+
+use crate::{
+    TFT_DISPLAY_WIDTH,
+    backlight::{self, Panel, Tap},
+    console,
+    contact::Field,
+    error::BadgeError,
+    font::FontStyle,
+    input::Buttons,
+    keyboard::keyboard,
+    led_effects::{Effect, SPEED_MAX, SPEED_MIN},
+    leds,
+    power::Delay,
+    qr::Ecc,
+    record::{BACKLIGHT_MIN, LED_CAP_MIN, QrContent, Settings, TILT_MAX, TILT_MIN, ThemeName},
+    settings,
+    status::StatusBar,
+    text_box::{TextBox, VerticalAlignment},
+    theme::Theme,
+};
+
+// zero is never
+const DIM_AFTER: [(u16, &str); 6] = [
+    (0, "never"),
+    (10, "10s"),
+    (30, "30s"),
+    (60, "1min"),
+    (120, "2min"),
+    (300, "5min"),
+];
+const SLEEP_AFTER: [(u16, &str); 6] = [
+    (0, "never"),
+    (60, "1min"),
+    (120, "2min"),
+    (300, "5min"),
+    (600, "10min"),
+    (1800, "30min"),
+];
+
+const ROW_HEIGHT: u32 = 20;
+const ROWS_TOP: i32 = 2;
+const ROWS: usize = 11;
+const LABEL_X: i32 = 8;
+const VALUE_X: i32 = 160;
+const VALUE_WIDTH: u32 = TFT_DISPLAY_WIDTH as u32 - VALUE_X as u32 - 8;
+const BAR: Size = Size::new(100, 8);
+
+pub enum Kind {
+    Bool {
+        get: fn(&Settings) -> bool,
+        set: fn(&mut Settings, bool),
+    },
+    /// one of the names `name` gives for 0, 1, .. up to the first `None`
+    Choice {
+        name: fn(usize) -> Option<&'static str>,
+        get: fn(&Settings) -> usize,
+        set: fn(&mut Settings, usize),
+    },
+    Slider {
+        min: u16,
+        max: u16,
+        step: u16,
+        get: fn(&Settings) -> u16,
+        set: fn(&mut Settings, u16),
+    },
+    /// a badge field, edited with the keyboard
+    Text(Field),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Value<'a> {
+    Bool(bool),
+    Choice(usize),
+    Number(u16),
+    Text(&'a str),
+}
+
+pub struct Preference {
+    /// as typed in the shell
+    pub name: &'static str,
+    /// as shown on the screen
+    pub label: &'static str,
+    pub kind: Kind,
+}
+
+pub static PREFERENCES: [Preference; 18] = [
+    Preference {
+        name: "brightness",
+        label: "brightness",
+        kind: Kind::Slider {
+            min: BACKLIGHT_MIN as u16,
+            max: u8::MAX as u16,
+            step: 16,
+            get: |s| s.backlight as u16,
+            set: |s, level| s.backlight = level as u8,
+        },
+    },
+    Preference {
+        name: "dim-after",
+        label: "dim after",
+        kind: Kind::Choice {
+            name: |i| DIM_AFTER.get(i).map(|(_, name)| *name),
+            get: |s| {
+                DIM_AFTER
+                    .iter()
+                    .position(|(seconds, _)| *seconds == s.dim_after_s)
+                    .unwrap_or(0)
+            },
+            set: |s, i| s.dim_after_s = DIM_AFTER[i].0,
+        },
+    },
+    Preference {
+        name: "sleep-after",
+        label: "sleep after",
+        kind: Kind::Choice {
+            name: |i| SLEEP_AFTER.get(i).map(|(_, name)| *name),
+            get: |s| {
+                SLEEP_AFTER
+                    .iter()
+                    .position(|(seconds, _)| *seconds == s.sleep_after_s)
+                    .unwrap_or(0)
+            },
+            set: |s, i| s.sleep_after_s = SLEEP_AFTER[i].0,
+        },
+    },
+    Preference {
+        name: "tap-wake",
+        label: "tap to wake",
+        kind: Kind::Bool {
+            get: |s| s.tap_wake,
+            set: |s, on| s.tap_wake = on,
+        },
+    },
+    Preference {
+        name: "theme",
+        label: "theme",
+        kind: Kind::Choice {
+            name: |i| ThemeName::ALL.get(i).map(ThemeName::name),
+            get: |s| s.theme as usize,
+            set: |s, i| s.theme = ThemeName::ALL[i],
+        },
+    },
+    Preference {
+        name: "effect",
+        label: "led effect",
+        kind: Kind::Choice {
+            name: |i| Effect::ALL.get(i).map(Effect::name),
+            get: |s| s.ambient.effect as usize,
+            set: |s, i| s.ambient.effect = Effect::ALL[i],
+        },
+    },
+    Preference {
+        name: "speed",
+        label: "led speed",
+        kind: Kind::Slider {
+            min: SPEED_MIN as u16,
+            max: SPEED_MAX as u16,
+            step: 1,
+            get: |s| s.ambient.speed as u16,
+            set: |s, speed| s.ambient.speed = speed as u8,
+        },
+    },
+    Preference {
+        name: "led-brightness",
+        label: "led brightness",
+        kind: Kind::Slider {
+            min: 0,
+            max: u8::MAX as u16,
+            step: 16,
+            get: |s| s.ambient.brightness as u16,
+            set: |s, brightness| s.ambient.brightness = brightness as u8,
+        },
+    },
+    Preference {
+        name: "led-cap",
+        label: "led cap",
+        kind: Kind::Slider {
+            min: LED_CAP_MIN as u16,
+            max: u8::MAX as u16,
+            step: 8,
+            get: |s| s.led_brightness_cap as u16,
+            set: |s, cap| s.led_brightness_cap = cap as u8,
+        },
+    },
+    Preference {
+        name: "tilt",
+        label: "tilt sensitivity",
+        kind: Kind::Slider {
+            min: TILT_MIN as u16,
+            max: TILT_MAX as u16,
+            step: 1,
+            get: |s| s.tilt as u16,
+            set: |s, tilt| s.tilt = tilt as u8,
+        },
+    },
+    Preference {
+        name: "qr",
+        label: "qr code",
+        kind: Kind::Choice {
+            name: |i| QrContent::ALL.get(i).map(QrContent::name),
+            get: |s| s.qr as usize,
+            set: |s, i| s.qr = QrContent::ALL[i],
+        },
+    },
+    Preference {
+        name: "qr-ecc",
+        label: "qr ecc",
+        kind: Kind::Choice {
+            name: |i| Ecc::ALL.get(i).map(Ecc::name),
+            get: |s| s.qr_ecc as usize,
+            set: |s, i| s.qr_ecc = Ecc::ALL[i],
+        },
+    },
+    Preference {
+        name: "name",
+        label: "name",
+        kind: Kind::Text(Field::Name),
+    },
+    Preference {
+        name: "email",
+        label: "email",
+        kind: Kind::Text(Field::Email),
+    },
+    Preference {
+        name: "github",
+        label: "github",
+        kind: Kind::Text(Field::Github),
+    },
+    Preference {
+        name: "mastodon",
+        label: "mastodon",
+        kind: Kind::Text(Field::Mastodon),
+    },
+    Preference {
+        name: "company",
+        label: "company",
+        kind: Kind::Text(Field::Company),
+    },
+    Preference {
+        name: "url",
+        label: "url",
+        kind: Kind::Text(Field::Url),
+    },
+];
+
+fn choices(name: fn(usize) -> Option<&'static str>) -> impl Iterator<Item = &'static str> {
+    (0..).map_while(name)
+}
+
+impl Preference {
+    pub fn value<'a>(&self, settings: &'a Settings) -> Value<'a> {
+        match &self.kind {
+            Kind::Bool { get, .. } => Value::Bool(get(settings)),
+            Kind::Choice { get, .. } => Value::Choice(get(settings)),
+            Kind::Slider { get, .. } => Value::Number(get(settings)),
+            Kind::Text(field) => Value::Text(settings.contact.get(*field)),
+        }
+    }
+
+    /// False when `value` is not one this option takes, or does not fit.
+    /// Numbers are held to the slider's range.
+    pub fn set(&self, settings: &mut Settings, value: Value) -> bool {
+        match (&self.kind, value) {
+            (Kind::Bool { set, .. }, Value::Bool(on)) => set(settings, on),
+            (Kind::Choice { name, set, .. }, Value::Choice(i)) if name(i).is_some() => {
+                set(settings, i)
+            }
+            (Kind::Slider { min, max, set, .. }, Value::Number(number)) => {
+                set(settings, number.clamp(*min, *max))
+            }
+            (Kind::Text(field), Value::Text(text)) => return settings.contact.set(*field, text),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Moves on to the next value, or back to the one before. Choices go
+    /// round, sliders stop at their ends.
+    pub fn step(&self, settings: &mut Settings, forward: bool) {
+        let next = match (&self.kind, self.value(settings)) {
+            (Kind::Bool { .. }, Value::Bool(on)) => Value::Bool(!on),
+            (Kind::Choice { name, .. }, Value::Choice(i)) => {
+                let count = choices(*name).count();
+                Value::Choice(if forward {
+                    (i + 1) % count
+                } else {
+                    (i + count - 1) % count
+                })
+            }
+            (Kind::Slider { step, .. }, Value::Number(number)) if forward => {
+                Value::Number(number.saturating_add(*step))
+            }
+            (Kind::Slider { step, .. }, Value::Number(number)) => {
+                Value::Number(number.saturating_sub(*step))
+            }
+            _ => return,
+        };
+        self.set(settings, next);
+    }
+
+    /// Reads a value as typed in the shell.
+    pub fn parse<'a>(&self, text: &'a str) -> Option<Value<'a>> {
+        match &self.kind {
+            Kind::Bool { .. } => match text {
+                "on" | "yes" | "true" | "1" => Some(Value::Bool(true)),
+                "off" | "no" | "false" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            Kind::Choice { name, .. } => choices(*name)
+                .position(|choice| choice.eq_ignore_ascii_case(text))
+                .map(Value::Choice),
+            Kind::Slider { .. } => text.parse().ok().map(Value::Number),
+            Kind::Text(_) => Some(Value::Text(text)),
+        }
+    }
+
+    /// The value as text, the way `parse` takes it.
+    pub fn show(&self, settings: &Settings) -> String<64> {
+        let mut out = String::new();
+        let _ = match self.value(settings) {
+            Value::Bool(on) => out.write_str(if on { "on" } else { "off" }),
+            Value::Choice(i) => match &self.kind {
+                Kind::Choice { name, .. } => out.write_str(name(i).unwrap_or("?")),
+                _ => Ok(()),
+            },
+            Value::Number(number) => write!(out, "{}", number),
+            Value::Text(text) => out.write_str(text),
+        };
+        out
+    }
+
+    /// What `parse` takes, for the shell.
+    pub fn usage(&self) -> String<64> {
+        let mut out = String::new();
+        let _ = match &self.kind {
+            Kind::Bool { .. } => out.write_str("on|off"),
+            Kind::Choice { name, .. } => choices(*name).enumerate().try_for_each(|(i, choice)| {
+                if i > 0 {
+                    out.write_char('|')?;
+                }
+                out.write_str(choice)
+            }),
+            Kind::Slider { min, max, .. } => write!(out, "{}-{}", min, max),
+            Kind::Text(_) => out.write_str("text"),
+        };
+        out
+    }
+}
+
+/// The option with that name in the shell.
+pub fn find(name: &str) -> Option<&'static Preference> {
+    PREFERENCES
+        .iter()
+        .find(|preference| preference.name == name)
+}
+
+/// Puts everything but the theme into effect right away, the theme is
+/// picked up by the next app.
+pub fn apply(settings: &Settings) {
+    backlight::set_level(settings.backlight);
+    backlight::set_timeouts(settings.dim_after_s, settings.sleep_after_s);
+    backlight::set_tap_wake(settings.tap_wake);
+    leds::set_brightness_cap(settings.led_brightness_cap);
+    leds::set_ambient(settings.ambient);
+}
+
+fn draw_row<D, C>(
+    display: &mut D,
+    settings: &Settings,
+    index: usize,
+    row: usize,
+    selected: bool,
+    theme: &Theme<C>,
+) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    let bounds = Rectangle::new(
+        Point::new(0, ROWS_TOP + (row as u32 * ROW_HEIGHT) as i32),
+        Size::new(TFT_DISPLAY_WIDTH as u32, ROW_HEIGHT),
+    );
+    let (ink, paper) = if selected {
+        (theme.background, theme.selection)
+    } else {
+        (theme.foreground, theme.background)
+    };
+    display.fill_solid(&bounds, paper)?;
+    let Some(preference) = PREFERENCES.get(index) else {
+        return Ok(());
+    };
+
+    let y = bounds.center().y;
+    Text::with_baseline(
+        preference.label,
+        Point::new(LABEL_X, y),
+        MonoTextStyle::new(theme.font, ink),
+        Baseline::Middle,
+    )
+    .draw(display)?;
+
+    let value = Rectangle::new(
+        Point::new(VALUE_X, bounds.top_left.y),
+        Size::new(VALUE_WIDTH, ROW_HEIGHT),
+    );
+    if let Kind::Slider { min, max, get, .. } = preference.kind {
+        let bar = Rectangle::new(Point::new(VALUE_X, y - BAR.height as i32 / 2), BAR);
+        let filled = BAR.width * (get(settings).clamp(min, max) - min) as u32 / (max - min) as u32;
+        bar.into_styled(PrimitiveStyle::with_stroke(ink, 1))
+            .draw(display)?;
+        Rectangle::new(bar.top_left, Size::new(filled, BAR.height))
+            .into_styled(PrimitiveStyle::with_fill(ink))
+            .draw(display)?;
+    }
+    // long text ends in dots, the keyboard shows all of it
+    TextBox::new(
+        &preference.show(settings),
+        value,
+        FontStyle::new(theme.text_font, ink),
+    )
+    .with_alignment(Alignment::Right)
+    .with_vertical_alignment(VerticalAlignment::Middle)
+    .with_ellipsis()
+    .draw(display)
+}
+
+fn draw_rows<D, C>(
+    display: &mut D,
+    settings: &Settings,
+    scroll: usize,
+    selected: usize,
+    theme: &Theme<C>,
+) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    for row in 0..ROWS {
+        let index = scroll + row;
+        draw_row(display, settings, index, row, index == selected, theme)?;
+    }
+    Ok(())
+}
+
+/// Every option in `PREFERENCES`, a row each. Up/Down pick one, Left/Right
+/// change it and A toggles, cycles or opens the keyboard for text. Changes
+/// take effect straight away and are saved when B leaves. The same options
+/// can be set over usb with `set`.
+pub fn preferences<D, C>(
+    display: &mut D,
+    delay: &mut Delay,
+    buttons: &mut Buttons,
+    tap: &mut impl Tap,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+{
+    // what the shell changes is merged into the copy being edited
+    let mut saved = settings::current();
+    let mut settings = saved.clone();
+    let mut theme = *theme;
+    let mut shown = settings.theme;
+    let mut selected = 0;
+    let mut scroll = 0;
+    let mut redraw = true;
+    // rows paint their own background, the rest only when something covered it
+    let mut clear = true;
+
+    loop {
+        let input = buttons.poll();
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            clear = true;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, tap)?;
+        }
+        display.update_status()?;
+
+        let current = settings::current();
+        if current != saved {
+            for preference in &PREFERENCES {
+                let value = preference.value(&current);
+                if value != preference.value(&saved) {
+                    preference.set(&mut settings, value);
+                }
+            }
+            saved = current;
+            redraw = true;
+        }
+
+        let before = selected;
+        if input.pressed.down {
+            selected = (selected + 1) % PREFERENCES.len();
+        }
+        if input.pressed.up {
+            selected = (selected + PREFERENCES.len() - 1) % PREFERENCES.len();
+        }
+        let preference = &PREFERENCES[selected];
+
+        // sliders move while held, everything else a step per press
+        let slider = matches!(preference.kind, Kind::Slider { .. });
+        let right = input.pressed.right || (slider && input.held.right);
+        let left = input.pressed.left || (slider && input.held.left);
+        let mut changed = false;
+        if right || left {
+            preference.step(&mut settings, right);
+            changed = true;
+        }
+        if input.pressed.a {
+            match preference.kind {
+                Kind::Text(field) => {
+                    let edited: Option<String<64>> = keyboard(
+                        display,
+                        delay,
+                        buttons,
+                        tap,
+                        preference.label,
+                        settings.contact.get(field),
+                        settings.contact.capacity(field),
+                    )?;
+                    if let Some(text) = edited {
+                        settings.contact.set(field, &text);
+                    }
+                    clear = true;
+                }
+                Kind::Slider { .. } => {}
+                _ => {
+                    preference.step(&mut settings, true);
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            apply(&settings);
+        }
+        // shown in the new theme as soon as it is picked
+        if settings.theme != shown {
+            shown = settings.theme;
+            theme = Theme::new(shown);
+            clear = true;
+        }
+
+        if selected < scroll {
+            scroll = selected;
+            redraw = true;
+        }
+        if selected >= scroll + ROWS {
+            scroll = selected + 1 - ROWS;
+            redraw = true;
+        }
+
+        if clear {
+            display
+                .clear(theme.background)
+                .map_err(BadgeError::display)?;
+            clear = false;
+            redraw = true;
+        }
+        if redraw {
+            draw_rows(display, &settings, scroll, selected, &theme).map_err(BadgeError::display)?;
+            redraw = false;
+        } else if changed || before != selected {
+            for index in [before, selected] {
+                draw_row(
+                    display,
+                    &settings,
+                    index,
+                    index - scroll,
+                    index == selected,
+                    &theme,
+                )
+                .map_err(BadgeError::display)?;
+            }
+        }
+
+        if input.released.b {
+            settings::update(|saved| {
+                for preference in &PREFERENCES {
+                    preference.set(saved, preference.value(&settings));
+                }
+            });
+            settings::flush();
+            return Ok(());
+        }
+
+        delay.delay_ms(if changed { 80 } else { 10 });
+    }
+}
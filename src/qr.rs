@@ -0,0 +1,822 @@
+// QR code encoder, byte mode only, after the QR Code Model 2 spec (ISO/IEC
+// 18004). Versions up to `MAX_VERSION` are enough for a vCard and keep the
+// buffers on the stack small. Only depends on `core` and embedded-graphics,
+// so it builds for the host as well.
+
+use embedded_graphics::{
+    prelude::{DrawTarget, PixelColor, Point, Size},
+    primitives::Rectangle,
+};
+
+pub const MAX_VERSION: usize = 15;
+const MAX_SIZE: usize = MAX_VERSION * 4 + 17;
+// raw codewords of the largest version
+const MAX_CODEWORDS: usize = 655;
+const MAX_BLOCKS: usize = 18;
+/// Light modules around the code, the spec asks for four.
+pub const QUIET_ZONE: u32 = 4;
+
+/// How much of the code can be damaged and still read, roughly 7, 15, 25
+/// and 30 percent.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Ecc {
+    Low,
+    Medium,
+    Quartile,
+    High,
+}
+
+impl Ecc {
+    pub const ALL: [Ecc; 4] = [Ecc::Low, Ecc::Medium, Ecc::Quartile, Ecc::High];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ecc::Low => "L",
+            Ecc::Medium => "M",
+            Ecc::Quartile => "Q",
+            Ecc::High => "H",
+        }
+    }
+
+    // as written into the format information
+    fn format_bits(&self) -> u32 {
+        match self {
+            Ecc::Low => 1,
+            Ecc::Medium => 0,
+            Ecc::Quartile => 3,
+            Ecc::High => 2,
+        }
+    }
+}
+
+// per level, per version from 1
+const ECC_CODEWORDS_PER_BLOCK: [[u8; MAX_VERSION]; 4] = [
+    [7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22],
+    [10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24],
+    [13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30],
+    [17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24],
+];
+const ECC_BLOCKS: [[u8; MAX_VERSION]; 4] = [
+    [1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6],
+    [1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10],
+    [1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12],
+    [1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18],
+];
+
+/// The data does not fit the largest version at the chosen level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TooLong;
+
+// modules that carry data, everything but the function patterns
+fn raw_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+fn ecc_layout(version: usize, ecc: Ecc) -> (usize, usize) {
+    (
+        ECC_CODEWORDS_PER_BLOCK[ecc as usize][version - 1] as usize,
+        ECC_BLOCKS[ecc as usize][version - 1] as usize,
+    )
+}
+
+fn data_codewords(version: usize, ecc: Ecc) -> usize {
+    let (per_block, blocks) = ecc_layout(version, ecc);
+    raw_modules(version) / 8 - per_block * blocks
+}
+
+// GF(2^8) with the polynomial 0x11d
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= ((y >> i) & 1) as u16 * x as u16;
+    }
+    z as u8
+}
+
+fn rs_divisor(degree: usize) -> [u8; 30] {
+    // coefficients from the highest power down, the leading 1 left out
+    let mut divisor = [0u8; 30];
+    divisor[degree - 1] = 1;
+    let mut root: u8 = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            divisor[j] = gf_multiply(divisor[j], root);
+            if j + 1 < degree {
+                divisor[j] ^= divisor[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    divisor
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8], out: &mut [u8]) {
+    out.fill(0);
+    for byte in data {
+        let factor = byte ^ out[0];
+        out.copy_within(1.., 0);
+        let last = out.len() - 1;
+        out[last] = 0;
+        for (o, d) in out.iter_mut().zip(divisor) {
+            *o ^= gf_multiply(*d, factor);
+        }
+    }
+}
+
+struct Bits {
+    bytes: [u8; MAX_CODEWORDS],
+    len: usize,
+}
+
+impl Bits {
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if (value >> i) & 1 != 0 {
+                self.bytes[self.len / 8] |= 0x80 >> (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+}
+
+/// A finished code, `size` modules square.
+pub struct QrCode {
+    size: usize,
+    modules: [u128; MAX_SIZE],
+    function: [u128; MAX_SIZE],
+}
+
+impl QrCode {
+    /// Encodes `data` in the smallest version that holds it at `ecc`.
+    pub fn encode(data: &[u8], ecc: Ecc) -> Result<Self, TooLong> {
+        let version = (1..=MAX_VERSION)
+            .find(|version| {
+                let count_bits = if *version < 10 { 8 } else { 16 };
+                4 + count_bits + data.len() * 8 <= data_codewords(*version, ecc) * 8
+            })
+            .ok_or(TooLong)?;
+
+        let capacity = data_codewords(version, ecc);
+        let mut bits = Bits {
+            bytes: [0; MAX_CODEWORDS],
+            len: 0,
+        };
+        // byte mode, the length, the data
+        bits.push(0b0100, 4);
+        bits.push(data.len() as u32, if version < 10 { 8 } else { 16 });
+        for byte in data {
+            bits.push(*byte as u32, 8);
+        }
+        // terminator, up to a byte boundary, then alternating pad bytes
+        bits.push(0, (capacity * 8 - bits.len).min(4));
+        bits.push(0, (8 - bits.len % 8) % 8);
+        for pad in [0xec, 0x11].into_iter().cycle() {
+            if bits.len >= capacity * 8 {
+                break;
+            }
+            bits.push(pad, 8);
+        }
+
+        let mut codewords = [0u8; MAX_CODEWORDS];
+        let len = interleave(&bits.bytes[..capacity], version, ecc, &mut codewords);
+
+        let mut code = Self {
+            size: version * 4 + 17,
+            modules: [0; MAX_SIZE],
+            function: [0; MAX_SIZE],
+        };
+        code.draw_function_patterns(version);
+        code.draw_codewords(&codewords[..len]);
+
+        // keep the mask that leaves the fewest confusing patterns
+        let mut best = (u32::MAX, 0);
+        for mask in 0..8 {
+            code.apply_mask(mask);
+            code.draw_format(ecc, mask);
+            let penalty = code.penalty();
+            if penalty < best.0 {
+                best = (penalty, mask);
+            }
+            // masking twice undoes it
+            code.apply_mask(mask);
+        }
+        code.apply_mask(best.1);
+        code.draw_format(ecc, best.1);
+
+        Ok(code)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module at column `x`, row `y` is dark.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y] & (1 << x) != 0
+    }
+
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        if dark {
+            self.modules[y] |= 1 << x;
+        } else {
+            self.modules[y] &= !(1 << x);
+        }
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.set(x, y, dark);
+        self.function[y] |= 1 << x;
+    }
+
+    fn is_function(&self, x: usize, y: usize) -> bool {
+        self.function[y] & (1 << x) != 0
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        for (x, y) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                    if (0..size as i32).contains(&xx) && (0..size as i32).contains(&yy) {
+                        let distance = dx.abs().max(dy.abs());
+                        self.set_function(xx as usize, yy as usize, distance != 2 && distance != 4);
+                    }
+                }
+            }
+        }
+
+        let (positions, count) = alignment_positions(version);
+        for i in 0..count {
+            for j in 0..count {
+                // the corners with a finder pattern
+                let last = count - 1;
+                if (i, j) == (0, 0) || (i, j) == (0, last) || (i, j) == (last, 0) {
+                    continue;
+                }
+                for dy in -2i32..=2 {
+                    for dx in -2i32..=2 {
+                        self.set_function(
+                            (positions[i] as i32 + dx) as usize,
+                            (positions[j] as i32 + dy) as usize,
+                            dx.abs().max(dy.abs()) != 1,
+                        );
+                    }
+                }
+            }
+        }
+
+        // reserved now, filled in once the mask is known
+        self.draw_format(Ecc::Low, 0);
+
+        if version >= 7 {
+            let mut remainder = version as u32;
+            for _ in 0..12 {
+                remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1f25);
+            }
+            let bits = (version as u32) << 12 | remainder;
+            for i in 0..18 {
+                let dark = (bits >> i) & 1 != 0;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                self.set_function(a, b, dark);
+                self.set_function(b, a, dark);
+            }
+        }
+    }
+
+    fn draw_format(&mut self, ecc: Ecc, mask: u8) {
+        let data = ecc.format_bits() << 3 | mask as u32;
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (data << 10 | remainder) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        // around the top left finder
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        // and split between the other two
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        // two columns at a time from the right, snaking up and down
+        let mut right = size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward {
+                        size - 1 - vertical
+                    } else {
+                        vertical
+                    };
+                    if !self.is_function(x, y) && i < codewords.len() * 8 {
+                        self.set(x, y, (codewords[i / 8] >> (7 - i % 8)) & 1 != 0);
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.is_function(x, y) {
+                    self.modules[y] ^= 1 << x;
+                }
+            }
+        }
+    }
+
+    fn penalty(&self) -> u32 {
+        let size = self.size;
+        let mut penalty = 0;
+
+        // the same for rows and for columns
+        for transposed in [false, true] {
+            let get = |a: usize, b: usize| {
+                if transposed {
+                    self.get(b, a)
+                } else {
+                    self.get(a, b)
+                }
+            };
+            for line in 0..size {
+                // long runs of one color
+                let mut run = 1;
+                for i in 1..size {
+                    if get(i, line) == get(i - 1, line) {
+                        run += 1;
+                        if run == 5 {
+                            penalty += 3;
+                        } else if run > 5 {
+                            penalty += 1;
+                        }
+                    } else {
+                        run = 1;
+                    }
+                }
+
+                // anything that looks like a finder pattern, dark light dark
+                // light dark at 1:1:3:1:1 with four times as much light on a
+                // side. Runs alternate from light, the quiet zone counts as
+                // light as far out as it is needed.
+                let mut runs = [0; MAX_SIZE + 2];
+                let mut count = 1;
+                runs[0] = size;
+                for i in 0..size {
+                    if get(i, line) == (count % 2 == 0) {
+                        runs[count - 1] += 1;
+                    } else {
+                        runs[count] = 1;
+                        count += 1;
+                    }
+                }
+                if count % 2 == 0 {
+                    runs[count] = size;
+                    count += 1;
+                } else {
+                    runs[count - 1] += size;
+                }
+                for j in (3..count.saturating_sub(3)).step_by(2) {
+                    let n = runs[j - 2];
+                    let finder = runs[j - 1] == n
+                        && runs[j] == 3 * n
+                        && runs[j + 1] == n
+                        && runs[j + 2] == n;
+                    let (before, after) = (runs[j - 3], runs[j + 3]);
+                    if finder && before >= 4 * n && after >= n {
+                        penalty += 40;
+                    }
+                    if finder && after >= 4 * n && before >= n {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+
+        // 2x2 blocks of one color
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.get(x, y);
+                if color == self.get(x + 1, y)
+                    && color == self.get(x, y + 1)
+                    && color == self.get(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+
+        // far from half dark
+        let dark: u32 = self.modules[..size]
+            .iter()
+            .map(|row| row.count_ones())
+            .sum();
+        let total = (size * size) as u32;
+        // every full 5% away from half, rounded up past the first
+        let steps = (dark * 20).abs_diff(total * 10).div_ceil(total);
+        penalty + steps.saturating_sub(1) * 10
+    }
+
+    /// Draws the code with its quiet zone, `scale` pixels per module.
+    pub fn draw<D, C>(
+        &self,
+        display: &mut D,
+        top_left: Point,
+        scale: u32,
+        dark: C,
+        light: C,
+    ) -> Result<(), D::Error>
+    where
+        C: PixelColor,
+        D: DrawTarget<Color = C>,
+    {
+        display.fill_solid(
+            &Rectangle::new(top_left, Size::new_equal(self.pixel_size(scale))),
+            light,
+        )?;
+
+        let origin = top_left + Point::new_equal((QUIET_ZONE * scale) as i32);
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.get(x, y) {
+                    let position = Point::new((x as u32 * scale) as i32, (y as u32 * scale) as i32);
+                    display.fill_solid(
+                        &Rectangle::new(origin + position, Size::new_equal(scale)),
+                        dark,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Width and height of `draw`, quiet zone included.
+    pub fn pixel_size(&self, scale: u32) -> u32 {
+        (self.size as u32 + 2 * QUIET_ZONE) * scale
+    }
+}
+
+fn alignment_positions(version: usize) -> ([usize; 7], usize) {
+    let mut positions = [0; 7];
+    if version == 1 {
+        return (positions, 0);
+    }
+
+    let count = version / 7 + 2;
+    let step = (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2;
+    positions[0] = 6;
+    let mut position = version * 4 + 17 - 7;
+    for i in (1..count).rev() {
+        positions[i] = position;
+        position -= step;
+    }
+    (positions, count)
+}
+
+// splits the data into blocks, appends the error correction to each and
+// interleaves them, returns how many codewords that made
+fn interleave(data: &[u8], version: usize, ecc: Ecc, out: &mut [u8; MAX_CODEWORDS]) -> usize {
+    let (ecc_len, blocks) = ecc_layout(version, ecc);
+    let raw = raw_modules(version) / 8;
+    let short_blocks = blocks - raw % blocks;
+    let short_len = raw / blocks;
+    let divisor = rs_divisor(ecc_len);
+
+    // each block padded to the long length, the gap in short ones skipped
+    let block_len = short_len + 1;
+    let mut buffer = [0u8; MAX_CODEWORDS + MAX_BLOCKS];
+    let mut offset = 0;
+    for i in 0..blocks {
+        let data_len = short_len - ecc_len + if i < short_blocks { 0 } else { 1 };
+        let block = &mut buffer[i * block_len..(i + 1) * block_len];
+        block[..data_len].copy_from_slice(&data[offset..offset + data_len]);
+        let mut remainder = [0u8; 30];
+        rs_remainder(
+            &data[offset..offset + data_len],
+            &divisor[..ecc_len],
+            &mut remainder[..ecc_len],
+        );
+        block[block_len - ecc_len..].copy_from_slice(&remainder[..ecc_len]);
+        offset += data_len;
+    }
+
+    let mut len = 0;
+    for i in 0..block_len {
+        for j in 0..blocks {
+            if i != short_len - ecc_len || j >= short_blocks {
+                out[len] = buffer[j * block_len + i];
+                len += 1;
+            }
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use heapless::String;
+
+    use super::*;
+    use crate::contact::{Contact, Field};
+
+    // the same data encoded by qrcodegen, '#' is a dark module
+    // version 1, mask 7
+    const V1_L: [&str; 21] = [
+        "#######..#.##.#######",
+        "#.....#.##.#..#.....#",
+        "#.###.#.##..#.#.###.#",
+        "#.###.#..#.#..#.###.#",
+        "#.###.#.#...#.#.###.#",
+        "#.....#.#..##.#.....#",
+        "#######.#.#.#.#######",
+        "........#####........",
+        "##.#..##.##...###.##.",
+        ".##.##..#....#.##.###",
+        "#.#..####.#.#.##.#..#",
+        "...###..#.##..####...",
+        "...#..#####.#...##.##",
+        "........#.##.#.#.#..#",
+        "#######.#......#####.",
+        "#.....#...###..##...#",
+        "#.###.#..###.#.....#.",
+        "#.###.#.#.#...#.#.###",
+        "#.###.#.....##.##.#.#",
+        "#.....#.#....#..#....",
+        "#######.###.#.####.#.",
+    ];
+
+    // version 2, mask 6
+    const V2_M: [&str; 25] = [
+        "#######.#.#.#.#...#######",
+        "#.....#.##.#.#..#.#.....#",
+        "#.###.#.#.#..##...#.###.#",
+        "#.###.#....######.#.###.#",
+        "#.###.#.#######.#.#.###.#",
+        "#.....#..##..##...#.....#",
+        "#######.#.#.#.#.#.#######",
+        "..........####.#.........",
+        "#..######...#.####..#.###",
+        "##.#.#.#...#..##...#####.",
+        "..#...##....##.#.#.#.#..#",
+        ".#..#...#####..#..##.####",
+        "...##.#....#....#.##....#",
+        "#.#.....##..#.##.#..#..#.",
+        "###..###.##..######.#####",
+        "#..##....##.#.###.##.##.#",
+        "#..#..#.#..###.######.##.",
+        "........##.#....#...#.##.",
+        "#######.#.#.##..#.#.#...#",
+        "#.....#.####.#.##...#...#",
+        "#.###.#.##.##.#######....",
+        "#.###.#.##..#######....##",
+        "#.###.#..#.####..#..#####",
+        "#.....#..#..#..#.#.##.###",
+        "#######.#.#.....###..#..#",
+    ];
+
+    // version 7, mask 6
+    const V7_Q: [&str; 45] = [
+        "#######..##.##...##......##.#.#.##..#.#######",
+        "#.....#.##.####.##.#####.#########.#..#.....#",
+        "#.###.#..###..##.#.#.#..##...#.#.#.#..#.###.#",
+        "#.###.#.##...#.#......#.#####...##.##.#.###.#",
+        "#.###.#.##.#.#.#...######.#####.#####.#.###.#",
+        "#.....#..#.#..#.#.###...##..#..#......#.....#",
+        "#######.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#######",
+        "........##.##.##.####...###..###.###.........",
+        ".#.####.##.#.####..######.#.#.##..#..##.##.#.",
+        "#..#.#..#..#.##.#...#######.############..##.",
+        "#.#.#.#####..##..#...#..##.#.#.##.##..#...#.#",
+        "..#....#.....####.###.###...##.##.###..#..#.#",
+        "##.#####...#.#.#.#.###.#.#..##.##..##..###...",
+        "..####..##....#...##.#.#..#.######.##.#...###",
+        ".....##.#..###.#...##.##.###....####.#..##...",
+        "####...###.##...#.##.#.#.#..##.#...#.###.##..",
+        "#...###..#...#.######.#..###.#.####..##..####",
+        "#..###....#.######.####....##.#..#####.##.#..",
+        ".##..###...#.#..#.#......##..#.##.##....#.#.#",
+        "..#..#.##..........##.#.#....#.#.###.#...##..",
+        ".##.########.##..#.########..###.#..#####.##.",
+        "###.#...##.#.#.###..#...##....#.#.#.#...#.#.#",
+        ".#.##.#.####.###...##.#.#.#.#####.#.#.#.##.##",
+        "..#.#...#.#.#.##..###...###..##....##...#.#.#",
+        "##.######.#.#..#..#######...##..#.##########.",
+        ".##.##..#.##.#.#..##.#..#.#####..#.#.#...##..",
+        "#.#.#.##.#.#.#.#..#....##.#...##....#...###..",
+        "..#....##...#...#.#...#.##.#..#.#.##..##.####",
+        "###..##.#......##..###.#.....####..#...##.#..",
+        ".#..#..###.....#...###.#.#.####.#####..###..#",
+        "##.#..#.#.#.###.##.##.#.#...#########.##.#..#",
+        "..#..#.#######.#..#.##.##.#.#..#.....#.#.###.",
+        "..#.####.##.##...#.#...#.#.##.#..##..##.#.##.",
+        ".#.#.#..#########.#.####...####.###....##.#.#",
+        "....#.#.####.########.##.....#.#.###.#####.##",
+        ".####..##...#####...##.##.#.#.#...#..#..###.#",
+        "#..##.#.##.......#.###########..#.#########.#",
+        "........#..#.##...###...####.##.##..#...#.#.#",
+        "#######...#.#.#.#.###.#.##.....##.###.#.#.#..",
+        "#.....#.##..###.###.#...###.#.#..##.#...###..",
+        "#.###.#.#.###.##.##.#####..#....##########...",
+        "#.###.#.#..#..#.###..##.....#.#######.#.#..#.",
+        "#.###.#...#.#..##..#.######.##.####.#.#.##..#",
+        "#.....#.###..######..#..#.#.......###.##.####",
+        "#######...#........#######.##..#.#...#..#.#..",
+    ];
+
+    // version 10, mask 2
+    const V10_H: [&str; 57] = [
+        "#######.###...#.#.###.###...#.#...##...#.##.#.##..#######",
+        "#.....#.#.#.##.#.####.#...#....##.#...#....##..#..#.....#",
+        "#.###.#.##...#.###...#...#.#..###.#.##..#.#.####..#.###.#",
+        "#.###.#.....##...#..#....##.#...#.#.##...#.#...#..#.###.#",
+        "#.###.#..#.#.#...#..##.##.#####...#..#..#####..#..#.###.#",
+        "#.....#.##..##..####..#..##...##.######....##.#...#.....#",
+        "#######.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#######",
+        "........#..##...##.##.##.##...#.##..###.#.#..#.#.........",
+        "..###.#.###.#.#.####..###.#####..#.#..##...##....###..###",
+        "....##.##.#.....#...###...#.#..###.####.###.##.###..#.###",
+        ".#.###########.#.#.##.#....##...##.##..##..##..##.##.##..",
+        "..#.#..##....#..#...#.#.#.#...###.#.###.##..##.##...####.",
+        ".#....#.#.##.#####.##.###.##......#..#...#.#..##..#....#.",
+        ".####..###.#.######..###..#..#.##.#.##.#.##.##.##..#.##.#",
+        "#.#.#.##.#####.##.##.###.#.###..###.###.......#####...##.",
+        "#...#....###..##..#.#....######....###.####.#.###..#.####",
+        "....#.#...###.###.#....#....##.###.###....##..##.#..#....",
+        "#.##.....#..####.#####.#...#.#.##.##.#.#####....##.####.#",
+        ".###.####.###...#..#.##.#.#.#..####.#.##...#.######.####.",
+        "#..##..##.#...#.##..##..#.#..##..###.#.##.#.#..#.#.####..",
+        "##..####.#.##.######..####.#...###.#.#...###.###..##.#.##",
+        "...#.#.#.#...#......######.##.#...#..#...###.#..##.#.#..#",
+        "....#.##...#..###..#...##.#.##..#.#.#.##....###..##....#.",
+        ".#..#..#.......###..#.####.#########.##...####..##.###...",
+        "##.#..########...#.#...#....#...##.#.#.#.###.##..##...#..",
+        "###....##.#.######...#...####....#...#....#..#..#..#####.",
+        "##..#####..##...#.###...#######.#...#.##.###.########....",
+        ".#..#...#.#.#.#..#...##...#...###.###..####.##..#...####.",
+        "###.#.#.##.###.#.#..###...#.#.##..#...#..###..###.#.#....",
+        "#####...#.#.#..#..##.##.#.#...#.#..###..#####..##...#####",
+        "##.#########.###..#.###.#.#######..#..#..#....##########.",
+        "..###...#.##.#.##.##.#....###...#.#.#######.#.##..#.#.#.#",
+        "#....##.#..#..##...##.##..#.##..##..##.....#....#####....",
+        ".#...#.####..#...#..###..####.#.##..##...####...##.#.####",
+        "#.###.####..#.##...#.###.#.#.....###.##....####.##.#####.",
+        "##.#....######..#.....#..####.#.#.#.#...#...##....#.#.##.",
+        "####..####....##.#..####.###....##.####...#..####..##...#",
+        "#..#....##....###..#....###...#######....#####.#.#...#..#",
+        ".##.#.##.##..#...#.####.######..##.#....#..######..#.#.#.",
+        "##.....####.##.#.#.#....#####.#.#.#.#..##.##..##.#...###.",
+        ".####.#####.#...##....#.#..##...#.#.##...##..##.##..#..##",
+        "##..##.#....##..####......#######.#.#...####..##...#....#",
+        "###...#..#.....##.##....#.####.##..#.#.#.....#..##..####.",
+        "..#..#..#.###...#.##.#.#.###.##....#######..####.##.###..",
+        "...####.##.....##....#..##..##.##.#.#.#....#.##..####..##",
+        ".....#..#.###.##...#.#.##.##..#.#..#.##...##.#...##.....#",
+        "#.#..###.###.....#....####.####.#####....#.#######.#####.",
+        "#####..#..##..#.####...###...####.#####.###.##.#..#.###.#",
+        "......#......#...#....#.#######.##....#...##..#.#####..##",
+        "........#...#.#.#....##..##...###.#..##..##.#..##...#.#.#",
+        "#######....##..####.##...##.#.###..#.........####.#.##.#.",
+        "#.....#.....##.#.#.####...#...###.#....####.#.#.#...###..",
+        "#.###.#.#..#.##.##.##....######.##..###...##..#.######...",
+        "#.###.#.####.#..########.....##..####..#.####..#....#.#..",
+        "#.###.#.##.#...#..###.##..#.##.##..##..##.....#.#....##..",
+        "#.....#..##....######..##..#.#.#..#..###.#.##..##..#..#..",
+        "#######..#..##....#.#.######.#.#####.##.#..#.##..#######.",
+    ];
+
+    fn assert_matches(data: &[u8], ecc: Ecc, expected: &[&str]) {
+        let code = QrCode::encode(data, ecc).unwrap();
+        assert_eq!(code.size(), expected.len());
+        for (y, row) in expected.iter().enumerate() {
+            for (x, module) in row.bytes().enumerate() {
+                assert_eq!(code.get(x, y), module == b'#', "module {x},{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn version_1_low() {
+        assert_matches(b"gopherbadge", Ecc::Low, &V1_L);
+    }
+
+    #[test]
+    fn version_2_medium() {
+        assert_matches(b"https://github.com/flejz", Ecc::Medium, &V2_M);
+    }
+
+    #[test]
+    fn version_7_quartile_with_version_information() {
+        assert_matches(
+            b"BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Ferris the Crab\r\nEMAIL:ferris@example.com\r\nEND:VCARD\r\n",
+            Ecc::Quartile,
+            &V7_Q,
+        );
+    }
+
+    #[test]
+    fn version_10_high_with_a_16_bit_length() {
+        assert_matches(
+            b"https://example.com/conference/2026/schedule?day=2&track=main&session=writing-embedded-rust-for-badges",
+            Ecc::High,
+            &V10_H,
+        );
+    }
+
+    #[test]
+    fn too_long_past_the_largest_version() {
+        // byte mode capacities of version 15 in the spec
+        let data = [b'x'; 521];
+        for (ecc, capacity) in [
+            (Ecc::Low, 520),
+            (Ecc::Medium, 412),
+            (Ecc::Quartile, 292),
+            (Ecc::High, 220),
+        ] {
+            let code = QrCode::encode(&data[..capacity], ecc).unwrap();
+            assert_eq!(code.size(), MAX_SIZE);
+            assert_eq!(
+                QrCode::encode(&data[..capacity + 1], ecc).err(),
+                Some(TooLong)
+            );
+        }
+    }
+
+    #[test]
+    fn a_full_vcard_is_too_long() {
+        // the badge page says so instead of drawing a code. Commas are
+        // escaped, so every field full of them is the longest card.
+        let mut contact = Contact::default();
+        for field in Field::ALL {
+            let mut text: String<64> = String::new();
+            while text.len() < contact.capacity(field) {
+                text.push(',').unwrap();
+            }
+            assert!(contact.set(field, &text));
+        }
+        let card = contact.vcard().unwrap();
+        for ecc in Ecc::ALL {
+            assert_eq!(QrCode::encode(card.as_bytes(), ecc).err(), Some(TooLong));
+        }
+
+        // and a short one is not
+        let mut contact = Contact::default();
+        assert!(contact.set(Field::Name, "Ferris"));
+        assert!(contact.set(Field::Github, "ferris"));
+        let card = contact.vcard().unwrap();
+        assert!(QrCode::encode(card.as_bytes(), Ecc::High).is_ok());
+    }
+}
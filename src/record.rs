@@ -0,0 +1,495 @@
+// The settings and the record they are kept in flash as. Fields are stored
+// in declaration order, new ones go at the end so records written by older
+// firmware still load, with the missing fields at their defaults. Values a
+// stale or corrupt record holds outside their ranges are held to them.
+
+use heapless::String;
+
+use crate::{
+    color::LED_BRIGHTNESS_CAP_DEFAULT,
+    contact::Contact,
+    led_effects::{Animation, Effect, SPEED_MAX, SPEED_MIN},
+    qr::Ecc,
+};
+
+// "gbrs"
+const MAGIC: u32 = 0x7372_6267;
+const HEADER_SIZE: usize = 8;
+pub const RECORD_SIZE: usize = 512;
+
+/// Lowest level the brightness setting goes to, the screen is unreadable
+/// below it.
+pub const BACKLIGHT_MIN: u8 = 16;
+/// Lowest led brightness cap, any lower and the leds look off.
+pub const LED_CAP_MIN: u8 = 8;
+pub const TILT_MIN: u8 = 1;
+pub const TILT_MAX: u8 = 20;
+
+/// Options that survive a reset.
+#[derive(Clone, PartialEq)]
+pub struct Settings {
+    pub led_brightness_cap: u8,
+    pub backlight: u8,
+    /// seconds without input before the backlight dims, zero never does
+    pub dim_after_s: u16,
+    /// seconds without input before the display sleeps, zero never does
+    pub sleep_after_s: u16,
+    /// schedule sessions picked out, by position in the file
+    pub starred: u64,
+    /// checksum of the schedule the stars belong to
+    pub schedule_id: u16,
+    pub contact: Contact,
+    /// what the badge page QR code holds
+    pub qr: QrContent,
+    pub qr_ecc: Ecc,
+    pub theme: ThemeName,
+    /// a tap on the badge wakes it up, not just the buttons
+    pub tap_wake: bool,
+    /// how far tilting moves things in the accelerometer apps
+    pub tilt: u8,
+    /// what the leds show when no app says otherwise
+    pub ambient: Animation,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum QrContent {
+    Url,
+    /// a contact card built from the badge fields
+    Vcard,
+}
+
+impl QrContent {
+    pub const ALL: [QrContent; 2] = [QrContent::Url, QrContent::Vcard];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            QrContent::Url => "url",
+            QrContent::Vcard => "vcard",
+        }
+    }
+}
+
+/// The built in themes, the one picked is stored in the settings.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ThemeName {
+    Rust,
+    Go,
+    HighContrast,
+    Dark,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 4] = [
+        ThemeName::Rust,
+        ThemeName::Go,
+        ThemeName::HighContrast,
+        ThemeName::Dark,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThemeName::Rust => "rust",
+            ThemeName::Go => "go",
+            ThemeName::HighContrast => "contrast",
+            ThemeName::Dark => "dark",
+        }
+    }
+}
+
+/// Why a record was not loaded.
+#[derive(Debug, PartialEq)]
+pub enum Invalid {
+    /// blank flash, nothing was ever saved
+    Blank,
+    /// the body length in the header is more than the record holds
+    Length(usize),
+    Checksum,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            led_brightness_cap: LED_BRIGHTNESS_CAP_DEFAULT,
+            backlight: u8::MAX,
+            dim_after_s: 30,
+            sleep_after_s: 120,
+            starred: 0,
+            schedule_id: 0,
+            contact: Contact::default(),
+            qr: QrContent::Url,
+            qr_ecc: Ecc::Medium,
+            theme: ThemeName::Rust,
+            tap_wake: true,
+            tilt: 10,
+            ambient: Animation::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads a record written by `record`.
+    pub fn from_record(record: &[u8]) -> Result<Self, Invalid> {
+        let Some(header) = record.get(..HEADER_SIZE) else {
+            return Err(Invalid::Blank);
+        };
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let len = u16::from_le_bytes([header[4], header[5]]) as usize;
+        let sum = u16::from_le_bytes([header[6], header[7]]);
+
+        if magic != MAGIC {
+            return Err(Invalid::Blank);
+        }
+        let Some(body) = record
+            .get(HEADER_SIZE..HEADER_SIZE + len)
+            .filter(|_| len <= RECORD_SIZE - HEADER_SIZE)
+        else {
+            return Err(Invalid::Length(len));
+        };
+        if checksum(body) != sum {
+            return Err(Invalid::Checksum);
+        }
+
+        Ok(Self::decode(&mut Reader { body, pos: 0 }))
+    }
+
+    /// The record to keep in flash, and how many bytes of it are used.
+    pub fn record(&self) -> ([u8; RECORD_SIZE], usize) {
+        let mut w = Writer {
+            buf: [0xff; RECORD_SIZE],
+            len: HEADER_SIZE,
+        };
+        self.encode(&mut w);
+
+        let body_len = w.len - HEADER_SIZE;
+        let sum = checksum(&w.buf[HEADER_SIZE..w.len]);
+        w.buf[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        w.buf[4..6].copy_from_slice(&(body_len as u16).to_le_bytes());
+        w.buf[6..8].copy_from_slice(&sum.to_le_bytes());
+        (w.buf, w.len)
+    }
+
+    fn encode(&self, w: &mut Writer) {
+        w.u8(self.led_brightness_cap);
+        w.u8(self.backlight);
+        w.u16(self.dim_after_s);
+        w.u16(self.sleep_after_s);
+        w.u64(self.starred);
+        w.u16(self.schedule_id);
+        w.str(&self.contact.name);
+        w.str(&self.contact.url);
+        w.u8(self.qr as u8);
+        w.u8(self.qr_ecc as u8);
+        w.str(&self.contact.email);
+        w.str(&self.contact.github);
+        w.str(&self.contact.mastodon);
+        w.str(&self.contact.company);
+        w.u8(self.theme as u8);
+        w.u8(self.tap_wake as u8);
+        w.u8(self.tilt);
+        w.u8(self.ambient.effect as u8);
+        w.u8(self.ambient.speed);
+        w.u8(self.ambient.brightness);
+        w.u8(self.ambient.hue);
+    }
+
+    fn decode(r: &mut Reader) -> Self {
+        let defaults = Self::default();
+        let led_brightness_cap = r.u8(defaults.led_brightness_cap).max(LED_CAP_MIN);
+        let backlight = r.u8(defaults.backlight).max(BACKLIGHT_MIN);
+        let dim_after_s = r.u16(defaults.dim_after_s);
+        let sleep_after_s = r.u16(defaults.sleep_after_s);
+        let starred = r.u64(defaults.starred);
+        let schedule_id = r.u16(defaults.schedule_id);
+        // name and url were stored before the rest of the contact
+        let (name, url) = (r.str(), r.str());
+        let qr = QrContent::ALL
+            .get(r.u8(u8::MAX) as usize)
+            .copied()
+            .unwrap_or(defaults.qr);
+        let qr_ecc = Ecc::ALL
+            .get(r.u8(u8::MAX) as usize)
+            .copied()
+            .unwrap_or(defaults.qr_ecc);
+        let contact = Contact {
+            name,
+            url,
+            email: r.str(),
+            github: r.str(),
+            mastodon: r.str(),
+            company: r.str(),
+        };
+        let theme = ThemeName::ALL
+            .get(r.u8(u8::MAX) as usize)
+            .copied()
+            .unwrap_or(defaults.theme);
+        let tap_wake = r.u8(defaults.tap_wake as u8) != 0;
+        let tilt = r.u8(defaults.tilt).clamp(TILT_MIN, TILT_MAX);
+        let ambient = Animation {
+            effect: Effect::ALL
+                .get(r.u8(u8::MAX) as usize)
+                .copied()
+                .unwrap_or(defaults.ambient.effect),
+            speed: r.u8(defaults.ambient.speed).clamp(SPEED_MIN, SPEED_MAX),
+            brightness: r.u8(defaults.ambient.brightness),
+            hue: r.u8(defaults.ambient.hue),
+        };
+        Self {
+            led_brightness_cap,
+            backlight,
+            dim_after_s,
+            sleep_after_s,
+            starred,
+            schedule_id,
+            qr,
+            qr_ecc,
+            contact,
+            theme,
+            tap_wake,
+            tilt,
+            ambient,
+        }
+    }
+}
+
+struct Writer {
+    buf: [u8; RECORD_SIZE],
+    len: usize,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.buf[self.len] = value;
+        self.len += 1;
+    }
+
+    fn u16(&mut self, value: u16) {
+        for byte in value.to_le_bytes() {
+            self.u8(byte);
+        }
+    }
+
+    fn u64(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.u8(byte);
+        }
+    }
+
+    // length first, strings are short
+    fn str(&mut self, value: &str) {
+        self.u8(value.len() as u8);
+        for byte in value.bytes() {
+            self.u8(byte);
+        }
+    }
+}
+
+struct Reader<'a> {
+    body: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn u8(&mut self, default: u8) -> u8 {
+        match self.body.get(self.pos) {
+            Some(value) => {
+                self.pos += 1;
+                *value
+            }
+            None => default,
+        }
+    }
+
+    fn u16(&mut self, default: u16) -> u16 {
+        // a field cut in half by an older record is not a value
+        match self.body.get(self.pos..self.pos + 2) {
+            Some(bytes) => {
+                self.pos += 2;
+                u16::from_le_bytes([bytes[0], bytes[1]])
+            }
+            None => default,
+        }
+    }
+
+    fn u64(&mut self, default: u64) -> u64 {
+        match self.body.get(self.pos..self.pos + 8) {
+            Some(bytes) => {
+                self.pos += 8;
+                let mut le = [0; 8];
+                le.copy_from_slice(bytes);
+                u64::from_le_bytes(le)
+            }
+            None => default,
+        }
+    }
+
+    // empty when missing, or too long for the field
+    fn str<const N: usize>(&mut self) -> String<N> {
+        let len = self.u8(0) as usize;
+        let Some(bytes) = self.body.get(self.pos..self.pos + len) else {
+            return String::new();
+        };
+        self.pos += len;
+        core::str::from_utf8(bytes)
+            .ok()
+            .and_then(|text| String::try_from(text).ok())
+            .unwrap_or_default()
+    }
+}
+
+pub fn checksum(bytes: &[u8]) -> u16 {
+    // fletcher-16
+    let (a, b) = bytes.iter().fold((0u16, 0u16), |(a, b), byte| {
+        let a = (a + *byte as u16) % 255;
+        (a, (b + a) % 255)
+    });
+    (b << 8) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed() -> Settings {
+        let mut settings = Settings {
+            led_brightness_cap: 200,
+            backlight: 64,
+            dim_after_s: 10,
+            sleep_after_s: 1800,
+            starred: 0x8000_0000_0000_0001,
+            schedule_id: 0xbeef,
+            qr: QrContent::Vcard,
+            qr_ecc: Ecc::High,
+            theme: ThemeName::Dark,
+            tap_wake: false,
+            tilt: TILT_MAX,
+            ambient: Animation {
+                effect: Effect::ALL[Effect::ALL.len() - 1],
+                speed: SPEED_MAX,
+                brightness: 77,
+                hue: 200,
+            },
+            ..Settings::default()
+        };
+        settings.contact.name.push_str("Zoë Łukasz").unwrap();
+        settings
+            .contact
+            .url
+            .push_str("https://example.com")
+            .unwrap();
+        settings.contact.email.push_str("zoe@example.com").unwrap();
+        settings.contact.github.push_str("zoe").unwrap();
+        settings
+            .contact
+            .mastodon
+            .push_str("@zoe@example.social")
+            .unwrap();
+        settings.contact.company.push_str("Ferris & Co").unwrap();
+        settings
+    }
+
+    // a record around `body`, as firmware that wrote only that much would
+    fn with_body(body: &[u8]) -> [u8; RECORD_SIZE] {
+        let mut record = [0xff; RECORD_SIZE];
+        record[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        record[4..6].copy_from_slice(&(body.len() as u16).to_le_bytes());
+        record[6..8].copy_from_slice(&checksum(body).to_le_bytes());
+        record[HEADER_SIZE..HEADER_SIZE + body.len()].copy_from_slice(body);
+        record
+    }
+
+    #[test]
+    fn round_trips_every_field() {
+        for settings in [Settings::default(), changed()] {
+            let (record, len) = settings.record();
+            assert!(Settings::from_record(&record[..len]) == Ok(settings));
+        }
+    }
+
+    #[test]
+    fn an_older_record_loads_with_defaults() {
+        let settings = changed();
+        let (record, len) = settings.record();
+        let body = &record[HEADER_SIZE..len];
+
+        // up to the qr options, before the rest of the contact was added
+        let name_and_url = 1 + settings.contact.name.len() + 1 + settings.contact.url.len();
+        let before_contact = 1 + 1 + 2 + 2 + 8 + 2 + name_and_url + 1 + 1;
+        let old = Settings::from_record(&with_body(&body[..before_contact])).unwrap();
+        assert!(old.contact.name == settings.contact.name);
+        assert!(old.contact.url == settings.contact.url);
+        assert!(old.qr_ecc == Ecc::High);
+        assert!(old.contact.email.is_empty());
+        assert!(old.theme == ThemeName::Rust);
+        assert!(old.tilt == Settings::default().tilt);
+        assert!(old.ambient == Animation::default());
+
+        // a field cut in half is not read either
+        let old = Settings::from_record(&with_body(&body[..3])).unwrap();
+        assert_eq!(old.backlight, 64);
+        assert_eq!(old.dim_after_s, Settings::default().dim_after_s);
+    }
+
+    #[test]
+    fn holds_values_to_their_ranges() {
+        let mut w = Writer {
+            buf: [0; RECORD_SIZE],
+            len: 0,
+        };
+        // led cap, backlight, dim, sleep, starred, schedule id
+        for value in [0, 0] {
+            w.u8(value);
+        }
+        w.u16(7);
+        w.u16(7);
+        w.u64(0);
+        w.u16(0);
+        // name, url, qr, qr ecc, the rest of the contact
+        w.str("");
+        w.str("");
+        w.u8(9);
+        w.u8(9);
+        for _ in 0..4 {
+            w.str("");
+        }
+        // theme, tap wake, tilt, effect, speed, brightness, hue
+        for value in [9, 1, 255, 99, 0, 0, 0] {
+            w.u8(value);
+        }
+
+        let settings = Settings::from_record(&with_body(&w.buf[..w.len])).unwrap();
+        let defaults = Settings::default();
+        assert_eq!(settings.led_brightness_cap, LED_CAP_MIN);
+        assert_eq!(settings.backlight, BACKLIGHT_MIN);
+        assert_eq!(settings.tilt, TILT_MAX);
+        assert_eq!(settings.ambient.speed, SPEED_MIN);
+        assert!(settings.qr == defaults.qr);
+        assert!(settings.qr_ecc == defaults.qr_ecc);
+        assert!(settings.theme == defaults.theme);
+        assert!(settings.ambient.effect == defaults.ambient.effect);
+
+        let mut fast = changed();
+        fast.ambient.speed = u8::MAX;
+        let (record, len) = fast.record();
+        let settings = Settings::from_record(&record[..len]).unwrap();
+        assert_eq!(settings.ambient.speed, SPEED_MAX);
+    }
+
+    #[test]
+    fn refuses_blank_and_damaged_records() {
+        let blank = [0xff; RECORD_SIZE];
+        assert_eq!(Settings::from_record(&blank).err(), Some(Invalid::Blank));
+
+        let (mut record, len) = changed().record();
+        record[len - 1] ^= 1;
+        assert_eq!(
+            Settings::from_record(&record).err(),
+            Some(Invalid::Checksum)
+        );
+
+        record[4..6].copy_from_slice(&(RECORD_SIZE as u16).to_le_bytes());
+        assert_eq!(
+            Settings::from_record(&record).err(),
+            Some(Invalid::Length(RECORD_SIZE))
+        );
+    }
+}
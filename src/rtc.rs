@@ -0,0 +1,161 @@
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use rp2040_hal::{Timer, fugit::Instant};
+
+use crate::log::log_info;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Local wall time, there is no time zone on the badge.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+// days since 1970-01-01, Howard Hinnant's days_from_civil
+fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year } as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// and back again
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = (year_of_era + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+    (year, month, day)
+}
+
+impl DateTime {
+    pub fn from_seconds(seconds: i64) -> Self {
+        let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+        let time = seconds.rem_euclid(SECONDS_PER_DAY);
+        Self {
+            year,
+            month,
+            day,
+            hour: (time / 3_600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+
+    /// Seconds since 1970-01-01 00:00, in local time.
+    pub fn seconds(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * SECONDS_PER_DAY
+            + self.hour as i64 * 3_600
+            + self.minute as i64 * 60
+            + self.second as i64
+    }
+
+    /// 0 is Monday.
+    pub fn weekday(&self) -> u8 {
+        // 1970-01-01 was a Thursday
+        (days_from_civil(self.year, self.month, self.day) + 3).rem_euclid(7) as u8
+    }
+
+    /// Reads `YYYY-MM-DD`.
+    pub fn parse_date(text: &str) -> Option<(i32, u8, u8)> {
+        let mut parts = text.splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+        ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((year, month, day))
+    }
+
+    /// Reads `HH:MM` or `HH:MM:SS`.
+    pub fn parse_time(text: &str) -> Option<(u8, u8, u8)> {
+        let mut parts = text.splitn(3, ':');
+        let hour = parts.next()?.parse().ok()?;
+        let minute = parts.next()?.parse().ok()?;
+        let second = parts.next().map_or(Some(0), |s| s.parse().ok())?;
+        (hour < 24 && minute < 60 && second < 60).then_some((hour, minute, second))
+    }
+
+    /// Reads `YYYY-MM-DD HH:MM[:SS]`, as two words.
+    pub fn parse(date: &str, time: &str) -> Option<Self> {
+        let (year, month, day) = Self::parse_date(date)?;
+        let (hour, minute, second) = Self::parse_time(time)?;
+        Some(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        })
+    }
+}
+
+struct Rtc {
+    timer: Timer,
+    // local seconds at the given timer instant, `None` until set
+    base: Option<(i64, Instant<u64, 1, 1_000_000>)>,
+}
+
+static RTC: Mutex<RefCell<Option<Rtc>>> = Mutex::new(RefCell::new(None));
+
+pub fn init(timer: Timer) {
+    critical_section::with(|cs| {
+        RTC.borrow(cs).replace(Some(Rtc { timer, base: None }));
+    });
+}
+
+/// Sets the wall time, in local seconds since 1970.
+pub fn set(seconds: i64) {
+    critical_section::with(|cs| {
+        if let Some(rtc) = RTC.borrow(cs).borrow_mut().as_mut() {
+            rtc.base = Some((seconds, rtc.timer.get_counter()));
+        }
+    });
+    let now = DateTime::from_seconds(seconds);
+    log_info!(
+        "time set to {}-{:02}-{:02} {:02}:{:02}",
+        now.year,
+        now.month,
+        now.day,
+        now.hour,
+        now.minute
+    );
+}
+
+/// Forgets the wall time, after the timer stopped for a while.
+pub fn invalidate() {
+    critical_section::with(|cs| {
+        if let Some(rtc) = RTC.borrow(cs).borrow_mut().as_mut() {
+            rtc.base = None;
+        }
+    });
+}
+
+/// Local seconds since 1970, `None` until the time was set.
+pub fn seconds() -> Option<i64> {
+    critical_section::with(|cs| {
+        let rtc = RTC.borrow(cs).borrow();
+        let rtc = rtc.as_ref()?;
+        let (seconds, at) = rtc.base?;
+        Some(seconds + (rtc.timer.get_counter() - at).to_secs() as i64)
+    })
+}
+
+pub fn now() -> Option<DateTime> {
+    seconds().map(DateTime::from_seconds)
+}
@@ -0,0 +1,439 @@
+// The conference schedule format. One entry per line, the usb shell takes
+// lines of up to 256 bytes and drops longer ones:
+//
+//     # comments and blank lines are skipped
+//     track Main
+//     track Workshop
+//     day 2026-11-12
+//     09:00 09:45 1 Opening keynote|Ada Lovelace
+//     10:00 11:30 2 Soldering 101
+//
+// Tracks are numbered from 1 in the order they are declared, sessions belong
+// to the `day` above them, the speaker after `|` is optional. Only depends
+// on `core`, so it builds for the host as well.
+
+/// What was wrong with a line, and which one (from 1).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// not valid utf-8
+    Encoding,
+    BadDate,
+    BadTime,
+    /// ends before it starts
+    BadRange,
+    UnknownTrack,
+    /// a session before the first `day`
+    NoDay,
+    TooManyTracks,
+    TooManyDays,
+    Empty,
+    Unknown,
+}
+
+impl ErrorKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorKind::Encoding => "not utf-8",
+            ErrorKind::BadDate => "date is not YYYY-MM-DD",
+            ErrorKind::BadTime => "time is not HH:MM",
+            ErrorKind::BadRange => "session ends before it starts",
+            ErrorKind::UnknownTrack => "no such track",
+            ErrorKind::NoDay => "session before the first day",
+            ErrorKind::TooManyTracks => "too many tracks",
+            ErrorKind::TooManyDays => "too many days",
+            ErrorKind::Empty => "no sessions",
+            ErrorKind::Unknown => "not a track, day or session",
+        }
+    }
+}
+
+pub const MAX_TRACKS: usize = 8;
+pub const MAX_DAYS: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// Minutes since midnight.
+pub type Minutes = u16;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Session<'a> {
+    /// position in the file, stable for as long as the schedule is
+    pub index: usize,
+    /// index into `Schedule::days`
+    pub day: usize,
+    /// index into `Schedule::tracks`
+    pub track: usize,
+    pub start: Minutes,
+    pub end: Minutes,
+    pub title: &'a str,
+    pub speaker: Option<&'a str>,
+}
+
+impl Session<'_> {
+    pub fn is_running(&self, now: Minutes) -> bool {
+        (self.start..self.end).contains(&now)
+    }
+}
+
+enum Entry<'a> {
+    Track(&'a str),
+    Day(Date),
+    Session(Minutes, Minutes, usize, &'a str, Option<&'a str>),
+}
+
+fn parse_date(text: &str) -> Option<Date> {
+    let mut parts = text.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some(Date { year, month, day })
+}
+
+fn parse_time(text: &str) -> Option<Minutes> {
+    let (hour, minute) = text.split_once(':')?;
+    let hour: Minutes = hour.parse().ok()?;
+    let minute: Minutes = minute.parse().ok()?;
+    // 24:00 closes the last session of a day
+    (hour * 60 + minute <= 24 * 60 && minute < 60).then_some(hour * 60 + minute)
+}
+
+fn parse_line(line: &str) -> Result<Option<Entry<'_>>, ErrorKind> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let rest = rest.trim();
+    match word {
+        "track" if !rest.is_empty() => Ok(Some(Entry::Track(rest))),
+        "day" => parse_date(rest)
+            .map(|date| Some(Entry::Day(date)))
+            .ok_or(ErrorKind::BadDate),
+        time if time.contains(':') => {
+            let mut words = rest.splitn(3, ' ');
+            let start = parse_time(time).ok_or(ErrorKind::BadTime)?;
+            let end = words
+                .next()
+                .and_then(parse_time)
+                .ok_or(ErrorKind::BadTime)?;
+            if end <= start {
+                return Err(ErrorKind::BadRange);
+            }
+            let track: usize = words
+                .next()
+                .and_then(|track| track.parse().ok())
+                .filter(|track| *track >= 1)
+                .ok_or(ErrorKind::UnknownTrack)?;
+            let text = words.next().unwrap_or("").trim();
+            let (title, speaker) = match text.split_once('|') {
+                Some((title, speaker)) => (title.trim(), Some(speaker.trim())),
+                None => (text, None),
+            };
+            Ok(Some(Entry::Session(start, end, track - 1, title, speaker)))
+        }
+        _ => Err(ErrorKind::Unknown),
+    }
+}
+
+/// A schedule that parsed, the accessors do not fail.
+#[derive(Clone, Copy)]
+pub struct Schedule<'a> {
+    text: &'a str,
+}
+
+impl<'a> Schedule<'a> {
+    /// Checks every line, anything after a NUL or an erased flash byte is
+    /// ignored.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ParseError> {
+        let end = bytes
+            .iter()
+            .position(|byte| *byte == 0 || *byte == 0xff)
+            .unwrap_or(bytes.len());
+        let text = core::str::from_utf8(&bytes[..end]).map_err(|error| {
+            let line = bytes[..error.valid_up_to()]
+                .iter()
+                .filter(|byte| **byte == b'\n')
+                .count();
+            ParseError {
+                line: line + 1,
+                kind: ErrorKind::Encoding,
+            }
+        })?;
+
+        let (mut tracks, mut days, mut sessions) = (0, 0, 0);
+        for (i, line) in text.lines().enumerate() {
+            let error = |kind| ParseError { line: i + 1, kind };
+            match parse_line(line).map_err(error)? {
+                Some(Entry::Track(_)) if tracks == MAX_TRACKS => {
+                    return Err(error(ErrorKind::TooManyTracks));
+                }
+                Some(Entry::Track(_)) => tracks += 1,
+                Some(Entry::Day(_)) if days == MAX_DAYS => {
+                    return Err(error(ErrorKind::TooManyDays));
+                }
+                Some(Entry::Day(_)) => days += 1,
+                Some(Entry::Session(..)) if days == 0 => return Err(error(ErrorKind::NoDay)),
+                Some(Entry::Session(_, _, track, _, _)) if track >= tracks => {
+                    return Err(error(ErrorKind::UnknownTrack));
+                }
+                Some(Entry::Session(..)) => sessions += 1,
+                None => {}
+            }
+        }
+
+        if sessions == 0 {
+            return Err(ParseError {
+                line: text.lines().count(),
+                kind: ErrorKind::Empty,
+            });
+        }
+        Ok(Self { text })
+    }
+
+    fn entries(&self) -> impl Iterator<Item = Entry<'a>> {
+        self.text
+            .lines()
+            .filter_map(|line| parse_line(line).ok().flatten())
+    }
+
+    pub fn tracks(&self) -> impl Iterator<Item = &'a str> {
+        self.entries().filter_map(|entry| match entry {
+            Entry::Track(name) => Some(name),
+            _ => None,
+        })
+    }
+
+    pub fn days(&self) -> impl Iterator<Item = Date> {
+        self.entries().filter_map(|entry| match entry {
+            Entry::Day(date) => Some(date),
+            _ => None,
+        })
+    }
+
+    /// Every session in file order.
+    pub fn sessions(&self) -> impl Iterator<Item = Session<'a>> {
+        let mut day: Option<usize> = None;
+        let mut index = 0;
+        self.entries().filter_map(move |entry| match entry {
+            Entry::Day(_) => {
+                day = Some(day.map_or(0, |day| day + 1));
+                None
+            }
+            Entry::Session(start, end, track, title, speaker) => {
+                index += 1;
+                Some(Session {
+                    index: index - 1,
+                    // `parse` made sure there is a day above
+                    day: day.unwrap_or(0),
+                    track,
+                    start,
+                    end,
+                    title,
+                    speaker,
+                })
+            }
+            Entry::Track(_) => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_DAYS: &str = "\
+# comment
+track Main
+track Workshop
+
+day 2026-11-12
+09:00 09:45 1 Opening keynote|Ada Lovelace
+10:00 11:30 2 Soldering 101
+day 2026-11-13
+23:00 24:00 1 Late one | Grace Hopper
+";
+
+    fn error(text: &str) -> ParseError {
+        Schedule::parse(text.as_bytes()).err().unwrap()
+    }
+
+    fn kind(text: &str) -> ErrorKind {
+        error(text).kind
+    }
+
+    #[test]
+    fn parses_tracks_days_and_sessions() {
+        let schedule = Schedule::parse(TWO_DAYS.as_bytes()).unwrap();
+        assert!(schedule.tracks().eq(["Main", "Workshop"]));
+        assert!(schedule.days().eq([
+            Date {
+                year: 2026,
+                month: 11,
+                day: 12
+            },
+            Date {
+                year: 2026,
+                month: 11,
+                day: 13
+            },
+        ]));
+
+        let sessions: [Session; 3] = core::array::from_fn(|i| schedule.sessions().nth(i).unwrap());
+        assert_eq!(schedule.sessions().count(), 3);
+        assert_eq!(sessions.map(|s| s.index), [0, 1, 2]);
+        assert_eq!(sessions.map(|s| s.day), [0, 0, 1]);
+        assert_eq!(sessions.map(|s| s.track), [0, 1, 0]);
+        assert_eq!(sessions[0].start, 9 * 60);
+        assert_eq!(sessions[0].end, 9 * 60 + 45);
+    }
+
+    #[test]
+    fn speaker_is_optional() {
+        let schedule = Schedule::parse(TWO_DAYS.as_bytes()).unwrap();
+        let mut sessions = schedule.sessions();
+        let keynote = sessions.next().unwrap();
+        assert_eq!(keynote.title, "Opening keynote");
+        assert_eq!(keynote.speaker, Some("Ada Lovelace"));
+        let soldering = sessions.next().unwrap();
+        assert_eq!(soldering.title, "Soldering 101");
+        assert_eq!(soldering.speaker, None);
+        // spaces around the bar are trimmed
+        let late = sessions.next().unwrap();
+        assert_eq!(
+            (late.title, late.speaker),
+            ("Late one", Some("Grace Hopper"))
+        );
+    }
+
+    #[test]
+    fn midnight_ends_a_day() {
+        let schedule = Schedule::parse(TWO_DAYS.as_bytes()).unwrap();
+        let late = schedule.sessions().last().unwrap();
+        assert_eq!(late.end, 24 * 60);
+        assert!(late.is_running(23 * 60 + 59));
+        assert_eq!(
+            kind("track A\nday 2026-01-01\n23:00 24:01 1 x"),
+            ErrorKind::BadTime
+        );
+        assert_eq!(
+            kind("track A\nday 2026-01-01\n24:00 24:30 1 x"),
+            ErrorKind::BadTime
+        );
+    }
+
+    #[test]
+    fn stops_at_nul_or_erased_flash() {
+        for end in [0u8, 0xff] {
+            let mut bytes = [end; 256];
+            bytes[..TWO_DAYS.len()].copy_from_slice(TWO_DAYS.as_bytes());
+            // garbage after the end is not looked at
+            bytes[TWO_DAYS.len() + 1..TWO_DAYS.len() + 5].copy_from_slice(b"junk");
+            let schedule = Schedule::parse(&bytes).unwrap();
+            assert_eq!(schedule.sessions().count(), 3);
+        }
+    }
+
+    #[test]
+    fn reports_each_error_with_its_line() {
+        let day = "track A\nday 2026-01-01\n";
+        for (text, expected, line) in [
+            (
+                &b"track A\nday 2026-01-01\n09:00 10:00 1 caf\xc3"[..],
+                ErrorKind::Encoding,
+                3,
+            ),
+            (b"track A\nday 2026-13-01", ErrorKind::BadDate, 2),
+            (b"track A\nday tomorrow", ErrorKind::BadDate, 2),
+            (
+                b"track A\nday 2026-01-01\n9:60 10:00 1 x",
+                ErrorKind::BadTime,
+                3,
+            ),
+            (
+                b"track A\nday 2026-01-01\n09:00 noon 1 x",
+                ErrorKind::BadTime,
+                3,
+            ),
+            (
+                b"track A\nday 2026-01-01\n10:00 10:00 1 x",
+                ErrorKind::BadRange,
+                3,
+            ),
+            (
+                b"track A\nday 2026-01-01\n09:00 10:00 2 x",
+                ErrorKind::UnknownTrack,
+                3,
+            ),
+            (
+                b"track A\nday 2026-01-01\n09:00 10:00 0 x",
+                ErrorKind::UnknownTrack,
+                3,
+            ),
+            (b"track A\n09:00 10:00 1 x", ErrorKind::NoDay, 2),
+            (b"track A\nday 2026-01-01\n", ErrorKind::Empty, 2),
+            (b"", ErrorKind::Empty, 0),
+            (b"track A\nroom 5", ErrorKind::Unknown, 2),
+            (b"track", ErrorKind::Unknown, 1),
+        ] {
+            let error = Schedule::parse(text).err().unwrap();
+            assert_eq!(
+                error,
+                ParseError {
+                    line,
+                    kind: expected
+                },
+                "{}",
+                expected.description()
+            );
+        }
+        assert_eq!(kind(day), ErrorKind::Empty);
+    }
+
+    #[test]
+    fn limits_tracks_and_days() {
+        let mut text: heapless::Vec<u8, 512> = heapless::Vec::new();
+        let mut push = |line: &str| text.extend_from_slice(line.as_bytes()).unwrap();
+        for _ in 0..MAX_TRACKS {
+            push("track T\n");
+        }
+        for day in b'1'..=b'8' {
+            push("day 2026-01-0");
+            push(core::str::from_utf8(&[day]).unwrap());
+            push("\n09:00 10:00 8 x\n");
+        }
+        assert_eq!(Schedule::parse(&text).unwrap().days().count(), MAX_DAYS);
+
+        text.extend_from_slice(b"day 2026-01-09\n").unwrap();
+        assert_eq!(
+            Schedule::parse(&text).err().unwrap(),
+            ParseError {
+                line: MAX_TRACKS + 2 * MAX_DAYS + 1,
+                kind: ErrorKind::TooManyDays
+            }
+        );
+
+        let mut tracks: heapless::Vec<u8, 128> = heapless::Vec::new();
+        for _ in 0..=MAX_TRACKS {
+            tracks.extend_from_slice(b"track T\n").unwrap();
+        }
+        assert_eq!(
+            Schedule::parse(&tracks).err().unwrap(),
+            ParseError {
+                line: MAX_TRACKS + 1,
+                kind: ErrorKind::TooManyTracks
+            }
+        );
+    }
+}
@@ -0,0 +1,413 @@
+use accelerometer::Accelerometer;
+use core::{fmt::Write, write};
+use embedded_graphics::{
+    Drawable,
+    mono_font::{MonoTextStyle, MonoTextStyleBuilder, ascii::FONT_6X9},
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, Primitive, RgbColor, Size, WebColors},
+    primitives::{Line, PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use heapless::{Deque, String};
+use lis3dh::{DataRate, Lis3dh, Lis3dhCore, Range};
+use micromath::F32Ext;
+use rp2040_hal::Timer;
+
+use crate::{
+    ACCEL_DATA_RATE, ACCEL_RANGE, CONTENT_HEIGHT, TFT_DISPLAY_WIDTH,
+    backlight::{self, Panel},
+    console,
+    error::BadgeError,
+    input::Buttons,
+    power::Delay,
+    status::StatusBar,
+    supervisor::Faults,
+    theme::Theme,
+    usb_serial,
+};
+
+const PLOT_TOP: i32 = 20;
+const PLOT_HEIGHT: u32 = 144;
+const PLOT_WIDTH: usize = TFT_DISPLAY_WIDTH as usize;
+// blank columns kept ahead of the trace so the newest sample stands out
+const SWEEP_GAP: u32 = 4;
+// samples between two refreshes of the min/max/rms block
+const STATS_EVERY: u32 = 16;
+
+const RANGES: [Range; 4] = [Range::G2, Range::G4, Range::G8, Range::G16];
+const DATA_RATES: [DataRate; 7] = [
+    DataRate::Hz_1,
+    DataRate::Hz_10,
+    DataRate::Hz_25,
+    DataRate::Hz_50,
+    DataRate::Hz_100,
+    DataRate::Hz_200,
+    DataRate::Hz_400,
+];
+
+fn range_g(range: Range) -> f32 {
+    match range {
+        Range::G2 => 2.0,
+        Range::G4 => 4.0,
+        Range::G8 => 8.0,
+        Range::G16 => 16.0,
+    }
+}
+
+fn data_rate_hz(data_rate: DataRate) -> u16 {
+    match data_rate {
+        DataRate::Hz_1 => 1,
+        DataRate::Hz_10 => 10,
+        DataRate::Hz_25 => 25,
+        DataRate::Hz_50 => 50,
+        DataRate::Hz_100 => 100,
+        DataRate::Hz_200 => 200,
+        DataRate::Hz_400 => 400,
+        DataRate::PowerDown => 0,
+    }
+}
+
+/// Min, max and RMS of one axis over the samples currently on screen.
+#[derive(Clone, Copy)]
+pub struct AxisStats {
+    pub min: f32,
+    pub max: f32,
+    pub rms: f32,
+}
+
+impl AxisStats {
+    pub fn from_samples(samples: impl Iterator<Item = f32>) -> Self {
+        let mut min = f32::MAX;
+        let mut max = f32::MIN;
+        let mut sum_sq = 0.0;
+        let mut count = 0;
+
+        for sample in samples {
+            min = min.min(sample);
+            max = max.max(sample);
+            sum_sq += sample * sample;
+            count += 1;
+        }
+
+        if count == 0 {
+            return Self {
+                min: 0.0,
+                max: 0.0,
+                rms: 0.0,
+            };
+        }
+
+        Self {
+            min,
+            max,
+            rms: (sum_sq / count as f32).sqrt(),
+        }
+    }
+}
+
+fn sample_to_y(sample: f32, full_scale: f32) -> i32 {
+    let half_height = (PLOT_HEIGHT / 2) as f32 - 1.0;
+    let center = PLOT_TOP + (PLOT_HEIGHT / 2) as i32;
+    let offset = (sample / full_scale).clamp(-1.0, 1.0) * half_height;
+    center - offset as i32
+}
+
+fn draw_header<D, C>(
+    display: &mut D,
+    range: Range,
+    data_rate: DataRate,
+    streaming: bool,
+    theme: &Theme<C>,
+) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    let mut buf: String<64> = String::new();
+    let _ = write!(
+        &mut buf,
+        "range: +-{:<2}g  rate: {:<3}Hz  csv: {:<3}",
+        range_g(range) as u8,
+        data_rate_hz(data_rate),
+        if streaming { "on" } else { "off" }
+    );
+    Text::new(
+        &buf,
+        Point::new(10, 10),
+        text_style(theme.foreground, theme.background),
+    )
+    .draw(display)?;
+    Ok(())
+}
+
+fn draw_stats<D, C>(
+    display: &mut D,
+    history: &Deque<[f32; 3], PLOT_WIDTH>,
+    theme: &Theme<C>,
+) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    let y = PLOT_TOP + PLOT_HEIGHT as i32 + 12;
+
+    for (axis, (name, color)) in ["X", "Y", "Z"].into_iter().zip(theme.axes).enumerate() {
+        let stats = AxisStats::from_samples(history.iter().map(|sample| sample[axis]));
+
+        let mut buf: String<64> = String::new();
+        let _ = write!(
+            &mut buf,
+            "{}  min: {:>6.2}  max: {:>6.2}  rms: {:>5.2}",
+            name, stats.min, stats.max, stats.rms
+        );
+        Text::new(
+            &buf,
+            Point::new(10, y + axis as i32 * 12),
+            text_style(color, theme.background),
+        )
+        .draw(display)?;
+    }
+
+    Ok(())
+}
+
+fn text_style<C>(color: C, background: C) -> MonoTextStyle<'static, C>
+where
+    C: RgbColor,
+{
+    MonoTextStyleBuilder::new()
+        .font(&FONT_6X9)
+        .text_color(color)
+        .background_color(background)
+        .build()
+}
+
+fn draw_footer<D, C>(display: &mut D, theme: &Theme<C>) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    Text::new(
+        "U/D range  L/R rate  A csv  B exit",
+        Point::new(10, CONTENT_HEIGHT as i32 - 8),
+        text_style(theme.muted, theme.background),
+    )
+    .draw(display)?;
+    Ok(())
+}
+
+/// Scope-style live view of the accelerometer: X/Y/Z are traced across the
+/// screen as they arrive, with min/max/rms of the visible window underneath.
+/// Up/Down change the range, Left/Right the data rate, A toggles streaming the
+/// samples as CSV over the USB serial port and B leaves.
+pub fn scope<D, C, L>(
+    display: &mut D,
+    delay: &mut Delay,
+    timer: &Timer,
+    lis3dh: &mut Lis3dh<L>,
+    buttons: &mut Buttons,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+    L: Lis3dhCore,
+    L::PinError: core::fmt::Debug,
+    L::BusError: core::fmt::Debug,
+{
+    let result = sweep(display, delay, timer, lis3dh, buttons, theme);
+
+    // the other apps are tuned for the boot configuration, whatever happened
+    lis3dh
+        .set_range(ACCEL_RANGE)
+        .map_err(BadgeError::accelerometer)?;
+    lis3dh
+        .set_datarate(ACCEL_DATA_RATE)
+        .map_err(BadgeError::accelerometer)?;
+
+    result
+}
+
+fn sweep<D, C, L>(
+    display: &mut D,
+    delay: &mut Delay,
+    timer: &Timer,
+    lis3dh: &mut Lis3dh<L>,
+    buttons: &mut Buttons,
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C> + Panel + StatusBar,
+    D::Error: core::fmt::Debug,
+    L: Lis3dhCore,
+    L::PinError: core::fmt::Debug,
+    L::BusError: core::fmt::Debug,
+{
+    display
+        .clear(theme.background)
+        .map_err(BadgeError::display)?;
+
+    let mut range_idx = RANGES.iter().position(|r| *r == ACCEL_RANGE).unwrap();
+    let mut rate_idx = DATA_RATES
+        .iter()
+        .position(|r| *r == ACCEL_DATA_RATE)
+        .unwrap();
+    let mut streaming = false;
+    let mut faults = Faults::new(BadgeError::Accelerometer);
+
+    let mut history: Deque<[f32; 3], PLOT_WIDTH> = Deque::new();
+    let mut previous_y: Option<[i32; 3]> = None;
+    let mut column: i32 = 0;
+    let mut since_stats = 0;
+
+    let zero_y = sample_to_y(0.0, 1.0);
+
+    draw_header(
+        display,
+        RANGES[range_idx],
+        DATA_RATES[rate_idx],
+        streaming,
+        theme,
+    )
+    .map_err(BadgeError::display)?;
+    draw_footer(display, theme).map_err(BadgeError::display)?;
+
+    loop {
+        let input = buttons.poll();
+
+        if input.console {
+            console::overlay(display, delay, buttons)?;
+            display
+                .clear(theme.background)
+                .map_err(BadgeError::display)?;
+            draw_header(
+                display,
+                RANGES[range_idx],
+                DATA_RATES[rate_idx],
+                streaming,
+                theme,
+            )
+            .map_err(BadgeError::display)?;
+            draw_footer(display, theme).map_err(BadgeError::display)?;
+            previous_y = None;
+        }
+
+        if input.sleep {
+            backlight::sleep(display, delay, buttons, lis3dh)?;
+        }
+        display.update_status()?;
+
+        let ready = faults.check(lis3dh.is_data_ready())?.unwrap_or(false);
+        if ready && let Some(accel) = faults.check(lis3dh.accel_norm())? {
+            backlight::motion(&accel);
+            let sample = [accel.x, accel.y, accel.z];
+
+            if streaming {
+                let mut buf: String<48> = String::new();
+                let _ = write!(
+                    &mut buf,
+                    "{},{:.3},{:.3},{:.3}\r\n",
+                    timer.get_counter().ticks(),
+                    accel.x,
+                    accel.y,
+                    accel.z
+                );
+                usb_serial::write_str(&buf);
+            }
+
+            if history.is_full() {
+                history.pop_front();
+            }
+            let _ = history.push_back(sample);
+
+            // wipe the columns ahead of the trace and redraw the zero line there
+            let gap_width = SWEEP_GAP.min(PLOT_WIDTH as u32 - column as u32);
+            Rectangle::new(
+                Point::new(column, PLOT_TOP),
+                Size::new(gap_width, PLOT_HEIGHT),
+            )
+            .into_styled(PrimitiveStyle::with_fill(theme.background))
+            .draw(display)
+            .map_err(BadgeError::display)?;
+            Rectangle::new(Point::new(column, zero_y), Size::new(gap_width, 1))
+                .into_styled(PrimitiveStyle::with_fill(theme.muted))
+                .draw(display)
+                .map_err(BadgeError::display)?;
+
+            let full_scale = range_g(RANGES[range_idx]);
+            let y = sample.map(|value| sample_to_y(value, full_scale));
+
+            for axis in 0..3 {
+                let from = match previous_y {
+                    Some(previous_y) if column > 0 => Point::new(column - 1, previous_y[axis]),
+                    _ => Point::new(column, y[axis]),
+                };
+                Line::new(from, Point::new(column, y[axis]))
+                    .into_styled(PrimitiveStyle::with_stroke(theme.axes[axis], 1))
+                    .draw(display)
+                    .map_err(BadgeError::display)?;
+            }
+
+            previous_y = Some(y);
+            column = (column + 1) % PLOT_WIDTH as i32;
+
+            since_stats += 1;
+            if since_stats >= STATS_EVERY {
+                draw_stats(display, &history, theme).map_err(BadgeError::display)?;
+                since_stats = 0;
+            }
+        }
+
+        let mut reconfigured = false;
+
+        let range_step = match (input.pressed.up, input.pressed.down) {
+            (true, false) if range_idx + 1 < RANGES.len() => Some(range_idx + 1),
+            (false, true) if range_idx > 0 => Some(range_idx - 1),
+            _ => None,
+        };
+        if let Some(idx) = range_step
+            && faults.check(lis3dh.set_range(RANGES[idx]))?.is_some()
+        {
+            range_idx = idx;
+            reconfigured = true;
+        }
+
+        let rate_step = match (input.pressed.right, input.pressed.left) {
+            (true, false) if rate_idx + 1 < DATA_RATES.len() => Some(rate_idx + 1),
+            (false, true) if rate_idx > 0 => Some(rate_idx - 1),
+            _ => None,
+        };
+        if let Some(idx) = rate_step
+            && faults
+                .check(lis3dh.set_datarate(DATA_RATES[idx]))?
+                .is_some()
+        {
+            rate_idx = idx;
+            reconfigured = true;
+        }
+        if input.pressed.a {
+            streaming = !streaming;
+            if streaming {
+                usb_serial::write_str("t_us,x_g,y_g,z_g\r\n");
+            }
+            reconfigured = true;
+        }
+
+        if reconfigured {
+            draw_header(
+                display,
+                RANGES[range_idx],
+                DATA_RATES[rate_idx],
+                streaming,
+                theme,
+            )
+            .map_err(BadgeError::display)?;
+        }
+
+        if input.released.b {
+            return Ok(());
+        }
+    }
+}
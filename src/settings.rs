@@ -0,0 +1,65 @@
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use crate::{
+    error::BadgeError,
+    log::{log_error, log_warn},
+    record::{Invalid, Settings},
+    storage,
+};
+
+/// Settings stored in flash, or the defaults when there are none (or they are
+/// corrupt).
+pub fn load() -> Settings {
+    match Settings::from_record(storage::SETTINGS.read()) {
+        Ok(settings) => settings,
+        Err(Invalid::Blank) => Settings::default(),
+        Err(Invalid::Length(len)) => {
+            log_warn!("settings: bad length {}", len);
+            Settings::default()
+        }
+        Err(Invalid::Checksum) => {
+            log_warn!("settings: checksum mismatch");
+            Settings::default()
+        }
+    }
+}
+
+/// Writes `settings` to flash, skipping the erase cycle if nothing changed.
+pub fn save(settings: &Settings) -> Result<(), BadgeError> {
+    if load() == *settings {
+        return Ok(());
+    }
+
+    let (record, len) = settings.record();
+    storage::SETTINGS.write(&record[..len])
+}
+
+// changed from the usb interrupt, waiting to be written
+static PENDING: Mutex<RefCell<Option<Settings>>> = Mutex::new(RefCell::new(None));
+
+/// Changes the settings from the shell. Flash is not written from the usb
+/// interrupt, so the result is held until `flush` runs.
+pub fn update(change: impl FnOnce(&mut Settings)) {
+    critical_section::with(|cs| {
+        let mut pending = PENDING.borrow(cs).borrow_mut();
+        let settings = pending.get_or_insert_with(load);
+        change(settings);
+    });
+}
+
+/// The settings with any change from the shell that is still waiting.
+pub fn current() -> Settings {
+    critical_section::with(|cs| PENDING.borrow(cs).borrow().clone()).unwrap_or_else(load)
+}
+
+/// Writes what `update` changed. Called by `Buttons::poll`.
+pub fn flush() {
+    let Some(settings) = critical_section::with(|cs| PENDING.borrow(cs).take()) else {
+        return;
+    };
+    if let Err(error) = save(&settings) {
+        log_error!("settings: {:?}", error);
+    }
+}
@@ -0,0 +1,311 @@
+use core::fmt::Write;
+
+use heapless::String;
+
+use crate::{
+    agenda,
+    contact::Field,
+    crash,
+    log::{self, Level, Sink},
+    preferences::{self, PREFERENCES},
+    qr::Ecc,
+    record::QrContent,
+    rtc::{self, DateTime},
+    settings, usb_serial,
+};
+
+pub const PROMPT: &str = "> ";
+
+const HELP: &str = "commands:\r\n\
+    \x20 help                          this text\r\n\
+    \x20 crash [clear]                 show or forget the last panic\r\n\
+    \x20 log                           show the log filters\r\n\
+    \x20 log <sink> <level|off>        sink: defmt usb screen\r\n\
+    \x20                               level: debug info warn error\r\n\
+    \x20 time                          show the clock\r\n\
+    \x20 time <date> <HH:MM[:SS]>      set the local time, date as YYYY-MM-DD\r\n\
+    \x20 time <unix> [+HH:MM]          set from unix seconds and a utc offset\r\n\
+    \x20 schedule                      show the conference schedule\r\n\
+    \x20 schedule upload               replace it, end with a line holding .\r\n\
+    \x20 schedule clear                go back to the built-in one\r\n\
+    \x20 badge                         show the badge fields\r\n\
+    \x20 badge <field> [text]          set a field, no text clears it\r\n\
+    \x20                               field: name email github mastodon\r\n\
+    \x20                               company url\r\n\
+    \x20 badge vcard                   print the fields as a vcard\r\n\
+    \x20 badge qr <url|vcard> [ecc]    what the qr code holds, ecc: L M Q H\r\n\
+    \x20 set                           show the settings\r\n\
+    \x20 set <name> [value]            show or change one, text may have spaces\r\n";
+
+fn reply(args: core::fmt::Arguments) {
+    let mut buf: String<96> = String::new();
+    let _ = buf.write_fmt(args);
+    let _ = buf.push_str("\r\n");
+    usb_serial::write_str(&buf);
+}
+
+fn filter_name(filter: Option<Level>) -> &'static str {
+    filter.map_or("off", |level| level.name())
+}
+
+fn log_command<'a>(mut args: impl Iterator<Item = &'a str>) {
+    let (Some(sink), Some(level)) = (args.next(), args.next()) else {
+        for sink in Sink::ALL {
+            reply(format_args!(
+                "{:<7}{}",
+                sink.name(),
+                filter_name(log::filter(sink))
+            ));
+        }
+        return;
+    };
+
+    let Some(sink) = Sink::ALL.into_iter().find(|s| s.name() == sink) else {
+        reply(format_args!("unknown sink: {}", sink));
+        return;
+    };
+
+    let filter = match Level::ALL.into_iter().find(|l| l.name() == level) {
+        Some(level) => Some(level),
+        None if level == "off" => None,
+        None => {
+            reply(format_args!("unknown level: {}", level));
+            return;
+        }
+    };
+
+    log::set_filter(sink, filter);
+    reply(format_args!("{} -> {}", sink.name(), filter_name(filter)));
+}
+
+// `+HH:MM` or `-HH:MM` to seconds
+fn parse_offset(text: &str) -> Option<i64> {
+    let (sign, rest) = match text.as_bytes().first()? {
+        b'+' => (1, &text[1..]),
+        b'-' => (-1, &text[1..]),
+        _ => return None,
+    };
+    let mut parts = rest.splitn(2, ':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next().map_or(Some(0), |m| m.parse().ok())?;
+    (hours <= 14 && minutes < 60).then_some(sign * (hours * 3_600 + minutes * 60))
+}
+
+fn time_command<'a>(mut args: impl Iterator<Item = &'a str>) {
+    let seconds = match (args.next(), args.next()) {
+        (None, _) => {
+            match rtc::now() {
+                Some(now) => reply(format_args!(
+                    "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+                    now.year, now.month, now.day, now.hour, now.minute, now.second
+                )),
+                None => reply(format_args!("time not set")),
+            }
+            return;
+        }
+        (Some(date), Some(time)) if date.contains('-') => {
+            DateTime::parse(date, time).map(|now| now.seconds())
+        }
+        (Some(unix), offset) => unix.parse::<i64>().ok().and_then(|unix| match offset {
+            Some(offset) => parse_offset(offset).map(|offset| unix + offset),
+            None => Some(unix),
+        }),
+    };
+
+    match seconds {
+        Some(seconds) => {
+            rtc::set(seconds);
+            time_command(core::iter::empty());
+        }
+        None => reply(format_args!("usage: time <YYYY-MM-DD> <HH:MM[:SS]>")),
+    }
+}
+
+fn schedule_command<'a>(mut args: impl Iterator<Item = &'a str>) {
+    match args.next() {
+        Some("upload") => {
+            agenda::begin_upload();
+            reply(format_args!("send the schedule, end with a line holding ."));
+        }
+        Some("clear") => {
+            agenda::clear();
+            reply(format_args!("back to the built-in schedule"));
+        }
+        _ => match agenda::load() {
+            Ok(schedule) => {
+                reply(format_args!(
+                    "{} days, {} tracks, {} sessions",
+                    schedule.days().count(),
+                    schedule.tracks().count(),
+                    schedule.sessions().count()
+                ));
+                for (i, date) in schedule.days().enumerate() {
+                    reply(format_args!(
+                        "day {}: {}-{:02}-{:02}, {} sessions",
+                        i + 1,
+                        date.year,
+                        date.month,
+                        date.day,
+                        schedule.sessions().filter(|s| s.day == i).count()
+                    ));
+                }
+            }
+            Err(_) => reply(format_args!("no schedule")),
+        },
+    }
+}
+
+fn badge_command(line: &str) {
+    // the rest of the line, spaces and all
+    let rest = line.trim().strip_prefix("badge").unwrap_or("").trim();
+    let (word, text) = rest.split_once(' ').unwrap_or((rest, ""));
+    let text = text.trim();
+
+    match word {
+        "" => {
+            let settings = settings::current();
+            for field in Field::ALL {
+                reply(format_args!(
+                    "{:<9}{}",
+                    field.name(),
+                    settings.contact.get(field)
+                ));
+            }
+            reply(format_args!(
+                "{:<9}{} {}",
+                "qr",
+                settings.qr.name(),
+                settings.qr_ecc.name()
+            ));
+        }
+        "vcard" => match settings::current().contact.vcard() {
+            Some(vcard) => {
+                usb_serial::write_str(&vcard);
+            }
+            None => reply(format_args!("no vcard without a name")),
+        },
+        "qr" => {
+            let mut words = text.split_whitespace();
+            let content = words
+                .next()
+                .and_then(|word| QrContent::ALL.into_iter().find(|c| c.name() == word));
+            let ecc = match words.next() {
+                None => Some(None),
+                Some(word) => Ecc::ALL
+                    .into_iter()
+                    .find(|ecc| ecc.name().eq_ignore_ascii_case(word))
+                    .map(Some),
+            };
+            match (content, ecc) {
+                (Some(content), Some(ecc)) => {
+                    settings::update(|settings| {
+                        settings.qr = content;
+                        settings.qr_ecc = ecc.unwrap_or(settings.qr_ecc);
+                    });
+                    reply(format_args!("qr set"));
+                }
+                _ => reply(format_args!("usage: badge qr <url|vcard> [L|M|Q|H]")),
+            }
+        }
+        word => match Field::ALL.into_iter().find(|field| field.name() == word) {
+            Some(field) => {
+                let mut fits = true;
+                settings::update(|settings| fits = settings.contact.set(field, text));
+                if fits {
+                    reply(format_args!("{} set", word));
+                } else {
+                    reply(format_args!("{} too long, unchanged", word));
+                }
+            }
+            None => reply(format_args!("unknown badge field: {}", word)),
+        },
+    }
+}
+
+fn set_command(line: &str) {
+    let rest = line.trim().strip_prefix("set").unwrap_or("").trim();
+    let (name, text) = rest.split_once(' ').unwrap_or((rest, ""));
+    let text = text.trim();
+
+    if name.is_empty() {
+        let settings = settings::current();
+        for preference in &PREFERENCES {
+            reply(format_args!(
+                "{:<15}{}",
+                preference.name,
+                preference.show(&settings)
+            ));
+        }
+        return;
+    }
+    let Some(preference) = preferences::find(name) else {
+        reply(format_args!("unknown setting: {} (try set)", name));
+        return;
+    };
+    if text.is_empty() {
+        reply(format_args!("{}", preference.show(&settings::current())));
+        return;
+    }
+    let Some(value) = preference.parse(text) else {
+        reply(format_args!("usage: set {} <{}>", name, preference.usage()));
+        return;
+    };
+
+    let mut accepted = false;
+    settings::update(|settings| accepted = preference.set(settings, value));
+    if accepted {
+        preferences::apply(&settings::current());
+        reply(format_args!("{} set", name));
+    } else {
+        reply(format_args!("{} too long, unchanged", name));
+    }
+}
+
+/// A host opened the serial port. The clock is not kept across power cycles,
+/// so ask for the time; a host tool answers with a `time` command.
+pub fn connected() {
+    if rtc::seconds().is_none() {
+        usb_serial::write_str("time?\r\n");
+    }
+}
+
+/// A line too long for the USB serial buffer ended. It is dropped, and
+/// with it a schedule upload it was part of.
+pub fn too_long() {
+    if agenda::cancel_upload() {
+        reply(format_args!("line too long, upload dropped"));
+    } else {
+        reply(format_args!("line too long, ignored"));
+    }
+}
+
+/// Runs one line typed on the USB serial port. Called from the USB interrupt,
+/// so commands must be quick and may only answer through `usb_serial::write`.
+pub fn execute(line: &str) {
+    // the lines of a schedule upload are not commands
+    if agenda::receive(line, reply) {
+        return;
+    }
+
+    let mut words = line.split_whitespace();
+
+    match words.next() {
+        None => {}
+        Some("help") => {
+            usb_serial::write_str(HELP);
+        }
+        Some("log") => log_command(words),
+        Some("time") => time_command(words),
+        Some("schedule") => schedule_command(words),
+        Some("badge") => badge_command(line),
+        Some("set") => set_command(line),
+        Some("crash") => match words.next() {
+            Some("clear") => {
+                crash::clear();
+                reply(format_args!("crash record cleared"));
+            }
+            _ => crash::report(),
+        },
+        Some(command) => reply(format_args!("unknown command: {} (try help)", command)),
+    }
+}
@@ -1,4 +1,3 @@
-use cortex_m::delay::Delay;
 use embedded_graphics::{
     Drawable,
     image::Image,
@@ -7,38 +6,41 @@ use embedded_graphics::{
     prelude::{DrawTarget, Point, RgbColor, WebColors},
     text::{Alignment, Baseline, Text, TextStyleBuilder},
 };
-use embedded_hal::{delay::DelayNs, digital::OutputPin};
-use embedded_hal_compat::Forward;
-use rp2040_hal::gpio::{FunctionSio, Pin, PinId, PullDown, SioOutput};
+use embedded_hal::delay::DelayNs;
 use tinybmp::Bmp;
 
-use crate::bmp::BmpExt;
+use crate::{
+    bmp::{self, BmpExt},
+    error::BadgeError,
+    power::Delay,
+    theme::Theme,
+};
 
-pub fn splash_screen<D, C, P>(
+pub fn splash_screen<D, C>(
     display: &mut D,
-    delay: &mut Forward<Delay>,
-    display_backlight_pin: &mut Pin<P, FunctionSio<SioOutput>, PullDown>,
-    splash_logo: &[u8],
-) where
+    delay: &mut Delay,
+    splash_logo: &'static [u8],
+    theme: &Theme<C>,
+) -> Result<(), BadgeError>
+where
     C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
     D: DrawTarget<Color = C>,
     D::Error: core::fmt::Debug,
-    P: PinId,
 {
-    display_backlight_pin.set_high().unwrap();
-    display.clear(C::WHITE).unwrap();
+    display.clear(C::WHITE).map_err(BadgeError::display)?;
 
-    let gopher_panic: Bmp<C> = Bmp::from_slice(splash_logo).unwrap();
+    let gopher_panic: Bmp<C> = bmp::load(splash_logo)?;
     Image::new(&gopher_panic, gopher_panic.screen_center())
         .draw(display)
-        .unwrap();
+        .map_err(BadgeError::display)?;
 
     Text::with_text_style(
         "Rustified Gopherbadge ",
         Point::new(display.bounding_box().center().x, 20),
         MonoTextStyleBuilder::new()
             .font(&FONT_9X18_BOLD)
-            .text_color(C::CSS_TOMATO)
+            // the logo needs the white, the theme shows in the title
+            .text_color(theme.background)
             .build(),
         TextStyleBuilder::new()
             .alignment(Alignment::Center)
@@ -46,7 +48,8 @@ pub fn splash_screen<D, C, P>(
             .build(),
     )
     .draw(display)
-    .unwrap();
+    .map_err(BadgeError::display)?;
 
     delay.delay_ms(2000);
+    Ok(())
 }
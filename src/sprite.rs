@@ -6,7 +6,7 @@ use embedded_graphics::{
 };
 use tinybmp::Bmp;
 
-use crate::{TFT_DISPLAY_HEIGHT, TFT_DISPLAY_WIDTH, image_rotate::ImageRotate};
+use crate::{CONTENT_HEIGHT, TFT_DISPLAY_WIDTH, image_rotate::ImageRotate};
 
 pub struct SpriteBuilder<'a, C> {
     bmp: &'a Bmp<'a, C>,
@@ -98,10 +98,9 @@ where
         self.pos + Size::new(self.size.width / 2, self.size.height / 2)
     }
 
-    pub fn draw<D>(&mut self, display: &mut D, angle: f32)
+    pub fn draw<D>(&mut self, display: &mut D, angle: f32) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = C>,
-        D::Error: core::fmt::Debug,
     {
         if angle == 0.0 {
             // self.static_image.translate_mut(self.pos).draw(display)
@@ -109,34 +108,35 @@ where
         } else {
             self.rotated_image.update(angle, self.pos).draw(display)
         }
-        .unwrap();
     }
 
-    pub fn draw_with_transparency<D>(&self, display: &mut D)
+    pub fn draw_with_transparency<D>(&self, display: &mut D) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = C>,
-        D::Error: core::fmt::Debug,
     {
         for y in 0..self.bmp.size().height {
             for x in 0..self.bmp.size().width {
-                let pixel = self.bmp.pixel(Point::new(x as i32, y as i32)).unwrap();
-
-                if pixel != self.transparent_color.unwrap() {
-                    display
-                        .draw_iter(core::iter::once(Pixel(
-                            Point::new(x as i32, y as i32) + self.pos,
-                            pixel,
-                        )))
-                        .unwrap();
+                let point = Point::new(x as i32, y as i32);
+                if let Some(pixel) = self.bmp.pixel(point)
+                    && Some(pixel) != self.transparent_color
+                {
+                    display.draw_iter(core::iter::once(Pixel(point + self.pos, pixel)))?;
                 }
             }
         }
+
+        Ok(())
     }
 
-    pub fn clear_diff<D>(&self, display: &mut D, old_pos: Point, new_pos: &mut Point, bg: C)
+    pub fn clear_diff<D>(
+        &self,
+        display: &mut D,
+        old_pos: Point,
+        new_pos: &mut Point,
+        bg: C,
+    ) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = C>,
-        D::Error: core::fmt::Debug,
     {
         let dx = new_pos.x - old_pos.x;
         let dy = new_pos.y - old_pos.y;
@@ -154,8 +154,7 @@ where
                 Size::new(width.min(self.size.width), self.size.height),
             );
             rect.into_styled(PrimitiveStyle::with_fill(bg))
-                .draw(display)
-                .unwrap();
+                .draw(display)?;
         }
 
         // vertical move
@@ -171,15 +170,21 @@ where
                 Size::new(self.size.width, height.min(self.size.height)),
             );
             rect.into_styled(PrimitiveStyle::with_fill(bg))
-                .draw(display)
-                .unwrap();
+                .draw(display)?;
         }
+
+        Ok(())
     }
 
-    pub fn move_to<D>(&mut self, display: &mut D, new_pos: &mut Point, bg: C, angle: f32)
+    pub fn move_to<D>(
+        &mut self,
+        display: &mut D,
+        new_pos: &mut Point,
+        bg: C,
+        angle: f32,
+    ) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = C>,
-        D::Error: core::fmt::Debug,
     {
         if self.screen_boundaries {
             new_pos.x = new_pos
@@ -188,15 +193,15 @@ where
 
             new_pos.y = new_pos
                 .y
-                .clamp(0, TFT_DISPLAY_HEIGHT as i32 - self.size.height as i32);
+                .clamp(0, CONTENT_HEIGHT as i32 - self.size.height as i32);
         }
 
-        self.clear_diff(display, self.pos, new_pos, bg);
+        self.clear_diff(display, self.pos, new_pos, bg)?;
         self.pos = *new_pos;
         if self.transparent_color.is_none() {
-            self.draw(display, angle);
+            self.draw(display, angle)
         } else {
-            self.draw_with_transparency(display);
+            self.draw_with_transparency(display)
         }
     }
 }
@@ -0,0 +1,235 @@
+use core::{cell::Cell, fmt::Write, write};
+
+use critical_section::Mutex;
+use embedded_graphics::{
+    Drawable, Pixel,
+    mono_font::{MonoTextStyleBuilder, ascii::FONT_6X10},
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{
+        DrawTarget, DrawTargetExt, OriginDimensions, Point, Primitive, RgbColor, Size, WebColors,
+    },
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use heapless::String;
+use rp2040_hal::Timer;
+
+use crate::{
+    CONTENT_HEIGHT, STATUS_BAR_HEIGHT, TFT_DISPLAY_WIDTH, backlight::Panel, battery, console,
+    error::BadgeError, power::Delay, rtc, usb_serial,
+};
+
+/// The part of the panel below the bar, what apps draw into.
+pub const CONTENT: Rectangle = Rectangle::new(
+    Point::new(0, STATUS_BAR_HEIGHT as i32),
+    Size::new(TFT_DISPLAY_WIDTH as u32, CONTENT_HEIGHT as u32),
+);
+const BAR: Rectangle = Rectangle::new(
+    Point::zero(),
+    Size::new(TFT_DISPLAY_WIDTH as u32, STATUS_BAR_HEIGHT as u32),
+);
+
+const CLOCK: Point = Point::new(4, 8);
+const USB: Point = Point::new(TFT_DISPLAY_WIDTH as i32 - 72, 8);
+const NOTIFICATION: Rectangle = Rectangle::new(
+    Point::new(TFT_DISPLAY_WIDTH as i32 - 44, 2),
+    Size::new(12, 12),
+);
+const BATTERY_ICON: Point = Point::new(TFT_DISPLAY_WIDTH as i32 - 28, 2);
+
+/// What the bar shows, to only repaint the parts that changed.
+#[derive(Clone, Copy, PartialEq)]
+struct Shown {
+    minutes: u32,
+    // wall time rather than uptime
+    clock: bool,
+    usb: bool,
+    notification: bool,
+    // the battery sample, `None` before the first one
+    battery: Option<u32>,
+}
+
+// `None` when the bar has to be painted from scratch
+static SHOWN: Mutex<Cell<Option<Shown>>> = Mutex::new(Cell::new(None));
+
+/// Refreshing the status bar, for the apps that run below one.
+pub trait StatusBar {
+    /// Called once a frame, only draws what changed since the last call.
+    fn update_status(&mut self) -> Result<(), BadgeError>;
+}
+
+/// The panel with the status bar on top. Apps get this as their draw target,
+/// its origin is the top left of `CONTENT` and anything outside is clipped.
+pub struct Screen<'a, D> {
+    display: &'a mut D,
+    timer: Timer,
+}
+
+impl<'a, D> Screen<'a, D> {
+    /// Whatever was drawn before owned the whole panel, so the bar is
+    /// painted again on the first update.
+    pub fn new(display: &'a mut D, timer: Timer) -> Self {
+        critical_section::with(|cs| SHOWN.borrow(cs).set(None));
+        Self { display, timer }
+    }
+
+    /// The whole panel, for pages that draw over the bar.
+    pub fn panel(&mut self) -> &mut D {
+        critical_section::with(|cs| SHOWN.borrow(cs).set(None));
+        self.display
+    }
+}
+
+impl<D: DrawTarget> OriginDimensions for Screen<'_, D> {
+    fn size(&self) -> Size {
+        CONTENT.size
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for Screen<'_, D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.display.cropped(&CONTENT).draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.display.cropped(&CONTENT).fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.cropped(&CONTENT).fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.fill_solid(&CONTENT, color)
+    }
+}
+
+impl<D: Panel> Panel for Screen<'_, D> {
+    fn sleep(&mut self, delay: &mut Delay) -> Result<(), BadgeError> {
+        self.display.sleep(delay)
+    }
+
+    fn wake(&mut self, delay: &mut Delay) -> Result<(), BadgeError> {
+        self.display.wake(delay)
+    }
+}
+
+impl<D, C> StatusBar for Screen<'_, D>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+{
+    fn update_status(&mut self) -> Result<(), BadgeError> {
+        let battery = battery::reading();
+        let clock = rtc::seconds();
+        let now = Shown {
+            minutes: match clock {
+                Some(seconds) => (seconds / 60).rem_euclid(24 * 60) as u32,
+                None => (self.timer.get_counter().ticks() / 60_000_000) as u32,
+            },
+            clock: clock.is_some(),
+            usb: usb_serial::is_connected(),
+            notification: console::unread(),
+            battery: battery.map(|(_, sample)| sample),
+        };
+        let before = critical_section::with(|cs| SHOWN.borrow(cs).replace(Some(now)));
+        if before == Some(now) {
+            return Ok(());
+        }
+
+        draw(
+            self.display,
+            before,
+            &now,
+            battery.map(|(reading, _)| reading),
+        )
+        .map_err(BadgeError::display)
+    }
+}
+
+fn draw<D, C>(
+    display: &mut D,
+    before: Option<Shown>,
+    now: &Shown,
+    battery: Option<battery::Reading>,
+) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    if before.is_none() {
+        BAR.into_styled(PrimitiveStyle::with_fill(C::BLACK))
+            .draw(display)?;
+    }
+
+    let style = |color| {
+        MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(color)
+            .background_color(C::BLACK)
+            .build()
+    };
+
+    if before.is_none_or(|before| before.minutes != now.minutes) {
+        // uptime, in gray, until the time is set
+        let mut buf: String<8> = String::new();
+        let _ = write!(
+            &mut buf,
+            "{:02}:{:02}",
+            now.minutes / 60 % 100,
+            now.minutes % 60
+        );
+        let color = if now.clock { C::WHITE } else { C::CSS_GRAY };
+        Text::with_baseline(&buf, CLOCK, style(color), Baseline::Middle).draw(display)?;
+    }
+
+    if before.is_none_or(|before| before.usb != now.usb) {
+        let color = if now.usb {
+            C::CSS_DEEP_SKY_BLUE
+        } else {
+            C::BLACK
+        };
+        Text::with_baseline("USB", USB, style(color), Baseline::Middle).draw(display)?;
+    }
+
+    if before.is_none_or(|before| before.notification != now.notification) {
+        let color = if now.notification {
+            C::YELLOW
+        } else {
+            C::BLACK
+        };
+        NOTIFICATION
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(display)?;
+        if now.notification {
+            Text::with_baseline(
+                "!",
+                NOTIFICATION.center() - Point::new(2, 0),
+                MonoTextStyleBuilder::new()
+                    .font(&FONT_6X10)
+                    .text_color(C::BLACK)
+                    .build(),
+                Baseline::Middle,
+            )
+            .draw(display)?;
+        }
+    }
+
+    if let Some(reading) = battery
+        && before.is_none_or(|before| before.battery != now.battery)
+    {
+        battery::draw_icon(display, BATTERY_ICON, &reading)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use rp2040_flash::flash;
+
+use crate::{error::BadgeError, supervisor};
+
+const XIP_BASE: u32 = 0x1000_0000;
+const SECTOR_SIZE: u32 = 4096;
+const PAGE_SIZE: usize = 256;
+
+/// A range of flash kept out of the firmware image, see `memory.x`.
+pub struct Region {
+    offset: u32,
+    len: u32,
+}
+
+pub const SETTINGS: Region = Region {
+    offset: 0x1F_F000,
+    len: SECTOR_SIZE,
+};
+
+pub const SCHEDULE_LEN: usize = 4 * SECTOR_SIZE as usize;
+
+/// The conference schedule uploaded over usb, see `agenda`.
+pub const SCHEDULE: Region = Region {
+    offset: 0x1F_B000,
+    len: SCHEDULE_LEN as u32,
+};
+
+impl Region {
+    pub fn read(&self) -> &'static [u8] {
+        unsafe {
+            core::slice::from_raw_parts((XIP_BASE + self.offset) as *const u8, self.len as usize)
+        }
+    }
+
+    /// Erases the whole region and programs `data` at its start. Must not be
+    /// called with interrupts off, they run between the sectors.
+    pub fn write(&self, data: &[u8]) -> Result<(), BadgeError> {
+        if data.len() > self.len as usize {
+            return Err(BadgeError::storage("data larger than the region"));
+        }
+
+        // nothing may run from flash while it is being erased or programmed,
+        // that includes our own interrupt handlers. A sector at a time, as a
+        // whole region can take longer than the watchdog period.
+        for sector in (0..self.len).step_by(SECTOR_SIZE as usize) {
+            critical_section::with(|_| unsafe {
+                flash::flash_range_erase(self.offset + sector, SECTOR_SIZE, true);
+            });
+            supervisor::feed();
+        }
+
+        for (i, chunk) in data.chunks(PAGE_SIZE).enumerate() {
+            // `data` may live in flash itself, stage each page in ram first
+            let mut page = [0xffu8; PAGE_SIZE];
+            page[..chunk.len()].copy_from_slice(chunk);
+
+            critical_section::with(|_| unsafe {
+                flash::flash_range_program(self.offset + (i * PAGE_SIZE) as u32, &page, true);
+            });
+        }
+
+        Ok(())
+    }
+}
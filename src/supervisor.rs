@@ -0,0 +1,257 @@
+use core::{
+    cell::RefCell,
+    fmt::Debug,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use critical_section::Mutex;
+use embedded_graphics::{
+    Drawable,
+    mono_font::{MonoTextStyleBuilder, ascii::FONT_9X15_BOLD},
+    pixelcolor::{Rgb555, Rgb565, Rgb888},
+    prelude::{DrawTarget, Point, RgbColor, WebColors},
+    text::{Alignment, Text},
+};
+use embedded_hal::delay::DelayNs;
+use rp2040_hal::{
+    Timer,
+    fugit::MicrosDurationU32,
+    pac::{self, interrupt},
+    timer::{Alarm, Alarm1},
+    watchdog::{ScratchRegister, Watchdog},
+};
+
+use crate::{error::BadgeError, log::log_warn, power::Delay};
+
+const CHECK_INTERVAL: MicrosDurationU32 = MicrosDurationU32::millis(100);
+// how long the foreground may go without polling the buttons before the
+// badge is reset back to the menu
+const APP_TIMEOUT_MS: u32 = 3_000;
+// only has to catch the check interrupt itself being stuck
+const WATCHDOG_PERIOD: MicrosDurationU32 = MicrosDurationU32::millis(500);
+
+// scratch 4..7 belong to the boot rom
+const RUNNING: ScratchRegister = ScratchRegister::Scratch0;
+// upper half tells a value we wrote from whatever was left in the register
+const RUNNING_TAG: u32 = 0x6170_0000;
+const RUNNING_MASK: u32 = 0xffff_0000;
+
+// consecutive peripheral errors before an app gives up
+const MAX_FAULTS: u8 = 5;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ResetReason {
+    PowerOn,
+    RunPin,
+    Debugger,
+    Watchdog,
+    /// the watchdog was triggered on purpose, e.g. rebooting into the bootloader
+    Forced,
+}
+
+impl ResetReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResetReason::PowerOn => "power-on",
+            ResetReason::RunPin => "run pin",
+            ResetReason::Debugger => "debugger",
+            ResetReason::Watchdog => "watchdog",
+            ResetReason::Forced => "forced",
+        }
+    }
+}
+
+/// Why the chip came out of reset. A panic resets the core only, so it shows
+/// up as whatever reset came before it, see `crash::last` for those.
+pub fn reset_reason() -> ResetReason {
+    // SAFETY: read only, and the reason registers are not touched by the hal
+    let (watchdog, chip) = unsafe { (&*pac::WATCHDOG::ptr(), &*pac::VREG_AND_CHIP_RESET::ptr()) };
+
+    let reason = watchdog.reason().read();
+    if reason.timer().bit_is_set() {
+        return ResetReason::Watchdog;
+    }
+    if reason.force().bit_is_set() {
+        return ResetReason::Forced;
+    }
+
+    let chip_reset = chip.chip_reset().read();
+    if chip_reset.had_psm_restart().bit_is_set() {
+        ResetReason::Debugger
+    } else if chip_reset.had_run().bit_is_set() {
+        ResetReason::RunPin
+    } else {
+        ResetReason::PowerOn
+    }
+}
+
+struct Supervisor {
+    watchdog: Watchdog,
+    alarm: Alarm1,
+    silent_ms: u32,
+}
+
+static SUPERVISOR: Mutex<RefCell<Option<Supervisor>>> = Mutex::new(RefCell::new(None));
+// set by the foreground every frame, cleared by the check interrupt
+static ALIVE: AtomicBool = AtomicBool::new(true);
+
+/// Starts the watchdog. From here on the foreground has to call `checkin`
+/// at least every `APP_TIMEOUT_MS` or the badge resets. Returns the app that
+/// was running if that is what happened last time.
+pub fn init(mut watchdog: Watchdog, mut timer: Timer) -> Option<u16> {
+    let running = watchdog.read_scratch(RUNNING);
+    let hung = (reset_reason() == ResetReason::Watchdog && running & RUNNING_MASK == RUNNING_TAG)
+        .then_some((running & !RUNNING_MASK) as u16);
+    watchdog.write_scratch(RUNNING, 0);
+
+    // a halted core must not be reset under the debugger
+    watchdog.pause_on_debug(true);
+    watchdog.start(WATCHDOG_PERIOD);
+
+    // each alarm has one owner: leds 0, the supervisor 1 and `Delay` 2
+    let mut alarm = timer.alarm_1().expect("alarm 1 is the supervisor's");
+    alarm
+        .schedule(CHECK_INTERVAL)
+        .expect("CHECK_INTERVAL fits the 32 bit alarm");
+    alarm.enable_interrupt();
+
+    critical_section::with(|cs| {
+        SUPERVISOR.borrow(cs).replace(Some(Supervisor {
+            watchdog,
+            alarm,
+            silent_ms: 0,
+        }));
+    });
+
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_1);
+    }
+
+    hung
+}
+
+/// The foreground is still making progress.
+pub fn checkin() {
+    ALIVE.store(true, Ordering::Relaxed);
+}
+
+/// Feeds the watchdog straight away, for work that keeps interrupts off for
+/// long stretches and so holds up the check interrupt.
+pub fn feed() {
+    critical_section::with(|cs| {
+        if let Some(supervisor) = SUPERVISOR.borrow(cs).borrow_mut().as_mut() {
+            supervisor.watchdog.feed();
+        }
+    });
+}
+
+/// Remembers which app runs, so a watchdog reset can tell which one hung.
+pub fn enter(app: u16) {
+    critical_section::with(|cs| {
+        if let Some(supervisor) = SUPERVISOR.borrow(cs).borrow_mut().as_mut() {
+            supervisor
+                .watchdog
+                .write_scratch(RUNNING, RUNNING_TAG | app as u32);
+        }
+    });
+}
+
+/// Back in the menu.
+pub fn leave() {
+    critical_section::with(|cs| {
+        if let Some(supervisor) = SUPERVISOR.borrow(cs).borrow_mut().as_mut() {
+            supervisor.watchdog.write_scratch(RUNNING, 0);
+        }
+    });
+}
+
+/// Tells the user the badge was reset because `app` hung, before the menu
+/// comes back.
+pub fn hung_notice<D, C>(display: &mut D, delay: &mut Delay, app: &str) -> Result<(), D::Error>
+where
+    C: RgbColor + WebColors + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+    D: DrawTarget<Color = C>,
+{
+    display.clear(C::BLACK)?;
+
+    let center = display.bounding_box().center();
+    let style = |color| {
+        MonoTextStyleBuilder::new()
+            .font(&FONT_9X15_BOLD)
+            .text_color(color)
+            .build()
+    };
+
+    Text::with_alignment(
+        app,
+        center - Point::new(0, 12),
+        style(C::CSS_ORANGE),
+        Alignment::Center,
+    )
+    .draw(display)?;
+    Text::with_alignment(
+        "stopped responding",
+        center + Point::new(0, 12),
+        style(C::WHITE),
+        Alignment::Center,
+    )
+    .draw(display)?;
+
+    delay.delay_ms(2000);
+    Ok(())
+}
+
+/// Counts consecutive errors of a peripheral: a glitch is skipped, a device
+/// that keeps failing makes the app give up and go back to the menu.
+pub struct Faults {
+    error: BadgeError,
+    count: u8,
+}
+
+impl Faults {
+    pub fn new(error: BadgeError) -> Self {
+        Self { error, count: 0 }
+    }
+
+    /// The value on success, `None` on an error worth retrying and `error`
+    /// once the device keeps failing.
+    pub fn check<T, E: Debug>(&mut self, result: Result<T, E>) -> Result<Option<T>, BadgeError> {
+        match result {
+            Ok(value) => {
+                self.count = 0;
+                Ok(Some(value))
+            }
+            Err(error) if self.count + 1 >= MAX_FAULTS => Err(self.error.logged(error)),
+            Err(error) => {
+                self.count += 1;
+                log_warn!("{}: {:?} ({})", self.error.name(), error, self.count);
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[interrupt]
+fn TIMER_IRQ_1() {
+    critical_section::with(|cs| {
+        let mut supervisor = SUPERVISOR.borrow(cs).borrow_mut();
+        let Some(supervisor) = supervisor.as_mut() else {
+            return;
+        };
+
+        supervisor.alarm.clear_interrupt();
+        let _ = supervisor.alarm.schedule(CHECK_INTERVAL);
+
+        if ALIVE.load(Ordering::Relaxed) {
+            ALIVE.store(false, Ordering::Relaxed);
+            supervisor.silent_ms = 0;
+        } else {
+            supervisor.silent_ms += CHECK_INTERVAL.to_millis();
+        }
+
+        // a hung foreground stops the feeding and the watchdog takes over
+        if supervisor.silent_ms < APP_TIMEOUT_MS {
+            supervisor.watchdog.feed();
+        }
+    });
+}
@@ -0,0 +1,385 @@
+// Text wrapped into a rectangle at word boundaries. Works with any
+// embedded-graphics text style, the mono fonts as well as the firmware's
+// `FontStyle`.
+// Lines are worked out as they are drawn, nothing is allocated.
+
+use embedded_graphics::{
+    Drawable,
+    prelude::{DrawTarget, Point},
+    primitives::Rectangle,
+    text::{Alignment, Baseline, Text, TextStyleBuilder, renderer::TextRenderer},
+};
+use heapless::String;
+
+const ELLIPSIS: &str = "...";
+// room for a line and its ellipsis, longer lines are shortened to fit
+const LINE_LEN: usize = 160;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum VerticalAlignment {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// `text` wrapped to fit `bounds`. Text that does not fit is split into
+/// pages, `page` picks the one drawn.
+#[derive(Clone, Copy)]
+pub struct TextBox<'a, S> {
+    text: &'a str,
+    bounds: Rectangle,
+    style: S,
+    alignment: Alignment,
+    vertical_alignment: VerticalAlignment,
+    ellipsis: bool,
+    page: usize,
+}
+
+impl<'a, S: TextRenderer + Clone> TextBox<'a, S> {
+    pub fn new(text: &'a str, bounds: Rectangle, style: S) -> Self {
+        Self {
+            text,
+            bounds,
+            style,
+            alignment: Alignment::Left,
+            vertical_alignment: VerticalAlignment::Top,
+            ellipsis: false,
+            page: 0,
+        }
+    }
+
+    pub fn with_alignment(self, alignment: Alignment) -> Self {
+        Self { alignment, ..self }
+    }
+
+    pub fn with_vertical_alignment(self, vertical_alignment: VerticalAlignment) -> Self {
+        Self {
+            vertical_alignment,
+            ..self
+        }
+    }
+
+    /// Ends the page with `...` when there is more after it.
+    pub fn with_ellipsis(self) -> Self {
+        Self {
+            ellipsis: true,
+            ..self
+        }
+    }
+
+    pub fn with_page(self, page: usize) -> Self {
+        Self { page, ..self }
+    }
+
+    fn width(&self, text: &str) -> u32 {
+        self.style
+            .measure_string(text, Point::zero(), Baseline::Top)
+            .bounding_box
+            .size
+            .width
+    }
+
+    fn lines_per_page(&self) -> usize {
+        (self.bounds.size.height / self.style.line_height()).max(1) as usize
+    }
+
+    // the first line of `text` and what is left after it
+    fn split_line<'t>(&self, text: &'t str) -> (&'t str, &'t str) {
+        let (paragraph, next) = match text.split_once('\n') {
+            Some((paragraph, next)) => (paragraph, Some(next)),
+            None => (text, None),
+        };
+        let max = self.bounds.size.width;
+
+        // as many whole words as fit
+        let mut end = 0;
+        for (i, _) in paragraph
+            .match_indices(' ')
+            .chain(core::iter::once((paragraph.len(), "")))
+        {
+            if self.width(paragraph[..i].trim_end()) > max {
+                break;
+            }
+            end = i;
+        }
+
+        // a word wider than the box is broken wherever it has to be
+        if end == 0 && !paragraph.is_empty() {
+            end = paragraph
+                .char_indices()
+                .skip(1)
+                .map(|(i, _)| i)
+                .chain(core::iter::once(paragraph.len()))
+                .take_while(|i| self.width(&paragraph[..*i]) <= max)
+                .last()
+                .unwrap_or_else(|| paragraph.chars().next().map_or(0, char::len_utf8));
+        }
+
+        if end >= paragraph.len() {
+            return (paragraph, next.unwrap_or(""));
+        }
+        (
+            paragraph[..end].trim_end(),
+            text[end..].trim_start_matches(' '),
+        )
+    }
+
+    fn lines(&self) -> impl Iterator<Item = &'a str> + '_ {
+        let mut rest = self.text;
+        core::iter::from_fn(move || {
+            if rest.is_empty() {
+                return None;
+            }
+            let (line, next) = self.split_line(rest);
+            rest = next;
+            Some(line)
+        })
+    }
+
+    /// How many pages the text takes.
+    pub fn pages(&self) -> usize {
+        self.lines().count().div_ceil(self.lines_per_page()).max(1)
+    }
+
+    fn draw_line<D>(&self, display: &mut D, line: &str, y: i32) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = S::Color>,
+    {
+        let x = match self.alignment {
+            Alignment::Left => self.bounds.top_left.x,
+            Alignment::Center => self.bounds.center().x,
+            Alignment::Right => self.bounds.top_left.x + self.bounds.size.width as i32 - 1,
+        };
+        let text_style = TextStyleBuilder::new()
+            .alignment(self.alignment)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(line, Point::new(x, y), self.style.clone(), text_style)
+            .draw(display)?;
+        Ok(())
+    }
+
+    // `line` cut short enough to take `...` after it
+    fn with_ellipsis_added(&self, line: &str) -> String<LINE_LEN> {
+        let max = self.bounds.size.width;
+        let mut end = line.len().min(LINE_LEN - ELLIPSIS.len());
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        loop {
+            let mut out = String::new();
+            let _ = out.push_str(line[..end].trim_end());
+            let fits = out.push_str(ELLIPSIS).is_ok() && self.width(&out) <= max;
+            if fits || end == 0 {
+                return out;
+            }
+            end = line[..end].char_indices().last().map_or(0, |(i, _)| i);
+        }
+    }
+}
+
+impl<S: TextRenderer + Clone> Drawable for TextBox<'_, S> {
+    type Color = S::Color;
+    type Output = ();
+
+    fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let per_page = self.lines_per_page();
+        let mut lines = self.lines().skip(self.page * per_page).peekable();
+        let height = self.style.line_height() as i32;
+
+        let shown = self
+            .lines()
+            .skip(self.page * per_page)
+            .take(per_page)
+            .count() as i32;
+        let room = self.bounds.size.height as i32 - shown * height;
+        let mut y = self.bounds.top_left.y
+            + match self.vertical_alignment {
+                VerticalAlignment::Top => 0,
+                VerticalAlignment::Middle => room / 2,
+                VerticalAlignment::Bottom => room,
+            };
+
+        for i in 0..per_page {
+            let Some(line) = lines.next() else {
+                break;
+            };
+            let last = i + 1 == per_page;
+            if last && self.ellipsis && lines.peek().is_some() {
+                self.draw_line(display, &self.with_ellipsis_added(line), y)?;
+            } else {
+                self.draw_line(display, line, y)?;
+            }
+            y += height;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        mono_font::{MonoTextStyle, ascii::FONT_6X10},
+        pixelcolor::BinaryColor,
+        prelude::Size,
+        text::renderer::TextMetrics,
+    };
+
+    // ten characters wide, three lines high
+    fn text_box(text: &str) -> TextBox<'_, MonoTextStyle<'static, BinaryColor>> {
+        let bounds = Rectangle::new(Point::zero(), Size::new(60, 30));
+        TextBox::new(
+            text,
+            bounds,
+            MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+        )
+    }
+
+    fn lines<'a>(
+        text_box: &TextBox<'a, MonoTextStyle<'static, BinaryColor>>,
+    ) -> heapless::Vec<&'a str, 16> {
+        text_box.lines().collect()
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let text_box = text_box("the quick brown fox jumps");
+        assert_eq!(lines(&text_box), ["the quick", "brown fox", "jumps"]);
+    }
+
+    #[test]
+    fn keeps_a_line_that_fits_exactly() {
+        assert_eq!(lines(&text_box("0123456789")), ["0123456789"]);
+        assert_eq!(lines(&text_box("abcd efghi jk")), ["abcd efghi", "jk"]);
+        assert_eq!(lines(&text_box("0123456789 ab")), ["0123456789", "ab"]);
+    }
+
+    #[test]
+    fn keeps_empty_lines_between_paragraphs() {
+        assert_eq!(lines(&text_box("one\n\ntwo")), ["one", "", "two"]);
+    }
+
+    #[test]
+    fn breaks_a_word_wider_than_the_box() {
+        let text_box = text_box("abcdefghijklmnopqrstuvwxyz end");
+        assert_eq!(lines(&text_box), ["abcdefghij", "klmnopqrst", "uvwxyz end"]);
+    }
+
+    #[test]
+    fn breaks_between_characters_not_bytes() {
+        let text_box = text_box("ééééééééééé ü");
+        assert_eq!(lines(&text_box), ["éééééééééé", "é ü"]);
+    }
+
+    #[test]
+    fn the_ellipsis_keeps_the_text_before_it() {
+        let text_box = text_box("");
+        assert_eq!(text_box.with_ellipsis_added("the quick"), "the qui...");
+        assert_eq!(text_box.with_ellipsis_added("a b"), "a b...");
+        assert_eq!(text_box.with_ellipsis_added("abcd efghi"), "abcd ef...");
+        // the space before the cut goes, the ellipsis follows the word
+        assert_eq!(text_box.with_ellipsis_added("abcdef ghij"), "abcdef...");
+        assert_eq!(text_box.with_ellipsis_added("éééééééééé"), "ééééééé...");
+    }
+
+    #[test]
+    fn the_ellipsis_fits_a_line_longer_than_the_buffer() {
+        let long: heapless::String<200> = core::iter::repeat_n('a', 200).collect();
+        let wide = Rectangle::new(Point::zero(), Size::new(6 * 300, 10));
+        let text_box = TextBox::new("", wide, MonoTextStyle::new(&FONT_6X10, BinaryColor::On));
+        let shortened = text_box.with_ellipsis_added(&long);
+        assert_eq!(shortened.len(), LINE_LEN);
+        assert!(shortened.starts_with("aaaa"));
+        assert!(shortened.ends_with("a..."));
+    }
+
+    #[test]
+    fn splits_into_pages() {
+        assert_eq!(text_box("").pages(), 1);
+        assert_eq!(text_box("one\ntwo\nthree").pages(), 1);
+        assert_eq!(text_box("one\ntwo\nthree\nfour").pages(), 2);
+        let text_box = text_box("1\n2\n3\n4\n5\n6\n7");
+        assert_eq!(text_box.pages(), 3);
+
+        // a box lower than a line still shows one
+        let low = Rectangle::new(Point::zero(), Size::new(60, 4));
+        let low = TextBox::new("1\n2", low, MonoTextStyle::new(&FONT_6X10, BinaryColor::On));
+        assert_eq!(low.pages(), 2);
+    }
+
+    // keeps what is drawn instead of drawing it, six pixels a character
+    #[derive(Clone)]
+    struct Drawn<'a>(&'a RefCell<heapless::Vec<String<LINE_LEN>, 4>>);
+
+    impl TextRenderer for Drawn<'_> {
+        type Color = BinaryColor;
+
+        fn draw_string<D>(
+            &self,
+            text: &str,
+            position: Point,
+            _: Baseline,
+            _: &mut D,
+        ) -> Result<Point, D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            self.0
+                .borrow_mut()
+                .push(String::try_from(text).unwrap())
+                .unwrap();
+            Ok(position)
+        }
+
+        fn draw_whitespace<D>(
+            &self,
+            _: u32,
+            position: Point,
+            _: Baseline,
+            _: &mut D,
+        ) -> Result<Point, D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            Ok(position)
+        }
+
+        fn measure_string(&self, text: &str, position: Point, _: Baseline) -> TextMetrics {
+            let size = Size::new(6 * text.chars().count() as u32, 10);
+            TextMetrics {
+                bounding_box: Rectangle::new(position, size),
+                next_position: position + size.x_axis(),
+            }
+        }
+
+        fn line_height(&self) -> u32 {
+            10
+        }
+    }
+
+    fn drawn(text: &str, page: usize) -> heapless::Vec<String<LINE_LEN>, 4> {
+        let drawn = RefCell::new(heapless::Vec::new());
+        let bounds = Rectangle::new(Point::zero(), Size::new(60, 30));
+        TextBox::new(text, bounds, Drawn(&drawn))
+            .with_ellipsis()
+            .with_page(page)
+            .draw(&mut MockDisplay::<BinaryColor>::new())
+            .unwrap();
+        drawn.into_inner()
+    }
+
+    #[test]
+    fn draws_the_page_asked_for_with_an_ellipsis_when_more_follows() {
+        let text = "1\n2\n3\n4\n5\n6 and so\n7";
+        assert_eq!(drawn(text, 0), ["1", "2", "3..."]);
+        assert_eq!(drawn(text, 1), ["4", "5", "6 and s..."]);
+        assert_eq!(drawn(text, 2), ["7"]);
+        assert!(drawn(text, 3).is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use embedded_graphics::{
+    mono_font::{
+        MonoFont,
+        ascii::{FONT_9X15_BOLD, FONT_9X18_BOLD},
+    },
+    pixelcolor::Rgb888,
+    prelude::{RgbColor, WebColors},
+};
+
+use crate::{
+    font::{Font, PROPORTIONAL_15, PROPORTIONAL_20},
+    record::ThemeName,
+    settings,
+};
+
+const GOPHER_BLUE: Rgb888 = Rgb888::new(0x00, 0xad, 0xd8);
+const GO_YELLOW: Rgb888 = Rgb888::new(0xfd, 0xdd, 0x00);
+
+/// Colors and fonts the apps draw with. Selected items are drawn in the
+/// background color on `selection`.
+#[derive(Clone, Copy)]
+pub struct Theme<C> {
+    pub background: C,
+    pub foreground: C,
+    /// values and highlights that should stand out
+    pub accent: C,
+    /// hints, grids and unlit parts, faint against the background
+    pub muted: C,
+    pub selection: C,
+    /// traces of the x, y and z axes in plots
+    pub axes: [C; 3],
+    /// menus and fields
+    pub font: &'static MonoFont<'static>,
+    /// what the user typed, names and contact details
+    pub text_font: &'static Font,
+}
+
+impl<C: From<Rgb888>> Theme<C> {
+    pub fn new(name: ThemeName) -> Self {
+        let (background, foreground, accent, muted, selection) = match name {
+            ThemeName::Rust => (
+                Rgb888::CSS_ORANGE_RED,
+                Rgb888::WHITE,
+                Rgb888::CSS_GOLD,
+                Rgb888::CSS_DARK_RED,
+                Rgb888::WHITE,
+            ),
+            ThemeName::Go => (
+                GOPHER_BLUE,
+                Rgb888::WHITE,
+                GO_YELLOW,
+                Rgb888::CSS_MIDNIGHT_BLUE,
+                Rgb888::WHITE,
+            ),
+            ThemeName::HighContrast => (
+                Rgb888::BLACK,
+                Rgb888::WHITE,
+                Rgb888::YELLOW,
+                Rgb888::CSS_GRAY,
+                Rgb888::YELLOW,
+            ),
+            ThemeName::Dark => (
+                Rgb888::BLACK,
+                Rgb888::CSS_LIGHT_GRAY,
+                Rgb888::CSS_ORANGE,
+                Rgb888::CSS_DIM_GRAY,
+                Rgb888::CSS_ORANGE,
+            ),
+        };
+        // told apart from each other and from the background
+        let axes = match name {
+            ThemeName::Rust => [Rgb888::CSS_GOLD, Rgb888::WHITE, Rgb888::BLACK],
+            ThemeName::Go => [GO_YELLOW, Rgb888::WHITE, Rgb888::BLACK],
+            ThemeName::HighContrast | ThemeName::Dark => {
+                [Rgb888::RED, Rgb888::GREEN, Rgb888::CSS_DEEP_SKY_BLUE]
+            }
+        };
+        // bigger and bolder text where it fits the layouts
+        let (font, text_font) = match name {
+            ThemeName::HighContrast => (&FONT_9X18_BOLD, &PROPORTIONAL_20),
+            _ => (&FONT_9X15_BOLD, &PROPORTIONAL_15),
+        };
+        Self {
+            background: background.into(),
+            foreground: foreground.into(),
+            accent: accent.into(),
+            muted: muted.into(),
+            selection: selection.into(),
+            axes: axes.map(Into::into),
+            font,
+            text_font,
+        }
+    }
+
+    /// The theme picked in the settings.
+    pub fn current() -> Self {
+        Self::new(settings::current().theme)
+    }
+}
@@ -0,0 +1,173 @@
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use critical_section::Mutex;
+use heapless::Deque;
+use rp2040_hal::{
+    pac::{self, interrupt},
+    usb::UsbBus,
+};
+use usb_device::{
+    bus::UsbBusAllocator,
+    device::{StringDescriptors, UsbDevice, UsbDeviceBuilder, UsbDeviceState, UsbVidPid},
+};
+use usbd_serial::{SerialPort, USB_CLASS_CDC};
+
+use crate::{
+    line::{LineBuffer, TooLong},
+    shell,
+};
+
+// bytes waiting for the host, whatever does not fit is dropped
+const TX_CAPACITY: usize = 2048;
+// room for a schedule line with a long title and speaker
+const LINE_LEN: usize = 256;
+
+struct UsbSerial {
+    device: UsbDevice<'static, UsbBus>,
+    serial: SerialPort<'static, UsbBus>,
+    // the shell command being typed, utf-8 and decoded once complete
+    line: LineBuffer<LINE_LEN>,
+}
+
+static USB_SERIAL: Mutex<RefCell<Option<UsbSerial>>> = Mutex::new(RefCell::new(None));
+// mirrored by the interrupt, so it can be read without taking USB_SERIAL
+static CONNECTED: AtomicBool = AtomicBool::new(false);
+// kept apart from USB_SERIAL so code running inside the interrupt can write too
+static TX: Mutex<RefCell<Deque<u8, TX_CAPACITY>>> = Mutex::new(RefCell::new(Deque::new()));
+
+/// Builds the CDC device and hands it over to the USB interrupt, which keeps
+/// it enumerated regardless of what the foreground app is doing.
+pub fn init(usb_bus: &'static UsbBusAllocator<UsbBus>) {
+    let serial = SerialPort::new(usb_bus);
+
+    let device = UsbDeviceBuilder::new(usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .strings(&[StringDescriptors::default()
+            .manufacturer("Evil Corp")
+            .product("go-desecrator")
+            .serial_number("N#-of-the-BEST")]) // not beast :)
+        // only fails for more languages than usb allows, this is one
+        .expect("one set of strings")
+        .device_class(USB_CLASS_CDC) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    critical_section::with(|cs| {
+        USB_SERIAL.borrow(cs).replace(Some(UsbSerial {
+            device,
+            serial,
+            line: LineBuffer::new(),
+        }));
+    });
+
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::USBCTRL_IRQ);
+    }
+}
+
+/// A host is attached and has the serial port open.
+pub fn is_connected() -> bool {
+    CONNECTED.load(Ordering::Relaxed)
+}
+
+/// Best-effort write: never blocks, bytes that do not fit are dropped.
+/// Returns how many bytes were queued. Safe to call from any context.
+pub fn write(bytes: &[u8]) -> usize {
+    if !is_connected() {
+        return 0;
+    }
+
+    let written = critical_section::with(|cs| {
+        let mut tx = TX.borrow(cs).borrow_mut();
+        bytes
+            .iter()
+            .take_while(|byte| tx.push_back(**byte).is_ok())
+            .count()
+    });
+
+    // the interrupt does the actual sending
+    pac::NVIC::pend(pac::Interrupt::USBCTRL_IRQ);
+
+    written
+}
+
+pub fn write_str(text: &str) -> usize {
+    write(text.as_bytes())
+}
+
+fn flush(usb: &mut UsbSerial, tx: &mut Deque<u8, TX_CAPACITY>) {
+    while !tx.is_empty() {
+        let (front, _) = tx.as_slices();
+        match usb.serial.write(front) {
+            Ok(count) => {
+                for _ in 0..count {
+                    tx.pop_front();
+                }
+            }
+            // the endpoint is busy, the next interrupt picks up the rest
+            Err(_) => break,
+        }
+    }
+}
+
+#[interrupt]
+fn USBCTRL_IRQ() {
+    critical_section::with(|cs| {
+        let mut usb_serial = USB_SERIAL.borrow(cs).borrow_mut();
+        let Some(usb) = usb_serial.as_mut() else {
+            return;
+        };
+
+        if usb.device.poll(&mut [&mut usb.serial]) {
+            let mut buf = [0u8; 64];
+            let count = usb.serial.read(&mut buf).unwrap_or(0);
+
+            for byte in &buf[..count] {
+                match byte {
+                    b'\r' | b'\n' => {
+                        write_str("\r\n");
+                        match usb.line.finish() {
+                            // dropped whole, a cut line could still parse
+                            Err(TooLong) => shell::too_long(),
+                            Ok(line) => match core::str::from_utf8(&line) {
+                                Ok("") => {}
+                                // commands write through TX, not through usb
+                                Ok(line) => shell::execute(line),
+                                Err(_) => {
+                                    write_str("not utf-8\r\n");
+                                }
+                            },
+                        }
+                        write_str(shell::PROMPT);
+                    }
+                    // backspace and delete
+                    0x08 | 0x7f => {
+                        if usb.line.backspace() {
+                            write_str("\x08 \x08");
+                        }
+                    }
+                    // past the end nothing is echoed, the line is dropped
+                    byte => {
+                        if usb.line.push(*byte) {
+                            write(&[*byte]);
+                        }
+                    }
+                }
+            }
+        }
+
+        let connected = usb.device.state() == UsbDeviceState::Configured && usb.serial.dtr();
+        if connected && !CONNECTED.load(Ordering::Relaxed) {
+            shell::connected();
+        }
+        CONNECTED.store(connected, Ordering::Relaxed);
+
+        if connected {
+            flush(usb, &mut TX.borrow(cs).borrow_mut());
+        } else {
+            // nobody listening, stale output would only confuse the next host
+            TX.borrow(cs).borrow_mut().clear();
+        }
+    });
+}